@@ -1,15 +1,19 @@
-use std::collections::{HashMap, HashSet};
-use world::{ChunkId, CHUNK_SIZE_F};
+use std::collections::HashMap;
+use world::{lod::Lod, ChunkId, CHUNK_SIZE_F};
 
 pub(crate) enum ChunkAction {
     Load(ChunkId),
     Unload(ChunkId),
+    /// The chunk is still loaded, but needs a mesh at a different level of detail.
+    ChangeLod(ChunkId),
 }
 
 pub(crate) struct ChunkTracker {
     load_distance: f32,
     unload_distance: f32,
-    loaded: HashSet<ChunkId>,
+    /// Distances beyond which chunks use the next coarser level of detail
+    lod_distances: Vec<f32>,
+    loaded: HashMap<ChunkId, Lod>,
     center: glm::Vec3,
 }
 
@@ -18,6 +22,7 @@ impl ChunkTracker {
         Self {
             load_distance,
             unload_distance,
+            lod_distances: vec![],
             loaded: Default::default(),
             center: Default::default(),
         }
@@ -33,6 +38,18 @@ impl ChunkTracker {
         self.get_needed_actions()
     }
 
+    pub(crate) fn set_lod_distances(&mut self, lod_distances: Vec<f32>) -> Vec<ChunkAction> {
+        self.lod_distances = lod_distances;
+        self.get_needed_actions()
+    }
+
+    /**
+     * Level of detail of a loaded chunk, full detail for chunks that are not tracked.
+     */
+    pub(crate) fn lod(&self, id: &ChunkId) -> Lod {
+        self.loaded.get(id).copied().unwrap_or(Lod::FULL)
+    }
+
     pub(crate) fn set_center(&mut self, center: glm::Vec3) -> Vec<ChunkAction> {
         self.center = center;
         self.get_needed_actions()
@@ -43,10 +60,16 @@ impl ChunkTracker {
 
         self.add_load_actions(&mut actions);
         self.add_unload_actions(&mut actions);
+        self.add_lod_actions(&mut actions);
 
         actions
     }
 
+    fn lod_for(&self, id: &ChunkId) -> Lod {
+        let distance = glm::distance(&self.center, &id.center());
+        Lod::for_distance(distance, &self.lod_distances)
+    }
+
     fn add_load_actions(&mut self, actions: &mut Vec<ChunkAction>) {
         let start = glm::IVec3::new(
             ((self.center.x - self.load_distance) / CHUNK_SIZE_F).floor() as i32,
@@ -69,7 +92,7 @@ impl ChunkTracker {
                 for x in start.x..end.x {
                     let candidate = ChunkId::new(x, y, z);
 
-                    if self.loaded.contains(&candidate) {
+                    if self.loaded.contains_key(&candidate) {
                         continue;
                     }
 
@@ -86,7 +109,7 @@ impl ChunkTracker {
         ids_to_load.sort_unstable_by(|a, b| distances[a].total_cmp(&distances[b]));
 
         for id in ids_to_load {
-            self.loaded.insert(id);
+            self.loaded.insert(id, self.lod_for(&id));
             actions.push(ChunkAction::Load(id))
         }
     }
@@ -94,7 +117,7 @@ impl ChunkTracker {
     fn add_unload_actions(&mut self, actions: &mut Vec<ChunkAction>) {
         let ids_to_remove = self
             .loaded
-            .iter()
+            .keys()
             .filter(|chunk| {
                 glm::distance2(&chunk.center(), &self.center) > self.unload_distance.powi(2)
             })
@@ -106,15 +129,29 @@ impl ChunkTracker {
             actions.push(ChunkAction::Unload(id))
         }
     }
+
+    fn add_lod_actions(&mut self, actions: &mut Vec<ChunkAction>) {
+        let changed = self
+            .loaded
+            .iter()
+            .filter(|(id, lod)| self.lod_for(id) != **lod)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        for id in changed {
+            self.loaded.insert(id, self.lod_for(&id));
+            actions.push(ChunkAction::ChangeLod(id))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use test::Bencher;
-    use world::{CHUNK_SIZE_F, HALF_CHUNK_F};
+    use world::{lod::Lod, ChunkId, CHUNK_SIZE_F, HALF_CHUNK_F};
 
-    use crate::chunk_stream::ChunkTracker;
+    use crate::chunk_stream::{ChunkAction, ChunkTracker};
 
     fn test_full_load(center: glm::Vec3, load_distance: f32, expected_chunks: usize) {
         let mut stream = ChunkTracker::new(load_distance, f32::MAX);
@@ -138,6 +175,25 @@ mod tests {
         test_full_load(center, CHUNK_SIZE_F * 1.75, 27);
     }
 
+    #[test]
+    fn changes_lod_when_moving() {
+        let mut stream = ChunkTracker::new(CHUNK_SIZE_F * 4.0, f32::MAX);
+        stream.set_lod_distances(vec![CHUNK_SIZE_F * 1.5, CHUNK_SIZE_F * 2.5]);
+        stream.set_center(glm::vec3(32.0, 32.0, 32.0));
+
+        let far = ChunkId::new(3, 0, 0);
+        assert_eq!(stream.lod(&ChunkId::new(0, 0, 0)), Lod::FULL);
+        assert_eq!(stream.lod(&ChunkId::new(2, 0, 0)), Lod::new(1));
+        assert_eq!(stream.lod(&far), Lod::new(2));
+
+        let actions = stream.set_center(glm::vec3(32.0 + CHUNK_SIZE_F * 2.0, 32.0, 32.0));
+        assert!(actions
+            .iter()
+            .any(|action| matches!(action, ChunkAction::ChangeLod(id) if *id == far)));
+        assert_eq!(stream.lod(&far), Lod::FULL);
+        assert_eq!(stream.lod(&ChunkId::new(0, 0, 0)), Lod::new(1));
+    }
+
     //   fn test_additive_load(center: &glm::Vec3, load_distance: f32, loaded_chunks:  expected_new_chunks: usize) {
     //     let mut stream = ChunkStream::new(load_distance, f32::MAX);
     //     let actions = stream.update(center);
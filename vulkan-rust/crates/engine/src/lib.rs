@@ -12,12 +12,13 @@ use crate::{
     world_thread::{MeshEvent, Request},
 };
 use anyhow::Result;
-use gamedata::material::Material;
+use gamedata::material::{Material, RenderLayer};
 use geometry::Ray;
-use graphics::{camera::FlyingCamera, Mesh};
+use graphics::{camera::FlyingCamera, ChunkMesh};
 use logging::{log, LOG_ENGINE};
 use std::{
     collections::{BTreeMap, VecDeque},
+    path::PathBuf,
     sync::mpsc,
     thread,
     time::{Duration, Instant},
@@ -35,7 +36,11 @@ use winit::{
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
-use world::{ChunkData, ChunkId, MeshId, WorldPosition, CHUNK_SIZE_F};
+use world::{
+    gen::GENERATOR_VERSION,
+    storage::{WorldDir, WorldMeta},
+    Brush, ChunkData, ChunkId, MeshId, WorldGenConfig, WorldPosition, WorldSeed, CHUNK_SIZE_F,
+};
 
 mod chunk_stream;
 pub mod models;
@@ -47,12 +52,14 @@ mod world_thread;
 #[derive(Clone)]
 struct ChunkUpdate(ChunkId, ChunkData, Instant);
 
-const INITIAL_LOAD_DISTANCE: f32 = CHUNK_SIZE_F * 5.0;
-const INITIAL_UNLOAD_DISTANCE: f32 = CHUNK_SIZE_F * 6.0;
 const PLAYER_BUILDING_REACH: f32 = 10.0;
+const PLAYER_BRUSH_RADIUS: f32 = 3.0;
 const STAT_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct Engine {
+    world_dir: WorldDir,
+    meta: WorldMeta,
+    gen_config: WorldGenConfig,
     camera: FlyingCamera,
     meshes: BTreeMap<MeshId, usize>,
     deletion_queue: VecDeque<Delete>,
@@ -64,13 +71,41 @@ pub struct BlockUpdate {
 }
 
 impl Engine {
-    pub fn create() -> Self {
+    /**
+     * Opens the world at the given path or creates it with the given or a random seed.
+     */
+    pub fn create(world_path: impl Into<PathBuf>, seed: Option<WorldSeed>) -> Result<Self> {
         log!(*LOG_ENGINE, "Creating engine");
-        Self {
-            camera: FlyingCamera::new(glm::vec3(0., 0., 64.)),
+        let world_dir = WorldDir::new(world_path);
+        let meta = world_dir.load_or_create(seed)?;
+        log!(
+            *LOG_ENGINE,
+            "Opened world {} with seed {}",
+            world_dir.path().display(),
+            meta.seed
+        );
+        let gen_config = world_dir.read_gen_config()?;
+        if meta.generator_version != GENERATOR_VERSION {
+            log!(
+                *LOG_ENGINE,
+                "[WARN] World was generated with generator version {}, current is {}",
+                meta.generator_version,
+                GENERATOR_VERSION
+            );
+        }
+
+        let mut camera = FlyingCamera::new(glm::Vec3::from(meta.player.position));
+        camera.cam.pitch = meta.player.pitch;
+        camera.cam.yaw = meta.player.yaw;
+
+        Ok(Self {
+            world_dir,
+            meta,
+            gen_config,
+            camera,
             meshes: BTreeMap::new(),
             deletion_queue: Default::default(),
-        }
+        })
     }
 
     pub fn run(mut self) -> Result<()> {
@@ -78,13 +113,28 @@ impl Engine {
         pretty_env_logger::init();
 
         log!(*LOG_ENGINE, "Setting up world thread");
-        let (_world_thread, world_requests, world_events) = world_thread::spawn();
+        let (_world_thread, world_requests, world_events) = world_thread::spawn(
+            self.world_dir.clone(),
+            self.meta.world_seed(),
+            self.gen_config.clone(),
+        );
         world_requests
             .send(Request::SetRenderDistance(
-                INITIAL_LOAD_DISTANCE,
-                INITIAL_UNLOAD_DISTANCE,
+                self.meta.render.load_distance,
+                self.meta.render.unload_distance,
+            ))
+            .expect("World Thread must be available");
+        world_requests
+            .send(Request::SetLodDistances(
+                self.meta.render.lod_distances.clone(),
             ))
             .expect("World Thread must be available");
+        world_requests
+            .send(Request::SetMesher(self.meta.render.mesher))
+            .expect("World Thread must be available");
+        world_requests
+            .send(Request::SetHistoryDepth(self.meta.edit.history_depth))
+            .expect("World Thread must be available");
 
         log!(*LOG_ENGINE, "Setting up window");
         let event_loop = EventLoop::new();
@@ -110,10 +160,11 @@ impl Engine {
         let mut focused = false;
         let mut grabbed = false;
         let mut cursor_visible = false;
+        let mut control_pressed = false;
         // Game state
-        let mut godmode = false;
-        let mut load_distance = INITIAL_LOAD_DISTANCE;
-        let mut unload_distance = INITIAL_UNLOAD_DISTANCE;
+        let mut godmode = self.meta.player.godmode;
+        let mut load_distance = self.meta.render.load_distance;
+        let mut unload_distance = self.meta.render.unload_distance;
 
         let start = Instant::now();
         let mut stats = Stats::new();
@@ -228,6 +279,11 @@ impl Engine {
                     }
                 }
                 WindowEvent::CloseRequested => {
+                    self.meta.player.godmode = godmode;
+                    self.meta.render.load_distance = load_distance;
+                    self.meta.render.unload_distance = unload_distance;
+                    self.save_meta();
+
                     destroying = true;
                     *control_flow = ControlFlow::Exit;
                     world_requests
@@ -301,6 +357,12 @@ impl Engine {
                     virtual_keycode,
                     ..
                 }) => {
+                    if let Some(VirtualKeyCode::LControl | VirtualKeyCode::RControl) =
+                        virtual_keycode
+                    {
+                        control_pressed = state == ElementState::Pressed;
+                    }
+
                     if state == ElementState::Pressed {
                         match virtual_keycode {
                             Some(VirtualKeyCode::Escape) => {
@@ -313,6 +375,42 @@ impl Engine {
                             Some(VirtualKeyCode::F1) => {
                                 unsafe { app.recreate_swapchain(&window) }.unwrap();
                             }
+                            Some(VirtualKeyCode::Z) if control_pressed => {
+                                world_requests
+                                    .send(Request::Undo)
+                                    .expect("World Thread must be available");
+                            }
+                            Some(VirtualKeyCode::Y) if control_pressed => {
+                                world_requests
+                                    .send(Request::Redo)
+                                    .expect("World Thread must be available");
+                            }
+                            Some(VirtualKeyCode::F) => {
+                                world_requests
+                                    .send(Request::Fill {
+                                        ray: Ray::new(
+                                            self.camera.cam.position,
+                                            self.camera.cam.direction().normalize(),
+                                        ),
+                                        range: PLAYER_BUILDING_REACH,
+                                        brush: player_brush(),
+                                        material: Material::Debug,
+                                    })
+                                    .expect("World Thread must be available");
+                            }
+                            Some(VirtualKeyCode::R) => {
+                                world_requests
+                                    .send(Request::Replace {
+                                        ray: Ray::new(
+                                            self.camera.cam.position,
+                                            self.camera.cam.direction().normalize(),
+                                        ),
+                                        range: PLAYER_BUILDING_REACH,
+                                        brush: player_brush(),
+                                        to: Material::Debug,
+                                    })
+                                    .expect("World Thread must be available");
+                            }
                             Some(VirtualKeyCode::Tab) => {
                                 godmode = !godmode;
                             }
@@ -352,6 +450,18 @@ impl Engine {
         })
     }
 
+    fn save_meta(&mut self) {
+        let position = self.camera.cam.position;
+        self.meta.player.position = [position.x, position.y, position.z];
+        self.meta.player.pitch = self.camera.cam.pitch;
+        self.meta.player.yaw = self.camera.cam.yaw;
+
+        match self.world_dir.write_meta(&self.meta) {
+            Ok(()) => log!(*LOG_ENGINE, "Saved world metadata"),
+            Err(e) => log!(*LOG_ENGINE, "[WARN] Failed to save world metadata: {}", e),
+        }
+    }
+
     fn drain_deletion_queue(&mut self, app: &mut App) {
         let mut count = 0;
         while let Some(delete) = self.deletion_queue.pop_front() {
@@ -374,30 +484,32 @@ impl Engine {
         world_events: &mpsc::Receiver<MeshEvent>,
         app: &mut App,
         current_frame_start: &Instant,
-        prepare_meshes_tx: mpsc::Sender<(MeshId, Mesh)>,
+        prepare_meshes_tx: mpsc::Sender<(MeshId, ChunkMesh)>,
     ) {
         const ALLOWED_FRAME_TIME: Duration = Duration::from_millis(1);
 
         while let Ok(mesh_event) = world_events.try_recv() {
             match mesh_event {
-                MeshEvent::Add(id, (opaque_mesh, transparent_mesh)) => {
-                    if let Some(mesh) = opaque_mesh {
-                        prepare_meshes_tx.send((MeshId::Opaque(id), mesh)).unwrap();
-                        // self.meshes.insert(mesh_id, mesh.indices.len()); // TODO: move to later
-                    }
-                    if let Some(mesh) = transparent_mesh {
-                        prepare_meshes_tx
-                            .send((MeshId::Transparent(id), mesh))
-                            .unwrap();
-                        // self.meshes.insert(mesh_id, mesh.indices.len()); // TODO: move to later
+                MeshEvent::Add(id, layer_meshes) => {
+                    for (layer, mesh) in RenderLayer::ALL.into_iter().zip(layer_meshes) {
+                        let mesh_id = MeshId::new(id, layer);
+                        match mesh {
+                            Some(mesh) => prepare_meshes_tx.send((mesh_id, mesh)).unwrap(),
+                            // the layer lost all of its faces
+                            None => {
+                                if self.meshes.remove(&mesh_id).is_some() {
+                                    self.deletion_queue.push_back(Delete::Mesh(mesh_id));
+                                }
+                            }
+                        }
                     }
                 }
                 MeshEvent::Remove(id) => {
-                    let mesh_ids = [MeshId::Opaque(id), MeshId::Transparent(id)];
-                    self.deletion_queue
-                        .extend(mesh_ids.map(|id| Delete::Mesh(id)));
-                    self.meshes.remove(&mesh_ids[0]);
-                    self.meshes.remove(&mesh_ids[1]);
+                    for layer in RenderLayer::ALL {
+                        let mesh_id = MeshId::new(id, layer);
+                        self.deletion_queue.push_back(Delete::Mesh(mesh_id));
+                        self.meshes.remove(&mesh_id);
+                    }
                 }
             }
             if current_frame_start.elapsed() > ALLOWED_FRAME_TIME {
@@ -475,6 +587,16 @@ impl Engine {
     }
 }
 
+/**
+ * Brush of the fill and replace keys, relative to the targeted block.
+ */
+fn player_brush() -> Brush {
+    Brush::Sphere {
+        center: WorldPosition::new(0, 0, 0),
+        radius: PLAYER_BRUSH_RADIUS,
+    }
+}
+
 fn set_focus(
     new_focus: bool,
     window: &winit::window::Window,
@@ -1,10 +1,11 @@
 use crate::chunk_stream::{ChunkAction, ChunkTracker};
-use gamedata::material::Material;
+use gamedata::material::{Material, RenderLayer};
 use geometry::Ray;
-use graphics::Mesh;
+use graphics::ChunkMesh;
 use logging::{log, LOG_WORLD};
 use rayon::prelude::*;
 use std::{
+    borrow::Cow,
     collections::HashSet,
     sync::mpsc,
     thread,
@@ -12,15 +13,26 @@ use std::{
 };
 use threadpool::ThreadPool;
 use world::{
-    gen::chunk::{compress, Chunk},
-    mesh_generator::{generate_greedy_mesh, generate_greedy_mesh_water},
+    binary_mesh::{generate_binary_mesh, generate_lit_binary_mesh},
+    fluid::FluidSimulation,
+    gravity,
+    light::{self, Light, SkyBoundary},
+    lod::{self, Lod},
+    mesh_generator::{generate_layer_mesh, generate_lit_layer_mesh, Mesher},
+    neighborhood::ChunkNeighborhood,
+    octree_mesh::{expand_non_opaque, generate_lit_octree_mesh, generate_octree_mesh},
     slice::CubeSlice,
-    traits::{Data3D, Generate, Voxelize},
-    ChunkId, ChunkSeed, Raycast, World, WorldPosition, WorldSeed, CHUNK_SIZE, CHUNK_SIZE_I,
-    CHUNK_SIZE_SAFE,
+    storage::{fluids as fluid_levels, journal, RegionStore, WorldDir},
+    surface_nets::{generate_lit_surface_nets_mesh, generate_surface_nets_mesh},
+    traits::Data3D,
+    Brush, ChunkData, ChunkId, Raycast, World, WorldGenConfig, WorldPosition, WorldSeed,
+    CHUNK_SIZE, CHUNK_SIZE_SAFE,
 };
 
 const REMESH_INTERVAL: Duration = Duration::from_millis(100);
+const FLUID_TICK_BUDGET: usize = 2048;
+/// Chunks with more journaled edits than this are stored whole in their region
+const JOURNAL_CHUNK_LIMIT: usize = 4096;
 
 pub(crate) enum ModifyAction {
     Remove,
@@ -30,20 +42,46 @@ pub(crate) enum ModifyAction {
 pub(crate) enum Request {
     Move(glm::Vec3),
     SetRenderDistance(f32, f32),
+    SetLodDistances(Vec<f32>),
+    SetMesher(Mesher),
+    SetHistoryDepth(usize),
     Modify {
         ray: Ray,
         range: f32,
         action: ModifyAction,
     },
+    /// Fills the brush placed relative to the block in front of the targeted one
+    Fill {
+        ray: Ray,
+        range: f32,
+        brush: Brush,
+        material: Material,
+    },
+    /// Replaces the material of the targeted block inside the brush placed relative to it
+    Replace {
+        ray: Ray,
+        range: f32,
+        brush: Brush,
+        to: Material,
+    },
+    Undo,
+    Redo,
     Exit,
 }
 
+/// Meshes of a chunk indexed by render layer, None for layers without faces
+pub(crate) type LayerMeshes = [Option<ChunkMesh>; RenderLayer::ALL.len()];
+
 pub(crate) enum MeshEvent {
-    Add(ChunkId, (Option<Mesh>, Option<Mesh>)),
+    Add(ChunkId, LayerMeshes),
     Remove(ChunkId),
 }
 
-pub(crate) fn spawn() -> (
+pub(crate) fn spawn(
+    world_dir: WorldDir,
+    seed: WorldSeed,
+    gen_config: WorldGenConfig,
+) -> (
     thread::JoinHandle<()>,
     mpsc::Sender<Request>,
     mpsc::Receiver<MeshEvent>,
@@ -55,11 +93,31 @@ pub(crate) fn spawn() -> (
         .spawn(move || {
             log!(*LOG_WORLD, "World Thread started");
 
-            let seed = WorldSeed::new(17);
-            let mut world = World::new(seed.clone());
+            let mut world = World::with_config(seed, gen_config);
+            let store = RegionStore::new(world_dir.regions_path())
+                .expect("Region directory must be available");
+            let journal_path = world_dir.journal_path();
+            match journal::read(&journal_path) {
+                Ok(journal) => {
+                    log!(
+                        *LOG_WORLD,
+                        "Loaded {} edits in {} chunks",
+                        journal.edit_count(),
+                        journal.chunk_count()
+                    );
+                    world.chunk_manager.set_journal(journal);
+                }
+                Err(e) => log!(*LOG_WORLD, "[WARN] Failed to load edits: {}", e),
+            }
             let mut dirty_chunks = HashSet::default();
-            let mut overflow = Vec::new();
+            let fluids_path = world_dir.fluids_path();
+            let levels = fluid_levels::read(&fluids_path).unwrap_or_else(|e| {
+                log!(*LOG_WORLD, "[WARN] Failed to load water levels: {}", e);
+                Default::default()
+            });
+            let mut fluids = FluidSimulation::with_levels(FLUID_TICK_BUDGET, levels);
             let mut chunk_stream = ChunkTracker::new(0.0, 0.0);
+            let mut mesher = Mesher::default();
             let thread_pool = ThreadPool::new("meshing_thread", 8);
             let mut next_remesh = Instant::now();
 
@@ -73,15 +131,40 @@ pub(crate) fn spawn() -> (
                                 "Player moved, {} chunk actions needed",
                                 actions.len()
                             );
+                            update_fluids(&actions, &mut fluids);
                             process_chunk_actions(
                                 actions,
                                 &mut world,
+                                &store,
                                 &out_tx,
                                 &thread_pool,
                                 &mut dirty_chunks,
-                                &mut overflow,
                             );
                         }
+                        Request::SetLodDistances(lod_distances) => {
+                            let actions = chunk_stream.set_lod_distances(lod_distances);
+                            log!(
+                                *LOG_WORLD,
+                                "Level of detail distances updated, {} chunk actions needed",
+                                actions.len()
+                            );
+                            process_chunk_actions(
+                                actions,
+                                &mut world,
+                                &store,
+                                &out_tx,
+                                &thread_pool,
+                                &mut dirty_chunks,
+                            );
+                        }
+                        Request::SetMesher(new_mesher) => {
+                            log!(*LOG_WORLD, "Switching to {:?} meshing", new_mesher);
+                            mesher = new_mesher;
+                            dirty_chunks.extend(world.chunk_manager.ids());
+                        }
+                        Request::SetHistoryDepth(depth) => {
+                            world.chunk_manager.set_history_depth(depth);
+                        }
                         Request::SetRenderDistance(load_distance, unload_distance) => {
                             let actions =
                                 chunk_stream.set_distances(load_distance, unload_distance);
@@ -90,81 +173,142 @@ pub(crate) fn spawn() -> (
                                 "Render distance updated, {} chunk actions needed",
                                 actions.len()
                             );
+                            update_fluids(&actions, &mut fluids);
                             process_chunk_actions(
                                 actions,
                                 &mut world,
+                                &store,
                                 &out_tx,
                                 &thread_pool,
                                 &mut dirty_chunks,
-                                &mut overflow,
                             );
                         }
                         Request::Modify { ray, range, action } => {
                             log!(*LOG_WORLD, "Player attempts modification");
-                            if let Some(distance) = world.cast_ray(&ray, &(0.0..range)) {
-                                let (correction, material) = match action {
-                                    ModifyAction::Remove => (0.01, Material::Unset),
-                                    ModifyAction::Place(material) => (-0.01, material),
-                                };
-                                let hit = ray.point_on_ray(distance + correction);
-                                let position = WorldPosition::from(&hit);
-                                let chunk_id = ChunkId::from(&position);
-                                let data = world
+                            let (correction, material) = match action {
+                                ModifyAction::Remove => (0.01, Material::Unset),
+                                ModifyAction::Place(material) => (-0.01, material),
+                            };
+                            if let Some(position) = target(&world, &ray, range, correction) {
+                                world.chunk_manager.begin_transaction();
+                                world
                                     .chunk_manager
-                                    .get_mut(&chunk_id)
+                                    .edit_block(position.x, position.y, position.z, material)
                                     .expect("Should be impossible to modify unloaded chunk");
-                                let position_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
-                                data.set(
-                                    position_in_chunk.x as usize,
-                                    position_in_chunk.y as usize,
-                                    position_in_chunk.z as usize,
-                                    material,
-                                );
-
-                                let adjecent = chunk_id.get_adjecent();
-                                dirty_chunks.insert(chunk_id);
-
-                                if position_in_chunk.x == 0 {
-                                    if world.chunk_manager.get(&adjecent[0]).is_some() {
-                                        dirty_chunks.insert(adjecent[0]);
-                                    }
-                                } else if position_in_chunk.x == CHUNK_SIZE_I - 1 {
-                                    if world.chunk_manager.get(&adjecent[1]).is_some() {
-                                        dirty_chunks.insert(adjecent[1]);
-                                    }
-                                }
-
-                                if position_in_chunk.y == 0 {
-                                    if world.chunk_manager.get(&adjecent[2]).is_some() {
-                                        dirty_chunks.insert(adjecent[2]);
-                                    }
-                                } else if position_in_chunk.y == CHUNK_SIZE_I - 1 {
-                                    if world.chunk_manager.get(&adjecent[3]).is_some() {
-                                        dirty_chunks.insert(adjecent[3]);
-                                    }
-                                }
-
-                                if position_in_chunk.z == 0 {
-                                    if world.chunk_manager.get(&adjecent[4]).is_some() {
-                                        dirty_chunks.insert(adjecent[4]);
-                                    }
-                                } else if position_in_chunk.z == CHUNK_SIZE_I - 1 {
-                                    if world.chunk_manager.get(&adjecent[5]).is_some() {
-                                        dirty_chunks.insert(adjecent[5]);
-                                    }
-                                }
+                                dirty_chunks
+                                    .extend(world.chunk_manager.chunks_to_remesh(&position));
+                                dirty_chunks
+                                    .extend(gravity::settle(&mut world.chunk_manager, &position));
+                                world.chunk_manager.commit_transaction();
+                                fluids.schedule_around(&position);
                             }
 
                             break 'recv;
                         }
+                        Request::Fill {
+                            ray,
+                            range,
+                            brush,
+                            material,
+                        } => {
+                            let Some(position) = target(&world, &ray, range, -0.01) else {
+                                break 'recv;
+                            };
+                            let brush = brush.offset(&position);
+                            world.chunk_manager.begin_transaction();
+                            let dirty = world.chunk_manager.fill(&brush, material);
+                            log!(*LOG_WORLD, "Filled volume, {} chunks dirty", dirty.len());
+                            dirty_chunks.extend(dirty);
+                            let mut positions = vec![];
+                            brush.for_each_position(|p| positions.push(p));
+                            dirty_chunks
+                                .extend(gravity::settle_all(&mut world.chunk_manager, &positions));
+                            world.chunk_manager.commit_transaction();
+                            positions.iter().for_each(|p| fluids.schedule_around(p));
+
+                            break 'recv;
+                        }
+                        Request::Replace {
+                            ray,
+                            range,
+                            brush,
+                            to,
+                        } => {
+                            let Some(position) = target(&world, &ray, range, 0.01) else {
+                                break 'recv;
+                            };
+                            let Some(from) = world.chunk_manager.get_block(&position) else {
+                                break 'recv;
+                            };
+                            let brush = brush.offset(&position);
+                            world.chunk_manager.begin_transaction();
+                            let dirty = world.chunk_manager.replace(&brush, from, to);
+                            log!(
+                                *LOG_WORLD,
+                                "Replaced in volume, {} chunks dirty",
+                                dirty.len()
+                            );
+                            dirty_chunks.extend(dirty);
+                            let mut positions = vec![];
+                            brush.for_each_position(|p| positions.push(p));
+                            dirty_chunks
+                                .extend(gravity::settle_all(&mut world.chunk_manager, &positions));
+                            world.chunk_manager.commit_transaction();
+                            positions.iter().for_each(|p| fluids.schedule_around(p));
+
+                            break 'recv;
+                        }
+                        Request::Undo => {
+                            let dirty = world.chunk_manager.undo();
+                            log!(*LOG_WORLD, "Undo, {} chunks dirty", dirty.len());
+                            dirty_chunks.extend(dirty);
+                            // the step was settled when it was made, so nothing falls here
+                            let positions = match world.chunk_manager.history().peek_redo() {
+                                Some(transaction) => transaction.positions().cloned().collect(),
+                                None => vec![],
+                            };
+                            positions.iter().for_each(|p| fluids.schedule_around(p));
+
+                            break 'recv;
+                        }
+                        Request::Redo => {
+                            let dirty = world.chunk_manager.redo();
+                            log!(*LOG_WORLD, "Redo, {} chunks dirty", dirty.len());
+                            dirty_chunks.extend(dirty);
+                            // the step was settled when it was made, so nothing falls here
+                            let positions = match world.chunk_manager.history().peek_undo() {
+                                Some(transaction) => transaction.positions().cloned().collect(),
+                                None => vec![],
+                            };
+                            positions.iter().for_each(|p| fluids.schedule_around(p));
+
+                            break 'recv;
+                        }
                         Request::Exit => break 'thread,
                     }
                 }
 
-                mesh_dirty_chunks(&mut dirty_chunks, &world, &out_tx);
+                dirty_chunks.extend(fluids.tick(&mut world.chunk_manager));
+                dirty_chunks.extend(world.chunk_manager.take_relit_chunks());
+                mesh_dirty_chunks(&mut dirty_chunks, &world, &chunk_stream, mesher, &out_tx);
                 next_remesh += REMESH_INTERVAL;
             }
 
+            let ids = world.chunk_manager.ids().copied().collect::<Vec<_>>();
+            store_large_deltas(&mut world, &store, &ids);
+            match journal::write(&journal_path, world.chunk_manager.journal()) {
+                Ok(()) => log!(
+                    *LOG_WORLD,
+                    "Saved {} edits",
+                    world.chunk_manager.journal().edit_count()
+                ),
+                Err(e) => log!(*LOG_WORLD, "[WARN] Failed to save edits: {}", e),
+            }
+
+            if let Err(e) = fluid_levels::write(&fluids_path, fluids.levels()) {
+                log!(*LOG_WORLD, "[WARN] Failed to save water levels: {}", e);
+            }
+
             log!(*LOG_WORLD, "World Thread exited");
         })
         .expect("World Thread is mandatory");
@@ -175,6 +319,8 @@ pub(crate) fn spawn() -> (
 fn mesh_dirty_chunks(
     dirty_chunks: &mut HashSet<ChunkId>,
     world: &World,
+    chunk_stream: &ChunkTracker,
+    mesher: Mesher,
     out_tx: &mpsc::Sender<MeshEvent>,
 ) {
     if !dirty_chunks.is_empty() {
@@ -184,7 +330,7 @@ fn mesh_dirty_chunks(
     dirty_chunks
         .par_drain()
         .filter_map(|id| match world.chunk_manager.get(&id) {
-            Some(_) => Some(MeshEvent::Add(id, remesh(&id, &world))),
+            Some(_) => Some(MeshEvent::Add(id, remesh(&id, world, chunk_stream, mesher))),
             None => Some(MeshEvent::Remove(id)),
         })
         .collect::<Vec<_>>()
@@ -194,184 +340,270 @@ fn mesh_dirty_chunks(
         })
 }
 
+fn update_fluids(actions: &[ChunkAction], fluids: &mut FluidSimulation) {
+    for action in actions {
+        match action {
+            ChunkAction::Load(id) => fluids.load(id),
+            ChunkAction::Unload(id) => fluids.unload(id),
+            ChunkAction::ChangeLod(_) => {}
+        }
+    }
+}
+
 fn process_chunk_actions(
     actions: Vec<ChunkAction>,
     world: &mut World,
+    store: &RegionStore,
     out_tx: &mpsc::Sender<MeshEvent>,
     thread_pool: &ThreadPool,
     dirty_chunks: &mut HashSet<ChunkId>,
-    overflow_set: &mut Vec<(i32, i32, i32, Material)>,
 ) {
+    // neighbors read a changed chunk in their padding
+    for action in &actions {
+        if let ChunkAction::ChangeLod(id) = action {
+            dirty_chunks.insert(*id);
+            ChunkNeighborhood::dependents(id)
+                .filter(|id| world.chunk_manager.get(id).is_some())
+                .for_each(|id| {
+                    dirty_chunks.insert(id);
+                });
+        }
+    }
+
     let outcomes = actions
         .into_par_iter()
-        .map(|action| match action {
+        .filter_map(|action| match action {
             ChunkAction::Load(id) => {
-                let seed = ChunkSeed::new(&world.seed, &id);
-                let id = *seed.id();
-                let chunk = Chunk::generate(seed);
-                let generated_chunk = chunk.voxelize();
-                let chunk_data = compress(&generated_chunk.voxels);
-                (id, Some(chunk_data), Some(generated_chunk.overflow))
+                let mut chunk_data = match store.load(&id) {
+                    Ok(Some(chunk_data)) => chunk_data,
+                    result => {
+                        if let Err(e) = result {
+                            log!(*LOG_WORLD, "[WARN] Failed to load {:?}: {}", id, e);
+                        }
+
+                        world.generate_chunk(&id)
+                    }
+                };
+
+                // edits made after the chunk was stored
+                world.chunk_manager.journal().replay(&id, &mut chunk_data);
+
+                // chunks below the sea level are assumed to be covered until their neighbors arrive
+                let above = ChunkId::new(id.x, id.y, id.z + 1);
+                let sky = match world.chunk_manager.light(&above) {
+                    Some(light) => SkyBoundary::Chunk(light),
+                    None if WorldPosition::from(&id).z >= world.gen_config.sea_level => {
+                        SkyBoundary::Open
+                    }
+                    None => SkyBoundary::Dark,
+                };
+                let light = light::compute(&chunk_data, sky);
+                Some((id, Some((chunk_data, light))))
             }
-            ChunkAction::Unload(id) => (id, None, None),
+            ChunkAction::Unload(id) => Some((id, None)),
+            ChunkAction::ChangeLod(_) => None,
         })
         .collect::<Vec<_>>();
 
-    outcomes.into_iter().for_each(|(id, data, overflow)| {
+    outcomes.into_iter().for_each(|(id, data)| {
         dirty_chunks.insert(id);
 
         match data {
-            Some(data) => {
-                world.chunk_manager.insert(&id, data);
-                [
-                    ChunkId::new(id.x - 1, id.y, id.z),
-                    ChunkId::new(id.x, id.y - 1, id.z),
-                    ChunkId::new(id.x, id.y, id.z - 1),
-                ]
-                .into_iter()
-                .filter(|id| world.chunk_manager.get(id).is_some())
-                .for_each(|id| {
-                    dirty_chunks.insert(id);
-                });
+            Some((data, light)) => {
+                world.chunk_manager.insert_lit(&id, data, light);
+                ChunkNeighborhood::dependents(&id)
+                    .filter(|id| world.chunk_manager.get(id).is_some())
+                    .for_each(|id| {
+                        dirty_chunks.insert(id);
+                    });
+            }
+            None => {
+                store_large_deltas(world, store, &[id]);
+                world.chunk_manager.remove(&id);
             }
-            None => world.chunk_manager.remove(&id),
         }
+    });
+}
 
-        if let Some(overflow) = overflow {
-            overflow_set.extend(overflow);
+/**
+ * Block hit by the ray within range, moved along the ray by the correction to pick the
+ * block behind (positive) or in front of (negative) the hit face.
+ */
+fn target(world: &World, ray: &Ray, range: f32, correction: f32) -> Option<WorldPosition> {
+    let distance = world.cast_ray(ray, &(0.0..range))?;
+    Some(WorldPosition::from(
+        &ray.point_on_ray(distance + correction),
+    ))
+}
+
+fn store_large_deltas(world: &mut World, store: &RegionStore, ids: &[ChunkId]) {
+    match world
+        .chunk_manager
+        .store_large_deltas(store, ids, JOURNAL_CHUNK_LIMIT)
+    {
+        Ok(0) => {}
+        Ok(count) => log!(*LOG_WORLD, "Stored {} chunks in regions", count),
+        Err(e) => log!(*LOG_WORLD, "[WARN] Failed to store chunks: {}", e),
+    }
+}
+
+/**
+ * Chunk data as meshed at a level of detail.
+ */
+fn at_lod(data: &ChunkData, lod: Lod) -> Cow<ChunkData> {
+    match lod.is_full() {
+        true => Cow::Borrowed(data),
+        false => Cow::Owned(lod::collapse(data, lod)),
+    }
+}
+
+fn remesh(id: &ChunkId, world: &World, chunk_stream: &ChunkTracker, mesher: Mesher) -> LayerMeshes {
+    let lod = chunk_stream.lod(id);
+    // borders are read at the level of the neighbor, so both sides agree on the seam
+    let Some(neighborhood) = ChunkNeighborhood::gather(id, |id| {
+        world
+            .chunk_manager
+            .get(id)
+            .map(|data| at_lod(data, chunk_stream.lod(id)))
+    }) else {
+        println!("WARN: attempted to mesh non-exitent chunk");
+        return Default::default();
+    };
+    let chunk_data = neighborhood.center();
+
+    // read old data
+    let mut blocks = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+    let mut contains_opaque_blocks = false;
+    let mut contains_invisible_blocks = false;
+    // non opaque layers are meshed from the chunk and its positive borders, faces toward the
+    // negative neighbors belong to those neighbors
+    let mut contains_layer = [false; RenderLayer::ALL.len()];
+    chunk_data.for_each_region(|_, _, material| {
+        contains_opaque_blocks |= material.is_opaque();
+        contains_invisible_blocks |= !material.is_opaque();
+        if let Some(layer) = material.render_layer() {
+            contains_layer[layer as usize] = true;
         }
     });
 
-    overflow_set.retain(
-        |(x, y, z, m)| match world.chunk_manager.set_block(*x, *y, *z, *m) {
-            Ok(id) => {
-                dirty_chunks.insert(id);
-                false
-            }
-            Err(_) => true,
-        },
-    )
-}
+    // borders on all sides, only the positive ones are meshed by this chunk
+    neighborhood.for_each_border(|[x, y, z], material, meshed| {
+        blocks.set(x, y, z, material);
+        if let Some(layer) = material.render_layer() {
+            contains_layer[layer as usize] |= meshed && layer != RenderLayer::Opaque;
+        }
+        if meshed {
+            contains_opaque_blocks |= material.is_opaque();
+            contains_invisible_blocks |= !material.is_opaque();
+        }
+    });
+    contains_layer[RenderLayer::Opaque as usize] =
+        contains_opaque_blocks && contains_invisible_blocks;
 
-fn remesh(id: &ChunkId, world: &World) -> (Option<Mesh>, Option<Mesh>) {
-    if let Some(chunk_data) = world.chunk_manager.get(id) {
-        // read old data
-        let mut blocks = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
-        let mut contains_opaque_blocks = false;
-        let mut contains_invisible_blocks = false;
+    if !contains_layer.contains(&true) {
+        // println!("No mesh for full or empty chunk");
+        return Default::default();
+    }
 
-        // center chunk
+    // the octree mesher reads the center directly, the other meshers need all of it expanded and
+    // the non opaque layers only the voxels in and next to their regions
+    if mesher != Mesher::Greedy {
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
                 for z in 0..CHUNK_SIZE {
-                    let material = chunk_data.get(x, y, z);
-                    blocks.set(x + 1, y + 1, z + 1, material);
-                    contains_opaque_blocks |= material.is_opaque();
-                    contains_invisible_blocks |= !material.is_opaque();
+                    blocks.set(x + 1, y + 1, z + 1, chunk_data.get(x, y, z));
                 }
             }
         }
+    } else {
+        expand_non_opaque(chunk_data, &mut blocks);
+    }
 
-        let adjecent = id.get_adjecent();
-
-        // x
-        // if let Some(adjecent) = world.chunk_manager.get(&adjecent[0]) {
-        //     for y in 0..CHUNK_SIZE {
-        //         for z in 0..CHUNK_SIZE {
-        //             let material = adjecent.get(CHUNK_SIZE - 1, y, z);
-        //             blocks.set(0, y + 1, z + 1, material);
-        //             contains_opaque_blocks |= material.is_opaque();
-        //             contains_invisible_blocks |= !material.is_opaque();
-        //         }
-        //     }
-        // }
-        if let Some(adjecent) = world.chunk_manager.get(&adjecent[1]) {
-            for y in 0..CHUNK_SIZE {
-                for z in 0..CHUNK_SIZE {
-                    let material = adjecent.get(0, y, z);
-                    blocks.set(CHUNK_SIZE_SAFE - 1, y + 1, z + 1, material);
-                    contains_opaque_blocks |= material.is_opaque();
-                    contains_invisible_blocks |= !material.is_opaque();
-                }
-            }
+    // remesh, coarse voxels take the light of the voxel in front of them, so they are lit by the sky
+    let light = light_slice(id, world);
+    let meshes = RenderLayer::ALL.map(|layer| {
+        if !contains_layer[layer as usize] {
+            return None;
         }
-
-        // y
-        // if let Some(adjecent) = world.chunk_manager.get(&adjecent[2]) {
-        //     for x in 0..CHUNK_SIZE {
-        //         for z in 0..CHUNK_SIZE {
-        //             let material = adjecent.get(x, CHUNK_SIZE - 1, z);
-        //             blocks.set(x + 1, 0, z + 1, material);
-        //             contains_opaque_blocks |= material.is_opaque();
-        //             contains_invisible_blocks |= !material.is_opaque();
-        //         }
-        //     }
-        // }
-        if let Some(adjecent) = world.chunk_manager.get(&adjecent[3]) {
-            for x in 0..CHUNK_SIZE {
-                for z in 0..CHUNK_SIZE {
-                    let material = adjecent.get(x, 0, z);
-                    blocks.set(x + 1, CHUNK_SIZE_SAFE - 1, z + 1, material);
-                    contains_opaque_blocks |= material.is_opaque();
-                    contains_invisible_blocks |= !material.is_opaque();
-                }
+        let mesh = match (layer, mesher, lod.is_full()) {
+            (RenderLayer::Opaque, Mesher::Greedy, true) => {
+                generate_lit_octree_mesh(id, chunk_data, &blocks, &light)
             }
-        }
-
-        // x
-        // if let Some(adjecent) = world.chunk_manager.get(&adjecent[4]) {
-        //     for x in 0..CHUNK_SIZE {
-        //         for y in 0..CHUNK_SIZE {
-        //             let material = adjecent.get(x, y, CHUNK_SIZE - 1);
-        //             blocks.set(x + 1, y + 1, 0, material);
-        //             contains_opaque_blocks |= material.is_opaque();
-        //             contains_invisible_blocks |= !material.is_opaque();
-        //         }
-        //     }
-        // }
-        if let Some(adjecent) = world.chunk_manager.get(&adjecent[5]) {
-            for x in 0..CHUNK_SIZE {
-                for y in 0..CHUNK_SIZE {
-                    let material = adjecent.get(x, y, 0);
-                    blocks.set(x + 1, y + 1, CHUNK_SIZE_SAFE - 1, material);
-                    contains_opaque_blocks |= material.is_opaque();
-                    contains_invisible_blocks |= !material.is_opaque();
-                }
+            (RenderLayer::Opaque, Mesher::Greedy, false) => {
+                generate_octree_mesh(id, chunk_data, &blocks)
             }
-        }
+            (RenderLayer::Opaque, Mesher::Binary, true) => {
+                generate_lit_binary_mesh(id, &blocks, &light)
+            }
+            (RenderLayer::Opaque, Mesher::Binary, false) => generate_binary_mesh(id, &blocks),
+            (RenderLayer::Opaque, Mesher::SurfaceNets, true) => {
+                generate_lit_surface_nets_mesh(id, &blocks, &light)
+            }
+            (RenderLayer::Opaque, Mesher::SurfaceNets, false) => {
+                generate_surface_nets_mesh(id, &blocks)
+            }
+            (_, _, true) => generate_lit_layer_mesh(id, &blocks, &light, layer),
+            (_, _, false) => generate_layer_mesh(id, &blocks, layer),
+        };
+        // block meshes are packed for upload, smooth ones keep their full vertices
+        (!mesh.vertices.is_empty()).then(|| ChunkMesh::pack_or_smooth(mesh))
+    });
 
-        if contains_opaque_blocks && !contains_invisible_blocks {
-            // println!("No mesh for full chunk");
-            return (None, None);
-        }
+    if meshes.iter().all(Option::is_none) {
+        log!(*LOG_WORLD, "[WARN] Empty mesh produced for {:?}", id);
+    }
 
-        if !contains_opaque_blocks && contains_invisible_blocks {
-            // println!("No mesh for empty chunk");
-            return (None, None);
-        }
+    meshes
+}
 
-        // remesh
-        let mesh = generate_greedy_mesh(id, &blocks);
-        let opaque_mesh = if mesh.vertices.is_empty() {
-            None
-        } else {
-            Some(mesh)
-        };
+/**
+ * Light of a chunk in the padded layout used for meshing.
+ * Borders to missing neighbors repeat the light of the chunk itself.
+ */
+fn light_slice(id: &ChunkId, world: &World) -> CubeSlice<Light, CHUNK_SIZE_SAFE> {
+    let mut slice = CubeSlice::<Light, CHUNK_SIZE_SAFE>::default();
+    let Some(center) = world.chunk_manager.light(id) else {
+        return slice;
+    };
+
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                slice.set(x + 1, y + 1, z + 1, center.get(x, y, z));
+            }
+        }
+    }
 
-        let mesh = generate_greedy_mesh_water(id, &blocks);
-        let water_mesh = if mesh.vertices.is_empty() {
-            None
-        } else {
-            Some(mesh)
+    // faces of the neighbors on all six sides, in the order of the adjecent chunks
+    let last = CHUNK_SIZE - 1;
+    for (i, adjecent) in id.get_adjecent().iter().enumerate() {
+        let d = i / 2;
+        let (own, theirs, padded) = match i % 2 {
+            0 => (0, last, 0),
+            _ => (last, 0, CHUNK_SIZE_SAFE - 1),
         };
-
-        if opaque_mesh.is_none() && water_mesh.is_none() {
-            log!(*LOG_WORLD, "[WARN] Empty mesh produced for {:?}", id);
+        let neighbor = world.chunk_manager.light(adjecent);
+        for a in 0..CHUNK_SIZE {
+            for b in 0..CHUNK_SIZE {
+                let mut p = [0; 3];
+                p[(d + 1) % 3] = a;
+                p[(d + 2) % 3] = b;
+                let light = match neighbor {
+                    Some(light) => {
+                        p[d] = theirs;
+                        light.get(p[0], p[1], p[2])
+                    }
+                    None => {
+                        p[d] = own;
+                        center.get(p[0], p[1], p[2])
+                    }
+                };
+                let mut p = p.map(|c| c + 1);
+                p[d] = padded;
+                slice.set(p[0], p[1], p[2], light);
+            }
         }
-
-        (opaque_mesh, water_mesh)
-    } else {
-        println!("WARN: attempted to mesh non-exitent chunk");
-        (None, None)
     }
+
+    slice
 }
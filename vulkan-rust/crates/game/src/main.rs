@@ -1,7 +1,21 @@
 use engine::Engine;
+use std::env;
+use world::WorldSeed;
 
+const DEFAULT_WORLD: &str = "saves/world";
+
+/**
+ * Usage: game [world directory] [seed]
+ *
+ * New worlds use the given seed or a random one, existing worlds keep their seed.
+ * Seeds that are not numbers are hashed.
+ */
 fn main() {
+    let mut args = env::args().skip(1);
+    let world_path = args.next().unwrap_or_else(|| DEFAULT_WORLD.to_owned());
+    let seed = args.next().map(|seed| WorldSeed::from_text(&seed));
+
     println!("Starting game");
-    Engine::create().run().unwrap();
+    Engine::create(world_path, seed).unwrap().run().unwrap();
     println!("Exiting game");
 }
@@ -0,0 +1,80 @@
+use std::mem::variant_count;
+
+use crate::material::Material;
+
+/// Width and height of a tile in the atlas in pixels
+pub const TILE_SIZE: usize = 16;
+
+/**
+ * Tiles of the block texture atlas, laid out in a single row in this order.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Stone,
+    Dirt,
+    GrassTop,
+    GrassSide,
+    Sand,
+    Snow,
+    Ice,
+    WoodTop,
+    WoodSide,
+    Leaves,
+    Gravel,
+}
+
+impl Tile {
+    pub const ALL: [Tile; variant_count::<Tile>()] = [
+        Self::Stone,
+        Self::Dirt,
+        Self::GrassTop,
+        Self::GrassSide,
+        Self::Sand,
+        Self::Snow,
+        Self::Ice,
+        Self::WoodTop,
+        Self::WoodSide,
+        Self::Leaves,
+        Self::Gravel,
+    ];
+
+    /**
+     * Column of the tile in the atlas.
+     */
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/**
+ * Side of a block, blocks can show different tiles on top, bottom and their sides.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Top,
+    Side,
+    Bottom,
+}
+
+impl Material {
+    /**
+     * Tile on a side of the block, materials without one are drawn in their palette color.
+     */
+    pub fn tile(&self, side: Side) -> Option<Tile> {
+        match (*self, side) {
+            (Self::Stone, _) => Some(Tile::Stone),
+            (Self::Grass, Side::Top) => Some(Tile::GrassTop),
+            (Self::Grass, Side::Side) => Some(Tile::GrassSide),
+            (Self::Grass, Side::Bottom) => Some(Tile::Dirt),
+            (Self::Sand, _) => Some(Tile::Sand),
+            (Self::Snow, _) => Some(Tile::Snow),
+            (Self::Ice, _) => Some(Tile::Ice),
+            (Self::Wood, Side::Side) => Some(Tile::WoodSide),
+            (Self::Wood, _) => Some(Tile::WoodTop),
+            (Self::Leaves, _) => Some(Tile::Leaves),
+            (Self::Dirt, _) => Some(Tile::Dirt),
+            (Self::Gravel, _) => Some(Tile::Gravel),
+            _ => None,
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 extern crate nalgebra_glm as glm;
 
+pub mod atlas;
 pub mod material;
 pub mod quest;
 pub mod vector;
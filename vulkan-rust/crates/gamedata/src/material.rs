@@ -1,5 +1,7 @@
 use std::mem::variant_count;
 
+use octree::ValueCodec;
+
 const SOLID: u8 = 0b1000_0000;
 const OPAQUE: u8 = 0b0100_0000;
 const ID_MASK: u8 = 0b0011_1111;
@@ -8,23 +10,26 @@ const ID_MASK: u8 = 0b0011_1111;
  * First 2 bits determine solidity and opacity, all other bits are IDs.
  */
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[rustfmt::skip]
 pub enum Material {
     #[default]
-    Unset  =                  0,
-    Air    =                  1,
-    Water  =                  2,
-    Glass  = SOLID |          3,
-    Stone  = SOLID | OPAQUE | 4,
-    Grass  = SOLID | OPAQUE | 5,
-    Sand   = SOLID | OPAQUE | 6,
-    Snow   = SOLID | OPAQUE | 7,
-    Ice    = SOLID | OPAQUE | 8,
-    Wood   = SOLID | OPAQUE | 9,
-    Leaves = SOLID | OPAQUE | 10,
-    Dirt   = SOLID | OPAQUE | 11,
-    Debug  = SOLID | OPAQUE | 12,
+    Unset   =                  0,
+    Air     =                  1,
+    Water   =                  2,
+    Glass   = SOLID |          3,
+    Stone   = SOLID | OPAQUE | 4,
+    Grass   = SOLID | OPAQUE | 5,
+    Sand    = SOLID | OPAQUE | 6,
+    Snow    = SOLID | OPAQUE | 7,
+    Ice     = SOLID | OPAQUE | 8,
+    Wood    = SOLID | OPAQUE | 9,
+    Leaves  = SOLID |          10,
+    Dirt    = SOLID | OPAQUE | 11,
+    Debug   = SOLID | OPAQUE | 12,
+    Lamp    = SOLID | OPAQUE | 13,
+    Crystal = SOLID | OPAQUE | 14,
+    Gravel  = SOLID | OPAQUE | 15,
 }
 
 impl Material {
@@ -35,7 +40,15 @@ impl Material {
 
     #[inline]
     pub fn is_surface(&self) -> bool {
-        *self == Material::Grass || *self == Material::Sand
+        *self == Material::Grass || *self == Material::Sand || *self == Material::Snow
+    }
+
+    /**
+     * Blocks of this material fall down when there is nothing below them.
+     */
+    #[inline]
+    pub fn has_gravity(&self) -> bool {
+        *self == Material::Sand || *self == Material::Gravel
     }
 
     #[inline]
@@ -48,11 +61,36 @@ impl Material {
         (*self as u8 & OPAQUE) != 0
     }
 
+    /**
+     * Render layer of the faces of this material, None for materials that are never drawn.
+     */
+    #[inline]
+    pub fn render_layer(&self) -> Option<RenderLayer> {
+        match *self {
+            Self::Unset | Self::Air => None,
+            Self::Leaves => Some(RenderLayer::Cutout),
+            m if m.is_opaque() => Some(RenderLayer::Opaque),
+            _ => Some(RenderLayer::Translucent),
+        }
+    }
+
     #[inline]
     pub fn is_fillable(&self) -> bool {
         return (*self as u8) < 2;
     }
 
+    /**
+     * Block light level emitted by this material, 0 for materials that do not glow.
+     */
+    #[inline]
+    pub fn emission(&self) -> u8 {
+        match *self {
+            Self::Lamp => 15,
+            Self::Crystal => 10,
+            _ => 0,
+        }
+    }
+
     pub fn color(&self) -> glm::Vec4 {
         // match *self {
         //     Self::Air => palette::transparent(),
@@ -78,7 +116,7 @@ impl Material {
             Self::Unset => palette::TRANSPARENT,
             Self::Air => palette::TRANSPARENT,
             Self::Water => palette::WATER,
-            Self::Glass => palette::TRANSPARENT,
+            Self::Glass => palette::GLASS,
             Self::Stone => palette::STONE,
             Self::Grass => palette::GRASS,
             Self::Sand => palette::SAND,
@@ -88,6 +126,9 @@ impl Material {
             Self::Leaves => palette::LEAVES,
             Self::Dirt => palette::DIRT,
             Self::Debug => palette::RED,
+            Self::Lamp => palette::LAMP,
+            Self::Crystal => palette::CRYSTAL,
+            Self::Gravel => palette::GRAVEL,
         }
 
         // [u8::from(*self), 0, 0, 255]
@@ -107,6 +148,9 @@ impl Material {
         Self::Leaves,
         Self::Dirt,
         Self::Debug,
+        Self::Lamp,
+        Self::Crystal,
+        Self::Gravel,
     ];
 }
 
@@ -121,3 +165,35 @@ impl From<Material> for u8 {
         value as u8 & ID_MASK
     }
 }
+
+/**
+ * Materials are stored by their ID, so chunks can be encoded with the octree codec.
+ */
+impl ValueCodec for Material {
+    const SIZE: usize = 1;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+
+    fn read(bytes: &[u8]) -> Option<Self> {
+        let id = *bytes.first()?;
+        ((id as usize) < Self::ALL.len()).then(|| Self::from(id))
+    }
+}
+
+/**
+ * Group of faces drawn together. Cutouts are alpha tested, translucent faces are blended and
+ * drawn after everything else, chunk by chunk from back to front.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    Opaque,
+    Cutout,
+    Translucent,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; variant_count::<RenderLayer>()] =
+        [Self::Opaque, Self::Cutout, Self::Translucent];
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Write},
+    path::Path,
+};
+
+use gamedata::material::Material;
+
+use crate::{Mesh, Vertex};
+
+/**
+ * File formats meshes can be written to.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Wavefront OBJ with vertex colors
+    Obj,
+    /// Binary little endian PLY with vertex colors
+    Ply,
+    /// Binary glTF 2.0 with one material per voxel material
+    Glb,
+}
+
+impl Format {
+    /**
+     * Format of a file by its extension.
+     */
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(Self::Obj),
+            "ply" => Some(Self::Ply),
+            "glb" => Some(Self::Glb),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Writes meshes in a format, each placed at the offset of its chunk.
+ * OBJ and PLY keep the z up axis of the world, glTF is y up.
+ */
+pub fn write<W: Write>(
+    format: Format,
+    out: &mut W,
+    meshes: &[(glm::Vec3, &Mesh)],
+) -> io::Result<()> {
+    match format {
+        Format::Obj => write_obj(out, meshes),
+        Format::Ply => write_ply(out, meshes),
+        Format::Glb => write_glb(out, meshes),
+    }
+}
+
+/**
+ * Color of a vertex from the palette, blended like in the shader.
+ */
+fn vertex_color(vertex: &Vertex) -> [f32; 4] {
+    let mut color = [0.0; 4];
+    for i in 0..3 {
+        let material = Material::from(vertex.materials[i] as u8);
+        for (c, byte) in color.iter_mut().zip(material.color_bytes()) {
+            *c += vertex.blend[i] * byte as f32 / 255.0;
+        }
+    }
+    color
+}
+
+fn positions<'a>(meshes: &'a [(glm::Vec3, &Mesh)]) -> impl Iterator<Item = glm::Vec3> + 'a {
+    meshes
+        .iter()
+        .flat_map(|(offset, mesh)| mesh.vertices.iter().map(move |v| offset + v.pos_mat.xyz()))
+}
+
+fn vertices<'a>(meshes: &'a [(glm::Vec3, &Mesh)]) -> impl Iterator<Item = &'a Vertex> + 'a {
+    meshes.iter().flat_map(|(_, mesh)| mesh.vertices.iter())
+}
+
+/**
+ * Indices of all meshes into the vertices of all meshes.
+ */
+fn indices<'a>(meshes: &'a [(glm::Vec3, &Mesh)]) -> impl Iterator<Item = u32> + 'a {
+    let mut base = 0;
+    meshes.iter().flat_map(move |(_, mesh)| {
+        let offset = base;
+        base += mesh.vertices.len() as u32;
+        mesh.indices.iter().map(move |i| offset + i)
+    })
+}
+
+pub fn write_obj<W: Write>(out: &mut W, meshes: &[(glm::Vec3, &Mesh)]) -> io::Result<()> {
+    writeln!(out, "# {} meshes", meshes.len())?;
+    for (p, v) in positions(meshes).zip(vertices(meshes)) {
+        let [r, g, b, _] = vertex_color(v);
+        writeln!(out, "v {} {} {} {} {} {}", p.x, p.y, p.z, r, g, b)?;
+    }
+    for v in vertices(meshes) {
+        writeln!(out, "vn {} {} {}", v.normal.x, v.normal.y, v.normal.z)?;
+    }
+
+    let mut base = 1;
+    for (i, (_, mesh)) in meshes.iter().enumerate() {
+        writeln!(out, "o mesh{}", i)?;
+        for triangle in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| triangle[k] + base);
+            writeln!(out, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+        base += mesh.vertices.len() as u32;
+    }
+    Ok(())
+}
+
+pub fn write_ply<W: Write>(out: &mut W, meshes: &[(glm::Vec3, &Mesh)]) -> io::Result<()> {
+    let vertex_count = vertices(meshes).count();
+    let face_count = indices(meshes).count() / 3;
+
+    write!(
+        out,
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         element vertex {vertex_count}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         property float nx\n\
+         property float ny\n\
+         property float nz\n\
+         property uchar red\n\
+         property uchar green\n\
+         property uchar blue\n\
+         property uchar alpha\n\
+         element face {face_count}\n\
+         property list uchar uint vertex_indices\n\
+         end_header\n"
+    )?;
+
+    let mut bytes = Vec::with_capacity(vertex_count * 28 + face_count * 13);
+    for (p, v) in positions(meshes).zip(vertices(meshes)) {
+        for c in [p.x, p.y, p.z, v.normal.x, v.normal.y, v.normal.z] {
+            bytes.extend(c.to_le_bytes());
+        }
+        bytes.extend(vertex_color(v).map(|c| (c * 255.0).round() as u8));
+    }
+    let indices = indices(meshes).collect::<Vec<_>>();
+    for triangle in indices.chunks(3) {
+        bytes.push(3);
+        for i in triangle {
+            bytes.extend(i.to_le_bytes());
+        }
+    }
+    out.write_all(&bytes)
+}
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+/**
+ * Converts a color channel of the palette to the linear colors of glTF materials.
+ */
+fn srgb_to_linear(byte: u8) -> f32 {
+    let c = byte as f32 / 255.0;
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+/**
+ * Writes a binary glTF 2.0 file. Triangles are grouped into one primitive per material,
+ * the vertices are shared between them.
+ */
+pub fn write_glb<W: Write>(out: &mut W, meshes: &[(glm::Vec3, &Mesh)]) -> io::Result<()> {
+    // glTF is y up
+    let positions = positions(meshes)
+        .map(|p| glm::vec3(p.x, p.z, -p.y))
+        .collect::<Vec<_>>();
+    let normals = vertices(meshes)
+        .map(|v| glm::vec3(v.normal.x, v.normal.z, -v.normal.y))
+        .collect::<Vec<_>>();
+    let materials = vertices(meshes)
+        .map(|v| v.pos_mat.w as u8)
+        .collect::<Vec<_>>();
+
+    let mut by_material = BTreeMap::<u8, Vec<u32>>::new();
+    let all_indices = indices(meshes).collect::<Vec<_>>();
+    for triangle in all_indices.chunks(3) {
+        by_material
+            .entry(materials[triangle[0] as usize])
+            .or_default()
+            .extend(triangle);
+    }
+
+    let mut bin = Vec::new();
+    for v in positions.iter().chain(&normals) {
+        for c in [v.x, v.y, v.z] {
+            bin.extend(c.to_le_bytes());
+        }
+    }
+    for indices in by_material.values() {
+        for i in indices {
+            bin.extend(i.to_le_bytes());
+        }
+    }
+
+    // without triangles there is no mesh to reference the buffer
+    if by_material.is_empty() {
+        bin.clear();
+    }
+
+    let json = gltf_json(&positions, &by_material, bin.len());
+    let mut json = json.into_bytes();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let mut length = 12 + 8 + json.len();
+    if !bin.is_empty() {
+        length += 8 + bin.len();
+    }
+    for word in [GLB_MAGIC, 2, length as u32, json.len() as u32, GLB_JSON] {
+        out.write_all(&word.to_le_bytes())?;
+    }
+    out.write_all(&json)?;
+    if !bin.is_empty() {
+        for word in [bin.len() as u32, GLB_BIN] {
+            out.write_all(&word.to_le_bytes())?;
+        }
+        out.write_all(&bin)?;
+    }
+    Ok(())
+}
+
+fn gltf_json(
+    positions: &[glm::Vec3],
+    by_material: &BTreeMap<u8, Vec<u32>>,
+    buffer_length: usize,
+) -> String {
+    let mut json = String::from(r#"{"asset":{"version":"2.0","generator":"vulkan-rust"},"#);
+    if by_material.is_empty() {
+        json.push_str(r#""scene":0,"scenes":[{"nodes":[]}]}"#);
+        return json;
+    }
+
+    let count = positions.len();
+    let min = positions.iter().fold(positions[0], |m, p| m.inf(p));
+    let max = positions.iter().fold(positions[0], |m, p| m.sup(p));
+    let vec3 = |v: glm::Vec3| format!("[{},{},{}]", v.x, v.y, v.z);
+
+    let mut views = vec![
+        format!(
+            r#"{{"buffer":0,"byteOffset":0,"byteLength":{},"target":{ARRAY_BUFFER}}}"#,
+            count * 12
+        ),
+        format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{ARRAY_BUFFER}}}"#,
+            count * 12,
+            count * 12
+        ),
+    ];
+    let mut accessors = vec![
+        format!(
+            r#"{{"bufferView":0,"componentType":{FLOAT},"count":{count},"type":"VEC3","min":{},"max":{}}}"#,
+            vec3(min),
+            vec3(max)
+        ),
+        format!(r#"{{"bufferView":1,"componentType":{FLOAT},"count":{count},"type":"VEC3"}}"#),
+    ];
+    let mut materials = vec![];
+    let mut primitives = vec![];
+
+    let mut offset = count * 24;
+    for (i, (material, indices)) in by_material.iter().enumerate() {
+        let length = indices.len() * 4;
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{offset},"byteLength":{length},"target":{ELEMENT_ARRAY_BUFFER}}}"#
+        ));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+            views.len() - 1,
+            indices.len()
+        ));
+        offset += length;
+
+        let material = Material::from(*material);
+        let [r, g, b, a] = material.color_bytes();
+        let mut entry = format!(
+            r#"{{"name":"{:?}","pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},{}],"metallicFactor":0,"roughnessFactor":1}}"#,
+            material,
+            srgb_to_linear(r),
+            srgb_to_linear(g),
+            srgb_to_linear(b),
+            a as f32 / 255.0
+        );
+        if a < 255 {
+            entry.push_str(r#","alphaMode":"BLEND""#);
+        }
+        entry.push('}');
+        materials.push(entry);
+
+        primitives.push(format!(
+            r#"{{"attributes":{{"POSITION":0,"NORMAL":1}},"indices":{},"material":{i}}}"#,
+            accessors.len() - 1
+        ));
+    }
+
+    write!(
+        json,
+        r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{}]}}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{buffer_length}}}]}}"#,
+        primitives.join(","),
+        materials.join(","),
+        accessors.join(","),
+        views.join(",")
+    )
+    .unwrap();
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{write_glb, write_obj, write_ply, Format};
+    use crate::{Mesh, Vertex};
+
+    /**
+     * Unit square facing up, split into two triangles.
+     */
+    fn square(material: Material) -> Mesh {
+        let normal = glm::vec3(0.0, 0.0, 1.0);
+        let vertices = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| Vertex::from_material(glm::vec3(x, y, 1.0), material.into(), normal))
+            .to_vec();
+        Mesh {
+            vertices,
+            indices: vec![0, 1, 3, 0, 3, 2],
+        }
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Format::from_path("a/b.OBJ".as_ref()), Some(Format::Obj));
+        assert_eq!(Format::from_path("region.glb".as_ref()), Some(Format::Glb));
+        assert_eq!(Format::from_path("region.gltf".as_ref()), None);
+        assert_eq!(Format::from_path("region".as_ref()), None);
+    }
+
+    #[test]
+    fn obj_offsets_meshes_and_indices() {
+        let (stone, water) = (square(Material::Stone), square(Material::Water));
+        let meshes = [
+            (glm::vec3(0.0, 0.0, 0.0), &stone),
+            (glm::vec3(64.0, 0.0, 0.0), &water),
+        ];
+        let mut out = Vec::new();
+        write_obj(&mut out, &meshes).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("vn ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 4);
+        assert!(obj.contains("v 65 1 1 "));
+        // indices of the second mesh start after the vertices of the first
+        assert!(obj.lines().any(|l| l == "f 5//5 6//6 8//8"));
+    }
+
+    #[test]
+    fn ply_has_binary_vertices_and_faces() {
+        let stone = square(Material::Stone);
+        let mut out = Vec::new();
+        write_ply(&mut out, &[(glm::vec3(0.0, 0.0, 64.0), &stone)]).unwrap();
+
+        let end = b"end_header\n";
+        let header_length = out.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+        let header = std::str::from_utf8(&out[..header_length]).unwrap();
+        assert!(header.contains("element vertex 4\n"));
+        assert!(header.contains("element face 2\n"));
+
+        let body = &out[header_length..];
+        assert_eq!(body.len(), 4 * 28 + 2 * 13);
+        let z = f32::from_le_bytes(body[8..12].try_into().unwrap());
+        assert_eq!(z, 65.0);
+        assert_eq!(body[24..28], Material::Stone.color_bytes());
+        assert_eq!(body[4 * 28], 3);
+    }
+
+    #[test]
+    fn glb_has_a_primitive_per_material() {
+        let (stone, glass) = (square(Material::Stone), square(Material::Glass));
+        let meshes = [
+            (glm::vec3(0.0, 0.0, 0.0), &stone),
+            (glm::vec3(0.0, 0.0, 0.0), &glass),
+        ];
+        let mut out = Vec::new();
+        write_glb(&mut out, &meshes).unwrap();
+
+        let word = |i: usize| u32::from_le_bytes(out[i * 4..i * 4 + 4].try_into().unwrap());
+        assert_eq!(&out[0..4], b"glTF");
+        assert_eq!(word(1), 2);
+        assert_eq!(word(2) as usize, out.len());
+        assert_eq!(out.len() % 4, 0);
+
+        let json_length = word(3) as usize;
+        let json = std::str::from_utf8(&out[20..20 + json_length]).unwrap();
+        assert_eq!(json.matches(r#""POSITION":0"#).count(), 2);
+        assert!(json.contains(r#""name":"Stone""#));
+        assert!(json.contains(r#""name":"Glass""#));
+        assert!(json.contains(r#""alphaMode":"BLEND""#));
+        // 8 positions and normals, 12 indices
+        let bin_length =
+            u32::from_le_bytes(out[20 + json_length..24 + json_length].try_into().unwrap());
+        assert_eq!(bin_length, 8 * 24 + 12 * 4);
+    }
+
+    #[test]
+    fn glb_of_nothing_is_an_empty_scene() {
+        let mut out = Vec::new();
+        write_glb(&mut out, &[]).unwrap();
+        assert_eq!(out.len() % 4, 0);
+        assert!(std::str::from_utf8(&out[20..])
+            .unwrap()
+            .contains(r#""nodes":[]"#));
+    }
+}
@@ -2,14 +2,18 @@ extern crate nalgebra_glm as glm;
 
 pub mod camera;
 mod collision;
+pub mod export;
 mod frustum;
 mod input;
 mod mesh;
+mod packed_vertex;
 mod raycast;
+pub mod validate;
 mod vertex;
 
 pub use collision::CollisionDetection;
 pub use frustum::Frustum;
-pub use mesh::Mesh;
+pub use mesh::{ChunkMesh, Mesh, PackedMesh};
+pub use packed_vertex::PackedVertex;
 pub use raycast::Raycast;
 pub use vertex::Vertex;
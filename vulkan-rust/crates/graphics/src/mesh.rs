@@ -1,7 +1,83 @@
-use crate::Vertex;
+use std::mem::size_of;
 
-#[derive(Clone, PartialEq, Debug, Default)]
-pub struct Mesh {
-    pub vertices: Vec<Vertex>,
+use crate::{PackedVertex, Vertex};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mesh<V = Vertex> {
+    pub vertices: Vec<V>,
     pub indices: Vec<u32>,
 }
+
+pub type PackedMesh = Mesh<PackedVertex>;
+
+impl<V> Default for Mesh<V> {
+    fn default() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+}
+
+impl<V> Mesh<V> {
+    /**
+     * Size of the vertex and index buffers of the mesh.
+     */
+    pub fn bytes(&self) -> usize {
+        self.vertices.len() * size_of::<V>() + self.indices.len() * size_of::<u32>()
+    }
+}
+
+impl Mesh {
+    /**
+     * Packs the mesh if all of its vertices can be packed.
+     */
+    pub fn pack(&self) -> Option<PackedMesh> {
+        Some(PackedMesh {
+            vertices: self
+                .vertices
+                .iter()
+                .map(PackedVertex::pack)
+                .collect::<Option<_>>()?,
+            indices: self.indices.clone(),
+        })
+    }
+}
+
+impl PackedMesh {
+    pub fn unpack(&self) -> Mesh {
+        Mesh {
+            vertices: self.vertices.iter().map(PackedVertex::unpack).collect(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+/**
+ * Mesh of a chunk as it is uploaded. Block meshes are packed, smooth meshes keep full vertices.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub enum ChunkMesh {
+    Packed(PackedMesh),
+    Smooth(Mesh),
+}
+
+impl ChunkMesh {
+    pub fn bytes(&self) -> usize {
+        match self {
+            Self::Packed(mesh) => mesh.bytes(),
+            Self::Smooth(mesh) => mesh.bytes(),
+        }
+    }
+
+    /**
+     * Packs a block mesh. Meshes with any vertex that cannot be packed, like the meshes of the
+     * smooth meshers, keep their full vertices.
+     */
+    pub fn pack_or_smooth(mesh: Mesh) -> Self {
+        match mesh.pack() {
+            Some(packed) => Self::Packed(packed),
+            None => Self::Smooth(mesh),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use nalgebra_glm::{vec2, vec3};
+use std::mem::size_of;
+use vulkanalia::vk::{self, HasBuilder};
+
+use crate::Vertex;
+
+/// Highest position and texture coordinate, positions are counted in voxels inside a chunk
+const MAX_COORDINATE: u32 = (1 << 7) - 1;
+/// Vertices can be lowered below their voxel corner in steps of this fraction, like water surfaces
+const DROP_STEPS: u32 = 12;
+const MAX_DROP: u32 = (1 << 3) - 1;
+const MAX_LIGHT: u32 = 15;
+const MAX_AO: u32 = 3;
+const MAX_TILE: u32 = (1 << 5) - 2;
+
+/**
+ * Vertex of a block mesh in 8 bytes, all of its values are small integers inside a chunk.
+ * Must match the unpacking in shader.vert.
+ *
+ * | word | bits  | value                                  |
+ * |------|-------|----------------------------------------|
+ * | 0    | 0-20  | x, y and z, 7 bits each                |
+ * | 0    | 21-23 | normal, index into [`NORMALS`]         |
+ * | 0    | 24-31 | material                               |
+ * | 1    | 0-13  | u and v, 7 bits each                   |
+ * | 1    | 14-21 | sky and block light, 4 bits each       |
+ * | 1    | 22-23 | ambient occlusion                      |
+ * | 1    | 24-28 | atlas tile + 1, 0 for the palette      |
+ * | 1    | 29-31 | drop below z in twelfths of a voxel    |
+ */
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PackedVertex {
+    pub data: [u32; 2],
+}
+
+/// Face normals a packed vertex can have
+pub const NORMALS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, -1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+];
+
+/**
+ * Scales a value in 0..=1 to 0..=max, if it is exactly one of the steps.
+ */
+fn steps(value: f32, max: u32) -> Option<u32> {
+    let step = (value * max as f32).round();
+    (0.0..=max as f32)
+        .contains(&step)
+        .then_some(step as u32)
+        .filter(|&s| value == s as f32 / max as f32)
+}
+
+/**
+ * Value as a whole number in 0..=max.
+ */
+fn whole(value: f32, max: u32) -> Option<u32> {
+    (value.fract() == 0.0 && (0.0..=max as f32).contains(&value)).then_some(value as u32)
+}
+
+impl PackedVertex {
+    /**
+     * Packs a vertex of a block mesh. Vertices off the grid, with other normals or with
+     * blended materials cannot be packed.
+     */
+    pub fn pack(vertex: &Vertex) -> Option<Self> {
+        let material = vertex.pos_mat.w;
+        let unblended = vertex.materials == vec3(material, material, material)
+            && vertex.blend == vec3(1.0, 0.0, 0.0);
+        if !unblended {
+            return None;
+        }
+
+        let x = whole(vertex.pos_mat.x, MAX_COORDINATE)?;
+        let y = whole(vertex.pos_mat.y, MAX_COORDINATE)?;
+        let z = whole(vertex.pos_mat.z.ceil(), MAX_COORDINATE)?;
+        let drop = ((z as f32 - vertex.pos_mat.z) * DROP_STEPS as f32).round() as u32;
+        if drop > MAX_DROP || z as f32 - drop as f32 / DROP_STEPS as f32 != vertex.pos_mat.z {
+            return None;
+        }
+        let normal = NORMALS
+            .iter()
+            .position(|n| vertex.normal == vec3(n[0], n[1], n[2]))? as u32;
+        let material = whole(material, u8::MAX as u32)?;
+
+        let u = whole(vertex.uv.x, MAX_COORDINATE)?;
+        let v = whole(vertex.uv.y, MAX_COORDINATE)?;
+        let sky = steps(vertex.light.x, MAX_LIGHT)?;
+        let block = steps(vertex.light.y, MAX_LIGHT)?;
+        let ao = steps(vertex.ao, MAX_AO)?;
+        let tile = match vertex.tile {
+            tile if tile < 0.0 => 0,
+            tile => whole(tile, MAX_TILE)? + 1,
+        };
+
+        Some(Self {
+            data: [
+                x | y << 7 | z << 14 | normal << 21 | material << 24,
+                u | v << 7 | sky << 14 | block << 18 | ao << 22 | tile << 24 | drop << 29,
+            ],
+        })
+    }
+
+    pub fn unpack(&self) -> Vertex {
+        let bits =
+            |word: usize, offset: u32, count: u32| (self.data[word] >> offset) & ((1 << count) - 1);
+
+        let drop = bits(1, 29, 3) as f32 / DROP_STEPS as f32;
+        let position = vec3(
+            bits(0, 0, 7) as f32,
+            bits(0, 7, 7) as f32,
+            bits(0, 14, 7) as f32 - drop,
+        );
+        let normal = NORMALS[bits(0, 21, 3) as usize];
+        let material = bits(0, 24, 8) as u8;
+
+        let light = vec2(
+            bits(1, 14, 4) as f32 / MAX_LIGHT as f32,
+            bits(1, 18, 4) as f32 / MAX_LIGHT as f32,
+        );
+        let uv = vec2(bits(1, 0, 7) as f32, bits(1, 7, 7) as f32);
+        let tile = bits(1, 24, 5) as f32 - 1.0;
+
+        Vertex::from_material(position, material, vec3(normal[0], normal[1], normal[2]))
+            .with_light(light)
+            .with_ao(bits(1, 22, 2) as f32 / MAX_AO as f32)
+            .with_texture(uv, tile)
+    }
+
+    pub fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(size_of::<PackedVertex>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 1] {
+        let data = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(0)
+            .format(vk::Format::R32G32_UINT)
+            .offset(0)
+            .build();
+
+        [data]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::mem::size_of;
+
+    use nalgebra_glm::{vec2, vec3};
+
+    use super::{PackedVertex, NORMALS};
+    use crate::Vertex;
+
+    #[test]
+    fn packs_into_8_bytes() {
+        assert_eq!(size_of::<PackedVertex>(), 8);
+        assert_eq!(size_of::<Vertex>(), 76);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut count = 0;
+        for (i, normal) in NORMALS.into_iter().enumerate() {
+            for position in [[0, 0, 0], [64, 64, 64], [1, 63, 2], [17, 0, 64]] {
+                for material in [0, 1, 10, 0b1100_0011, u8::MAX] {
+                    for (light, ao) in [(vec2(1.0, 0.0), 1.0), (vec2(4.0, 15.0) / 15.0, 1.0 / 3.0)]
+                    {
+                        for tile in [-1.0, 0.0, 10.0, 30.0] {
+                            let position = position.map(|p| p as f32);
+                            let mut vertex = Vertex::from_material(
+                                vec3(position[0], position[1], position[2]),
+                                material,
+                                vec3(normal[0], normal[1], normal[2]),
+                            )
+                            .with_light(light)
+                            .with_ao(ao)
+                            .with_texture(vec2(i as f32, 64.0), tile);
+                            if material == 10 {
+                                vertex.pos_mat.z -= 2.0 / 12.0;
+                            }
+
+                            let packed = PackedVertex::pack(&vertex).unwrap();
+                            assert_eq!(packed.unpack(), vertex);
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(count, 6 * 4 * 5 * 2 * 4);
+    }
+
+    #[test]
+    fn smooth_vertices_are_not_packed() {
+        let vertex = Vertex::from_material(vec3(1.0, 2.0, 3.0), 1, vec3(0.0, 0.0, 1.0));
+        assert!(PackedVertex::pack(&vertex).is_some());
+
+        let off_grid = Vertex::from_material(vec3(1.5, 2.0, 3.0), 1, vec3(0.0, 0.0, 1.0));
+        let outside = Vertex::from_material(vec3(1.0, 128.0, 3.0), 1, vec3(0.0, 0.0, 1.0));
+        let tilted = Vertex::from_material(vec3(1.0, 2.0, 3.0), 1, vec3(0.6, 0.0, 0.8));
+        let blended = vertex.with_blend(vec3(1.0, 2.0, 1.0), vec3(0.5, 0.5, 0.0));
+        let dim = vertex.with_light(vec2(0.5, 0.0));
+        for vertex in [off_grid, outside, tilted, blended, dim] {
+            assert_eq!(PackedVertex::pack(&vertex), None, "{:?}", vertex);
+        }
+    }
+}
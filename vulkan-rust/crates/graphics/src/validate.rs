@@ -0,0 +1,352 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+use gamedata::material::Material;
+
+use crate::{Mesh, Vertex};
+
+/// Offset of the points sampled in each unit cell of a plane, off center so they never lie on
+/// the diagonal of a quad
+const SAMPLE_OFFSET: [f32; 2] = [0.5013, 0.4991];
+
+/**
+ * Problem found in a mesh, triangles are counted in groups of 3 indices.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    /// The index count is not a multiple of 3
+    IndexCount(usize),
+    IndexOutOfBounds {
+        triangle: usize,
+        index: u32,
+    },
+    /// Triangle without area
+    Degenerate {
+        triangle: usize,
+    },
+    /// Triangle whose front faces away from its normal
+    Winding {
+        triangle: usize,
+    },
+    /// Two triangles covering the same area of a plane, facing the same way
+    Overlap {
+        triangle: usize,
+        other: usize,
+    },
+    /// Edge without a matching edge in the opposite direction, T-junctions show up as these
+    OpenEdge {
+        from: glm::Vec3,
+        to: glm::Vec3,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexCount(count) => write!(f, "{} indices do not form triangles", count),
+            Self::IndexOutOfBounds { triangle, index } => {
+                write!(f, "triangle {} has index {} out of bounds", triangle, index)
+            }
+            Self::Degenerate { triangle } => write!(f, "triangle {} is degenerate", triangle),
+            Self::Winding { triangle } => {
+                write!(f, "triangle {} faces away from its normal", triangle)
+            }
+            Self::Overlap { triangle, other } => {
+                write!(f, "triangles {} and {} overlap", triangle, other)
+            }
+            Self::OpenEdge { from, to } => write!(
+                f,
+                "edge ({}, {}, {}) -> ({}, {}, {}) is open",
+                from.x, from.y, from.z, to.x, to.y, to.z
+            ),
+        }
+    }
+}
+
+/**
+ * Checks indices, degenerate triangles, winding and overlapping faces.
+ * Overlaps are only detected between faces on axis aligned planes.
+ */
+pub fn validate(mesh: &Mesh) -> Vec<MeshError> {
+    if mesh.indices.len() % 3 != 0 {
+        return vec![MeshError::IndexCount(mesh.indices.len())];
+    }
+
+    let mut errors = vec![];
+    let mut triangles = vec![];
+    for (triangle, indices) in mesh.indices.chunks(3).enumerate() {
+        if let Some(index) = indices.iter().find(|i| **i as usize >= mesh.vertices.len()) {
+            errors.push(MeshError::IndexOutOfBounds {
+                triangle,
+                index: *index,
+            });
+            continue;
+        }
+
+        let vertices = [0, 1, 2].map(|k| &mesh.vertices[indices[k] as usize]);
+        let [a, b, c] = vertices.map(|v| v.pos_mat.xyz());
+        let cross = (b - a).cross(&(c - a));
+        if cross.norm_squared() == 0.0 {
+            errors.push(MeshError::Degenerate { triangle });
+            continue;
+        }
+
+        let normal = vertices
+            .iter()
+            .fold(glm::Vec3::zeros(), |n, v| n + v.normal);
+        if cross.dot(&normal) <= 0.0 {
+            errors.push(MeshError::Winding { triangle });
+        }
+        triangles.push((triangle, [a, b, c], cross));
+    }
+
+    errors.extend(overlaps(&triangles));
+    errors
+}
+
+/**
+ * Checks a mesh of closed objects, every edge has to be shared with exactly one triangle
+ * running along it in the opposite direction.
+ */
+pub fn validate_closed(mesh: &Mesh) -> Vec<MeshError> {
+    let mut errors = validate(mesh);
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    let key = |v: &Vertex| [v.pos_mat.x, v.pos_mat.y, v.pos_mat.z].map(f32::to_bits);
+    let mut edges = HashMap::<_, usize>::new();
+    for indices in mesh.indices.chunks(3) {
+        let corners = [0, 1, 2].map(|k| key(&mesh.vertices[indices[k] as usize]));
+        for k in 0..3 {
+            *edges.entry((corners[k], corners[(k + 1) % 3])).or_default() += 1;
+        }
+    }
+
+    let mut open = edges
+        .iter()
+        .filter(|((from, to), count)| **count != 1 || edges.get(&(*to, *from)) != Some(&1))
+        .map(|((from, to), _)| (*from, *to))
+        .collect::<Vec<_>>();
+    open.sort();
+    let position = |p: [u32; 3]| glm::Vec3::from(p.map(f32::from_bits));
+    errors.extend(open.into_iter().map(|(from, to)| MeshError::OpenEdge {
+        from: position(from),
+        to: position(to),
+    }));
+    errors
+}
+
+/**
+ * Samples the unit cells covered by triangles on axis aligned planes, cells covered twice from
+ * the same side are overlaps.
+ */
+fn overlaps(triangles: &[(usize, [glm::Vec3; 3], glm::Vec3)]) -> Vec<MeshError> {
+    let mut covered = HashMap::new();
+    let mut pairs = BTreeSet::new();
+
+    for (triangle, corners, cross) in triangles {
+        let Some(d) = (0..3).find(|d| cross[(d + 1) % 3] == 0.0 && cross[(d + 2) % 3] == 0.0)
+        else {
+            continue;
+        };
+        let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+        let plane = (d, cross[d] > 0.0, corners[0][d].to_bits());
+        let points = corners.map(|c| glm::vec2(c[u], c[v]));
+        let min = points.iter().fold(points[0], |m, p| m.inf(p));
+        let max = points.iter().fold(points[0], |m, p| m.sup(p));
+
+        for cell_u in min.x.floor() as i32..max.x.ceil() as i32 {
+            for cell_v in min.y.floor() as i32..max.y.ceil() as i32 {
+                let sample = glm::vec2(
+                    cell_u as f32 + SAMPLE_OFFSET[0],
+                    cell_v as f32 + SAMPLE_OFFSET[1],
+                );
+                if !contains(&points, &sample) {
+                    continue;
+                }
+                if let Some(other) = covered.insert((plane, cell_u, cell_v), *triangle) {
+                    pairs.insert((other, *triangle));
+                }
+            }
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(other, triangle)| MeshError::Overlap { triangle, other })
+        .collect()
+}
+
+fn contains(triangle: &[glm::Vec2; 3], p: &glm::Vec2) -> bool {
+    let side = |a: &glm::Vec2, b: &glm::Vec2| (b - a).perp(&(p - a));
+    let sides = [0, 1, 2].map(|k| side(&triangle[k], &triangle[(k + 1) % 3]));
+    sides.iter().all(|s| *s > 0.0) || sides.iter().all(|s| *s < 0.0)
+}
+
+/**
+ * Size of a mesh, quads are groups of 6 indices counted by the material of their first vertex.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MeshStats {
+    pub vertices: usize,
+    pub indices: usize,
+    pub quads: BTreeMap<u8, usize>,
+    pub bytes: usize,
+    /// Size once packed for upload, if the mesh can be packed
+    pub packed_bytes: Option<usize>,
+}
+
+impl MeshStats {
+    pub fn of(mesh: &Mesh) -> Self {
+        let mut quads = BTreeMap::new();
+        for quad in mesh.indices.chunks(6) {
+            if let Some(vertex) = mesh.vertices.get(quad[0] as usize) {
+                *quads.entry(vertex.pos_mat.w as u8).or_default() += 1;
+            }
+        }
+
+        Self {
+            vertices: mesh.vertices.len(),
+            indices: mesh.indices.len(),
+            quads,
+            bytes: mesh.bytes(),
+            packed_bytes: mesh.pack().map(|packed| packed.bytes()),
+        }
+    }
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} vertices, {} indices, {} KiB",
+            self.vertices,
+            self.indices,
+            self.bytes / 1024
+        )?;
+        if let Some(packed_bytes) = self.packed_bytes {
+            write!(f, " ({} KiB packed)", packed_bytes / 1024)?;
+        }
+        for (material, quads) in &self.quads {
+            write!(f, ", {:?}: {} quads", Material::from(*material), quads)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate, validate_closed, MeshError, MeshStats};
+    use crate::{Mesh, Vertex};
+
+    /**
+     * Quad from a corner along two axes, counter clockwise seen from its normal.
+     */
+    fn quad(mesh: &mut Mesh, corner: glm::Vec3, du: glm::Vec3, dv: glm::Vec3) {
+        let normal = du.cross(&dv).normalize();
+        let i0 = mesh.vertices.len() as u32;
+        mesh.vertices.extend(
+            [corner, corner + du, corner + dv, corner + du + dv]
+                .map(|p| Vertex::from_material(p, 4, normal)),
+        );
+        mesh.indices
+            .extend([i0, i0 + 1, i0 + 3, i0, i0 + 3, i0 + 2]);
+    }
+
+    fn cube(mesh: &mut Mesh, min: glm::Vec3, size: f32) {
+        let [x, y, z] = [glm::Vec3::x(), glm::Vec3::y(), glm::Vec3::z()].map(|a| a * size);
+        let max = min + x + y + z;
+        quad(mesh, min, y, x);
+        quad(mesh, min, z, y);
+        quad(mesh, min, x, z);
+        quad(mesh, max, -x, -y);
+        quad(mesh, max, -y, -z);
+        quad(mesh, max, -z, -x);
+    }
+
+    #[test]
+    fn cube_is_closed() {
+        let mut mesh = Mesh::default();
+        cube(&mut mesh, glm::vec3(1.0, 2.0, 3.0), 2.0);
+        assert_eq!(validate_closed(&mesh), vec![]);
+
+        let stats = MeshStats::of(&mesh);
+        assert_eq!((stats.vertices, stats.indices), (24, 36));
+        assert_eq!(stats.quads.get(&4), Some(&6));
+        assert_eq!(stats.bytes, 24 * std::mem::size_of::<Vertex>() + 36 * 4);
+        assert_eq!(stats.packed_bytes, Some(24 * 8 + 36 * 4));
+    }
+
+    #[test]
+    fn finds_broken_triangles() {
+        let mut mesh = Mesh::default();
+        cube(&mut mesh, glm::vec3(0.0, 0.0, 0.0), 1.0);
+
+        let mut flipped = mesh.clone();
+        flipped.indices.swap(0, 1);
+        assert!(validate(&flipped).contains(&MeshError::Winding { triangle: 0 }));
+
+        let mut degenerate = mesh.clone();
+        degenerate.indices[2] = degenerate.indices[1];
+        assert!(validate(&degenerate).contains(&MeshError::Degenerate { triangle: 0 }));
+
+        let mut out_of_bounds = mesh.clone();
+        out_of_bounds.indices[5] = 24;
+        assert_eq!(
+            validate(&out_of_bounds),
+            vec![MeshError::IndexOutOfBounds {
+                triangle: 1,
+                index: 24
+            }]
+        );
+
+        mesh.indices.pop();
+        assert_eq!(validate(&mesh), vec![MeshError::IndexCount(35)]);
+    }
+
+    #[test]
+    fn finds_overlapping_faces() {
+        let mut mesh = Mesh::default();
+        let (x, y) = (glm::Vec3::x(), glm::Vec3::y());
+        quad(&mut mesh, glm::vec3(0.0, 0.0, 1.0), 2.0 * x, 2.0 * y);
+        quad(&mut mesh, glm::vec3(1.0, 1.0, 1.0), 2.0 * x, y);
+        // the same area seen from below is fine
+        quad(&mut mesh, glm::vec3(0.0, 0.0, 1.0), y, x);
+
+        let errors = validate(&mesh);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            MeshError::Overlap {
+                other: 0 | 1,
+                triangle: 2 | 3
+            }
+        ));
+    }
+
+    #[test]
+    fn t_junctions_are_open_edges() {
+        let mut mesh = Mesh::default();
+        cube(&mut mesh, glm::vec3(0.0, 0.0, 0.0), 2.0);
+        assert_eq!(validate_closed(&mesh), vec![]);
+
+        // split one side into two halves, its neighbors still end at the corners
+        let mut split = Mesh::default();
+        cube(&mut split, glm::vec3(0.0, 0.0, 0.0), 2.0);
+        split.indices.truncate(30);
+        split.vertices.truncate(20);
+        let (x, z) = (glm::Vec3::x(), glm::Vec3::z());
+        let max = glm::vec3(2.0, 2.0, 2.0);
+        quad(&mut split, max, -2.0 * z, -x);
+        quad(&mut split, max - x, -2.0 * z, -x);
+
+        let errors = validate_closed(&split);
+        assert!(!errors.is_empty());
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e, MeshError::OpenEdge { .. })));
+    }
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::vec4;
+use nalgebra_glm::{vec2, vec3, vec4};
 use std::{
     hash::{Hash, Hasher},
     mem::size_of,
@@ -10,6 +10,18 @@ use vulkanalia::vk::{self, HasBuilder};
 pub struct Vertex {
     pub pos_mat: glm::Vec4,
     pub normal: glm::Vec3,
+    /// Sky and block light in 0..=1
+    pub light: glm::Vec2,
+    /// Ambient occlusion in 0..=1, 1 is unoccluded
+    pub ao: f32,
+    /// Materials blended across a triangle, the same on all of its vertices
+    pub materials: glm::Vec3,
+    /// Weights of the blended materials
+    pub blend: glm::Vec3,
+    /// Texture coordinates in voxels, the tile repeats at every whole number
+    pub uv: glm::Vec2,
+    /// Tile of the texture atlas, negative for the palette color
+    pub tile: f32,
 }
 
 impl Vertex {
@@ -17,11 +29,46 @@ impl Vertex {
         Self {
             pos_mat: vec4(pos.x, pos.y, pos.z, material as f32),
             normal,
+            light: vec2(1.0, 0.0),
+            ao: 1.0,
+            materials: vec3(material as f32, material as f32, material as f32),
+            blend: vec3(1.0, 0.0, 0.0),
+            uv: vec2(0.0, 0.0),
+            tile: -1.0,
         }
     }
 
     pub fn new(pos_mat: glm::Vec4, normal: glm::Vec3) -> Self {
-        Self { pos_mat, normal }
+        Self {
+            pos_mat,
+            normal,
+            light: vec2(1.0, 0.0),
+            ao: 1.0,
+            materials: vec3(pos_mat.w, pos_mat.w, pos_mat.w),
+            blend: vec3(1.0, 0.0, 0.0),
+            uv: vec2(0.0, 0.0),
+            tile: -1.0,
+        }
+    }
+
+    pub fn with_light(self, light: glm::Vec2) -> Self {
+        Self { light, ..self }
+    }
+
+    pub fn with_ao(self, ao: f32) -> Self {
+        Self { ao, ..self }
+    }
+
+    pub fn with_blend(self, materials: glm::Vec3, blend: glm::Vec3) -> Self {
+        Self {
+            materials,
+            blend,
+            ..self
+        }
+    }
+
+    pub fn with_texture(self, uv: glm::Vec2, tile: f32) -> Self {
+        Self { uv, tile, ..self }
     }
 
     pub fn binding_description() -> vk::VertexInputBindingDescription {
@@ -32,7 +79,7 @@ impl Vertex {
             .build()
     }
 
-    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 8] {
         let pos_mat = vk::VertexInputAttributeDescription::builder()
             .binding(0)
             .location(0)
@@ -45,14 +92,80 @@ impl Vertex {
             .format(vk::Format::R32G32B32_SFLOAT)
             .offset(size_of::<glm::Vec4>() as u32)
             .build();
+        let light = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(2)
+            .format(vk::Format::R32G32_SFLOAT)
+            .offset((size_of::<glm::Vec4>() + size_of::<glm::Vec3>()) as u32)
+            .build();
+        let ao = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(3)
+            .format(vk::Format::R32_SFLOAT)
+            .offset(
+                (size_of::<glm::Vec4>() + size_of::<glm::Vec3>() + size_of::<glm::Vec2>()) as u32,
+            )
+            .build();
+        let materials = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(4)
+            .format(vk::Format::R32G32B32_SFLOAT)
+            .offset(
+                (size_of::<glm::Vec4>()
+                    + size_of::<glm::Vec3>()
+                    + size_of::<glm::Vec2>()
+                    + size_of::<f32>()) as u32,
+            )
+            .build();
+        let blend = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(5)
+            .format(vk::Format::R32G32B32_SFLOAT)
+            .offset(
+                (size_of::<glm::Vec4>()
+                    + size_of::<glm::Vec3>() * 2
+                    + size_of::<glm::Vec2>()
+                    + size_of::<f32>()) as u32,
+            )
+            .build();
+
+        let uv = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(6)
+            .format(vk::Format::R32G32_SFLOAT)
+            .offset(
+                (size_of::<glm::Vec4>()
+                    + size_of::<glm::Vec3>() * 3
+                    + size_of::<glm::Vec2>()
+                    + size_of::<f32>()) as u32,
+            )
+            .build();
+        let tile = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(7)
+            .format(vk::Format::R32_SFLOAT)
+            .offset(
+                (size_of::<glm::Vec4>()
+                    + size_of::<glm::Vec3>() * 3
+                    + size_of::<glm::Vec2>() * 2
+                    + size_of::<f32>()) as u32,
+            )
+            .build();
 
-        [pos_mat, normal]
+        [pos_mat, normal, light, ao, materials, blend, uv, tile]
     }
 }
 
 impl PartialEq for Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.pos_mat == other.pos_mat && self.normal == other.normal
+        self.pos_mat == other.pos_mat
+            && self.normal == other.normal
+            && self.light == other.light
+            && self.ao == other.ao
+            && self.materials == other.materials
+            && self.blend == other.blend
+            && self.uv == other.uv
+            && self.tile == other.tile
     }
 }
 
@@ -66,5 +179,17 @@ impl Hash for Vertex {
         self.normal[0].to_bits().hash(state);
         self.normal[1].to_bits().hash(state);
         self.normal[2].to_bits().hash(state);
+        self.light[0].to_bits().hash(state);
+        self.light[1].to_bits().hash(state);
+        self.ao.to_bits().hash(state);
+        self.materials[0].to_bits().hash(state);
+        self.materials[1].to_bits().hash(state);
+        self.materials[2].to_bits().hash(state);
+        self.blend[0].to_bits().hash(state);
+        self.blend[1].to_bits().hash(state);
+        self.blend[2].to_bits().hash(state);
+        self.uv[0].to_bits().hash(state);
+        self.uv[1].to_bits().hash(state);
+        self.tile.to_bits().hash(state);
     }
 }
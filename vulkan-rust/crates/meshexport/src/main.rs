@@ -0,0 +1,198 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use clap::{value_parser, Arg, ArgAction, Command};
+use gamedata::material::RenderLayer;
+use graphics::{
+    export::{self, Format},
+    validate::{validate, MeshStats},
+    Mesh,
+};
+use nalgebra_glm as glm;
+use world::{
+    binary_mesh::generate_binary_mesh,
+    gen::chunk::{compress, Chunk},
+    mesh_generator::{generate_layer_mesh, Mesher},
+    neighborhood::ChunkNeighborhood,
+    surface_nets::generate_surface_nets_mesh,
+    traits::{Generate, Voxelize},
+    ChunkId, ChunkManager, ChunkSeed, WorldGenConfig, WorldSeed,
+};
+
+/**
+ * Parses a chunk id written as x,y,z.
+ */
+fn parse_chunk_id(value: &str) -> Result<ChunkId, String> {
+    let coordinates = value
+        .split(',')
+        .map(|c| c.trim().parse::<i32>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match coordinates[..] {
+        [x, y, z] => Ok(ChunkId::new(x, y, z)),
+        _ => Err(format!("expected x,y,z but got {}", value)),
+    }
+}
+
+fn parse_mesher(value: &str) -> Result<Mesher, String> {
+    match value {
+        "greedy" => Ok(Mesher::Greedy),
+        "binary" => Ok(Mesher::Binary),
+        "surface_nets" => Ok(Mesher::SurfaceNets),
+        _ => Err(format!("unknown mesher {}", value)),
+    }
+}
+
+fn main() -> io::Result<()> {
+    let matches = Command::new("meshexport")
+        .about("Generates the chunks of a region and writes their meshes to OBJ, PLY or GLB")
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .required(true)
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("min")
+                .long("min")
+                .help("First chunk of the region as x,y,z")
+                .allow_hyphen_values(true)
+                .default_value("0,0,-1")
+                .value_parser(parse_chunk_id),
+        )
+        .arg(
+            Arg::new("max")
+                .long("max")
+                .help("Last chunk of the region as x,y,z, inclusive")
+                .allow_hyphen_values(true)
+                .default_value("1,1,0")
+                .value_parser(parse_chunk_id),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("Generator config to use instead of the defaults, like the worldgen.toml of a world")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("mesher")
+                .long("mesher")
+                .help("Mesher of the opaque layer, greedy, binary or surface_nets")
+                .default_value("greedy")
+                .value_parser(parse_mesher),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .required(true)
+                .help("File to write, the format is picked by the extension .obj, .ply or .glb")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("validate")
+                .long("validate")
+                .help("Print the statistics of every mesh and the problems found in it")
+                .action(ArgAction::SetTrue),
+        )
+        .get_matches();
+
+    let seed = WorldSeed::new(*matches.get_one::<u64>("seed").unwrap());
+    let min = matches.get_one::<ChunkId>("min").unwrap();
+    let max = matches.get_one::<ChunkId>("max").unwrap();
+    let config = match matches.get_one::<PathBuf>("config") {
+        Some(path) => WorldGenConfig::load(path)?,
+        None => WorldGenConfig::default(),
+    };
+    let mesher = *matches.get_one::<Mesher>("mesher").unwrap();
+    let output = matches.get_one::<PathBuf>("output").unwrap();
+    let Some(format) = Format::from_path(output) else {
+        eprintln!("Unknown format of {}", output.display());
+        std::process::exit(1);
+    };
+
+    let ids = (min.z..=max.z)
+        .flat_map(|z| (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| (x, y, z))))
+        .map(|(x, y, z)| ChunkId::new(x, y, z))
+        .collect::<Vec<_>>();
+
+    println!("Generating {} chunks...", ids.len());
+    let chunks = generate(&seed, Arc::new(config), &ids);
+
+    println!("Meshing...");
+    let meshes = ids
+        .iter()
+        .flat_map(|id| mesh(id, &chunks, mesher))
+        .collect::<Vec<_>>();
+    let valid = !matches.get_flag("validate") || report(&meshes);
+
+    let placed = meshes
+        .iter()
+        .map(|(id, mesh)| (glm::Vec3::from(id), mesh))
+        .collect::<Vec<_>>();
+
+    println!("Writing {}...", output.display());
+    let mut out = BufWriter::new(File::create(output)?);
+    export::write(format, &mut out, &placed)?;
+
+    if !valid {
+        eprintln!("Found broken meshes");
+        std::process::exit(1);
+    }
+    println!("Done");
+    Ok(())
+}
+
+/**
+ * Generates the chunks of the region. Structures reaching out of the region are cut off.
+ */
+fn generate(seed: &WorldSeed, config: Arc<WorldGenConfig>, ids: &[ChunkId]) -> ChunkManager {
+    let mut chunks = ChunkManager::new();
+    for id in ids {
+        let generated =
+            Chunk::generate(ChunkSeed::with_config(seed, id, config.clone())).voxelize();
+        chunks.insert(id, compress(&generated.voxels));
+    }
+    chunks
+}
+
+/**
+ * Prints the statistics and problems of every mesh, returns whether all of them are valid.
+ */
+fn report(meshes: &[(ChunkId, Mesh)]) -> bool {
+    let mut valid = true;
+    for (id, mesh) in meshes {
+        println!("{:?}: {}", id, MeshStats::of(mesh));
+        for error in validate(mesh) {
+            println!("  {}", error);
+            valid = false;
+        }
+    }
+    valid
+}
+
+/**
+ * Meshes of every render layer of a chunk. Faces toward chunks outside of the region are meshed.
+ */
+fn mesh(id: &ChunkId, chunks: &ChunkManager, mesher: Mesher) -> Vec<(ChunkId, Mesh)> {
+    let Some(neighborhood) = ChunkNeighborhood::gather(id, |id| chunks.get(id).map(Cow::Borrowed))
+    else {
+        return vec![];
+    };
+    let blocks = neighborhood.padded();
+
+    RenderLayer::ALL
+        .into_iter()
+        .map(|layer| match (layer, mesher) {
+            (RenderLayer::Opaque, Mesher::Binary) => generate_binary_mesh(id, &blocks),
+            (RenderLayer::Opaque, Mesher::SurfaceNets) => generate_surface_nets_mesh(id, &blocks),
+            _ => generate_layer_mesh(id, &blocks, layer),
+        })
+        .filter(|mesh| !mesh.vertices.is_empty())
+        .map(|mesh| (*id, mesh))
+        .collect()
+}
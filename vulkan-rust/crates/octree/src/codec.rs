@@ -0,0 +1,463 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    error::Error,
+    fmt,
+    hash::Hash,
+    mem::size_of,
+};
+
+use crate::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node};
+
+pub const CODEC_VERSION: u8 = 1;
+
+const TAG_BITS: u32 = 2;
+const TAG_EMPTY: usize = 0;
+const TAG_FULL: usize = 1;
+const TAG_SPARSE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    UnsupportedVersion(u8),
+    InvalidTag(usize),
+    InvalidValue,
+    InvalidIndex(usize),
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported codec version {v}"),
+            Self::InvalidTag(tag) => write!(f, "invalid node tag {tag}"),
+            Self::InvalidValue => write!(f, "invalid value in value table"),
+            Self::InvalidIndex(i) => write!(f, "value index {i} out of range"),
+            Self::TrailingBytes => write!(f, "trailing bytes after tree"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/**
+ * Fixed size binary representation of the values stored in a tree.
+ * Stricter than the `Copy + PartialEq` of the leaves: the encoder looks up the index of
+ * every leaf value in a hash map, a linear search of the table would be quadratic for
+ * trees with many distinct values. Float values can be stored through their bits.
+ */
+pub trait ValueCodec: Copy + Eq + Hash {
+    const SIZE: usize;
+
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_value_codec {
+    ($($t:ty),*) => {
+        $(
+            impl ValueCodec for $t {
+                const SIZE: usize = size_of::<$t>();
+
+                fn write(&self, out: &mut Vec<u8>) {
+                    out.extend(self.to_le_bytes());
+                }
+
+                fn read(bytes: &[u8]) -> Option<Self> {
+                    Some(Self::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_value_codec!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/**
+ * Versioned bitstream encoding of a tree.
+ *
+ * Layout: version byte, value count (u32 le) and the table of distinct values in order of
+ * appearance, followed by all nodes in pre-order. Every node is a 2 bit tag, full nodes
+ * carry an index into the value table. Leaves of sparse L1 nodes are a presence bit
+ * followed by an index. Indices use as few bits as the table size allows.
+ */
+pub trait Codec: Sized {
+    fn encode(&self) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError>;
+}
+
+/**
+ * Distinct values of a tree in order of appearance. The index finds values while writing.
+ */
+struct ValueTable<T> {
+    values: Vec<T>,
+    index: HashMap<T, usize>,
+}
+
+impl<T: ValueCodec> ValueTable<T> {
+    fn new() -> Self {
+        Self {
+            values: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    /**
+     * Table of decoded values, only used for reading.
+     */
+    fn from_values(values: Vec<T>) -> Self {
+        Self {
+            values,
+            index: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, value: T) {
+        if let Entry::Vacant(entry) = self.index.entry(value) {
+            entry.insert(self.values.len());
+            self.values.push(value);
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        usize::BITS - self.values.len().saturating_sub(1).leading_zeros()
+    }
+
+    fn write(&self, writer: &mut BitWriter, value: T) {
+        let index = self.index[&value];
+        writer.write(index, self.bits());
+    }
+
+    fn read(&self, reader: &mut BitReader) -> Result<T, DecodeError> {
+        let index = reader.read(self.bits())?;
+        self.values
+            .get(index)
+            .copied()
+            .ok_or(DecodeError::InvalidIndex(index))
+    }
+}
+
+/**
+ * Writes bits from least to most significant, padding the last byte with zeros.
+ */
+struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    buffer: u64,
+    len: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            buffer: 0,
+            len: 0,
+        }
+    }
+
+    fn write(&mut self, value: usize, bits: u32) {
+        debug_assert!(bits <= 32);
+        debug_assert!(value >> bits == 0);
+
+        self.buffer |= (value as u64) << self.len;
+        self.len += bits;
+        while self.len >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(self) {
+        if self.len > 0 {
+            self.out.push(self.buffer as u8);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> Result<usize, DecodeError> {
+        let mut value = 0;
+        for i in 0..bits {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or(DecodeError::UnexpectedEnd)?;
+            value |= ((byte >> (self.position % 8)) as usize & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn finish(self) -> Result<(), DecodeError> {
+        if (self.position + 7) / 8 == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+trait NodeBits<T: ValueCodec>: Sized {
+    fn collect_values(&self, table: &mut ValueTable<T>);
+    fn write_bits(&self, writer: &mut BitWriter, table: &ValueTable<T>);
+    fn read_bits(reader: &mut BitReader, table: &ValueTable<T>) -> Result<Self, DecodeError>;
+}
+
+impl<T: ValueCodec> NodeBits<T> for L1Node<T> {
+    fn collect_values(&self, table: &mut ValueTable<T>) {
+        match self {
+            Self::Empty => {}
+            Self::Full(value) => table.insert(*value),
+            Self::Sparse(leaves) => leaves.iter().flatten().for_each(|v| table.insert(*v)),
+        }
+    }
+
+    fn write_bits(&self, writer: &mut BitWriter, table: &ValueTable<T>) {
+        match self {
+            Self::Empty => writer.write(TAG_EMPTY, TAG_BITS),
+            Self::Full(value) => {
+                writer.write(TAG_FULL, TAG_BITS);
+                table.write(writer, *value);
+            }
+            Self::Sparse(leaves) => {
+                writer.write(TAG_SPARSE, TAG_BITS);
+                for leaf in leaves {
+                    writer.write(leaf.is_some() as usize, 1);
+                    if let Some(value) = leaf {
+                        table.write(writer, *value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_bits(reader: &mut BitReader, table: &ValueTable<T>) -> Result<Self, DecodeError> {
+        match reader.read(TAG_BITS)? {
+            TAG_EMPTY => Ok(Self::Empty),
+            TAG_FULL => Ok(Self::Full(table.read(reader)?)),
+            TAG_SPARSE => {
+                let mut leaves = [None; 8];
+                for leaf in leaves.iter_mut() {
+                    if reader.read(1)? == 1 {
+                        *leaf = Some(table.read(reader)?);
+                    }
+                }
+                Ok(Self::Sparse(leaves))
+            }
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+}
+
+macro_rules! impl_node_bits {
+    ($node:ident, $child:ident) => {
+        impl<T: ValueCodec> NodeBits<T> for $node<T> {
+            fn collect_values(&self, table: &mut ValueTable<T>) {
+                match self {
+                    Self::Empty => {}
+                    Self::Full(value) => table.insert(*value),
+                    Self::Sparse(children) => children.iter().for_each(|c| c.collect_values(table)),
+                }
+            }
+
+            fn write_bits(&self, writer: &mut BitWriter, table: &ValueTable<T>) {
+                match self {
+                    Self::Empty => writer.write(TAG_EMPTY, TAG_BITS),
+                    Self::Full(value) => {
+                        writer.write(TAG_FULL, TAG_BITS);
+                        table.write(writer, *value);
+                    }
+                    Self::Sparse(children) => {
+                        writer.write(TAG_SPARSE, TAG_BITS);
+                        children.iter().for_each(|c| c.write_bits(writer, table));
+                    }
+                }
+            }
+
+            fn read_bits(
+                reader: &mut BitReader,
+                table: &ValueTable<T>,
+            ) -> Result<Self, DecodeError> {
+                match reader.read(TAG_BITS)? {
+                    TAG_EMPTY => Ok(Self::Empty),
+                    TAG_FULL => Ok(Self::Full(table.read(reader)?)),
+                    TAG_SPARSE => Ok(Self::Sparse(Box::new([
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                        $child::read_bits(reader, table)?,
+                    ]))),
+                    tag => Err(DecodeError::InvalidTag(tag)),
+                }
+            }
+        }
+    };
+}
+
+impl_node_bits!(L2Node, L1Node);
+impl_node_bits!(L3Node, L2Node);
+impl_node_bits!(L4Node, L3Node);
+impl_node_bits!(L5Node, L4Node);
+impl_node_bits!(L6Node, L5Node);
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < n {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+
+    let (bytes, rest) = input.split_at(n);
+    *input = rest;
+    Ok(bytes)
+}
+
+macro_rules! impl_codec {
+    ($($node:ident),*) => {
+        $(
+            impl<T: ValueCodec> Codec for $node<T> {
+                fn encode(&self) -> Vec<u8> {
+                    let mut table = ValueTable::new();
+                    self.collect_values(&mut table);
+
+                    let mut out = vec![CODEC_VERSION];
+                    out.extend((table.values.len() as u32).to_le_bytes());
+                    table.values.iter().for_each(|v| v.write(&mut out));
+
+                    let mut writer = BitWriter::new(&mut out);
+                    self.write_bits(&mut writer, &table);
+                    writer.finish();
+
+                    out
+                }
+
+                fn decode(mut bytes: &[u8]) -> Result<Self, DecodeError> {
+                    let version = take(&mut bytes, 1)?[0];
+                    if version != CODEC_VERSION {
+                        return Err(DecodeError::UnsupportedVersion(version));
+                    }
+
+                    let count = u32::from_le_bytes(
+                        take(&mut bytes, 4)?.try_into().expect("Length was checked"),
+                    ) as usize;
+                    let mut values = Vec::with_capacity(count.min(bytes.len()));
+                    for _ in 0..count {
+                        let value =
+                            T::read(take(&mut bytes, T::SIZE)?).ok_or(DecodeError::InvalidValue)?;
+                        values.push(value);
+                    }
+
+                    let table = ValueTable::from_values(values);
+                    let mut reader = BitReader::new(bytes);
+                    let node = Self::read_bits(&mut reader, &table)?;
+                    reader.finish()?;
+
+                    Ok(node)
+                }
+            }
+        )*
+    };
+}
+
+impl_codec!(L1Node, L2Node, L3Node, L4Node, L5Node, L6Node);
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{Codec, DecodeError, CODEC_VERSION};
+    use crate::{L1Node, L2Node, L3Node, L6Node, LeafAccess};
+
+    fn random_tree(seed: u64, count: usize, values: u32) -> L6Node<u32> {
+        let mut random = StdRng::seed_from_u64(seed);
+        let mut tree = L6Node::Empty;
+        for _ in 0..count {
+            let x = random.gen_range(0..64);
+            let y = random.gen_range(0..64);
+            let z = random.gen_range(0..64);
+            tree.set(x, y, z, random.gen_range(0..values));
+        }
+        tree
+    }
+
+    #[test]
+    fn round_trip_all_levels() {
+        let mut l1 = L1Node::<u8>::Empty;
+        l1.set(1, 0, 1, 3);
+        assert_eq!(L1Node::decode(&l1.encode()), Ok(l1));
+
+        let mut l2 = L2Node::<u16>::Full(7);
+        l2.set(3, 3, 0, 1000);
+        assert_eq!(L2Node::decode(&l2.encode()), Ok(l2));
+
+        let mut l3 = L3Node::<i64>::Empty;
+        l3.set(0, 7, 2, -5);
+        l3.set(6, 1, 2, i64::MAX);
+        assert_eq!(L3Node::decode(&l3.encode()), Ok(l3));
+
+        for seed in 0..4 {
+            let tree = random_tree(seed, 2000, 1 << (seed * 3));
+            assert_eq!(L6Node::decode(&tree.encode()), Ok(tree));
+        }
+    }
+
+    #[test]
+    fn uniform_trees_are_tiny() {
+        // version + count + tag byte
+        assert_eq!(
+            L6Node::<u8>::Empty.encode(),
+            vec![CODEC_VERSION, 0, 0, 0, 0, 0]
+        );
+        // version + count + value + tag byte, the index needs no bits
+        assert_eq!(
+            L6Node::<u8>::Full(9).encode(),
+            vec![CODEC_VERSION, 1, 0, 0, 0, 9, 1]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let bytes = random_tree(1, 100, 4).encode();
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = CODEC_VERSION + 1;
+        assert_eq!(
+            L6Node::<u32>::decode(&wrong_version),
+            Err(DecodeError::UnsupportedVersion(CODEC_VERSION + 1))
+        );
+
+        assert_eq!(
+            L6Node::<u32>::decode(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            L6Node::<u32>::decode(&trailing),
+            Err(DecodeError::TrailingBytes)
+        );
+
+        // a full root without any values in the table
+        assert_eq!(
+            L6Node::<u8>::decode(&[CODEC_VERSION, 0, 0, 0, 0, 1]),
+            Err(DecodeError::InvalidIndex(0))
+        );
+        assert_eq!(
+            L6Node::<u8>::decode(&[CODEC_VERSION, 0, 0, 0, 0, 3]),
+            Err(DecodeError::InvalidTag(3))
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::array;
+
+use crate::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node, LeafAccess};
+
+/**
+ * Coarser copies of a tree, used for levels of detail.
+ * `pick` chooses the value representing 8 child values, `None` stands for empty children.
+ */
+pub trait Collapse<T: Copy + PartialEq>: Sized {
+    /**
+     * Single value representing the whole node.
+     */
+    fn collapsed<F>(&self, pick: &F) -> Option<T>
+    where
+        F: Fn(&[Option<T>; 8]) -> Option<T>;
+
+    /**
+     * Copy of the tree where every cube of `1 << level` voxels is full of its representative.
+     */
+    fn collapse<F>(&self, level: usize, pick: &F) -> Self
+    where
+        F: Fn(&[Option<T>; 8]) -> Option<T>;
+}
+
+impl<T: Copy + PartialEq> Collapse<T> for L1Node<T> {
+    fn collapsed<F>(&self, pick: &F) -> Option<T>
+    where
+        F: Fn(&[Option<T>; 8]) -> Option<T>,
+    {
+        match self {
+            Self::Full(value) => Some(*value),
+            Self::Sparse(values) => pick(values),
+            Self::Empty => None,
+        }
+    }
+
+    fn collapse<F>(&self, level: usize, pick: &F) -> Self
+    where
+        F: Fn(&[Option<T>; 8]) -> Option<T>,
+    {
+        if level < Self::LEVEL {
+            return self.clone();
+        }
+
+        match self.collapsed(pick) {
+            Some(value) => Self::Full(value),
+            None => Self::Empty,
+        }
+    }
+}
+
+macro_rules! impl_collapse {
+    ($node:ident) => {
+        impl<T: Copy + PartialEq> Collapse<T> for $node<T> {
+            fn collapsed<F>(&self, pick: &F) -> Option<T>
+            where
+                F: Fn(&[Option<T>; 8]) -> Option<T>,
+            {
+                match self {
+                    Self::Full(value) => Some(*value),
+                    Self::Sparse(children) => {
+                        pick(&array::from_fn(|i| children[i].collapsed(pick)))
+                    }
+                    Self::Empty => None,
+                }
+            }
+
+            fn collapse<F>(&self, level: usize, pick: &F) -> Self
+            where
+                F: Fn(&[Option<T>; 8]) -> Option<T>,
+            {
+                if level >= Self::LEVEL {
+                    return match self.collapsed(pick) {
+                        Some(value) => Self::Full(value),
+                        None => Self::Empty,
+                    };
+                }
+
+                match self {
+                    Self::Sparse(children) => Self::Sparse(Box::new(array::from_fn(|i| {
+                        children[i].collapse(level, pick)
+                    }))),
+                    _ => self.clone(),
+                }
+            }
+        }
+    };
+}
+
+impl_collapse!(L2Node);
+impl_collapse!(L3Node);
+impl_collapse!(L4Node);
+impl_collapse!(L5Node);
+impl_collapse!(L6Node);
+
+#[cfg(test)]
+mod test {
+    use super::Collapse;
+    use crate::{L3Node, L6Node, LeafAccess};
+
+    fn maximum(values: &[Option<u8>; 8]) -> Option<u8> {
+        values.iter().flatten().max().copied()
+    }
+
+    #[test]
+    fn collapses_cubes_of_level() {
+        let mut tree = L6Node::<u8>::Empty;
+        tree.set(1, 0, 0, 3);
+        tree.set(2, 2, 2, 5);
+        tree.set(40, 40, 40, 7);
+
+        let coarse = tree.collapse(2, &maximum);
+        for (x, y, z) in [(0, 0, 0), (3, 3, 3), (1, 2, 3)] {
+            assert_eq!(coarse.get(x, y, z), Some(5));
+        }
+        assert_eq!(coarse.get(4, 0, 0), None);
+        assert_eq!(coarse.get(43, 43, 43), Some(7));
+        assert_eq!(coarse.get(44, 40, 40), None);
+
+        assert_eq!(tree.collapse(0, &maximum), tree);
+        assert_eq!(tree.collapse(6, &maximum), L6Node::Full(7));
+    }
+
+    #[test]
+    fn full_and_empty_nodes_are_kept() {
+        let mut tree = L3Node::<u8>::Full(2);
+        tree.set(7, 7, 7, 9);
+        let coarse = tree.collapse(1, &maximum);
+        assert_eq!(coarse.get(0, 0, 0), Some(2));
+        assert_eq!(coarse.get(6, 6, 6), Some(9));
+
+        assert_eq!(
+            L6Node::<u8>::Empty.collapse(3, &maximum),
+            L6Node::<u8>::Empty
+        );
+    }
+}
@@ -2,6 +2,14 @@
 
 extern crate test;
 
+mod codec;
+mod collapse;
+mod regions;
+
+pub use codec::{Codec, DecodeError, ValueCodec, CODEC_VERSION};
+pub use collapse::Collapse;
+pub use regions::Regions;
+
 /**
  * Generic node in an octree
  */
@@ -210,11 +218,47 @@ impl_leaf_access!(6, L6Node, L5Node);
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
     use std::mem::size_of;
     use test::Bencher;
 
-    use crate::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node, LeafAccess};
+    use crate::{Codec, L1Node, L2Node, L3Node, L4Node, L5Node, L6Node, LeafAccess};
+
+    const RAW_SIZE: usize = 64 * 64 * 64;
+
+    /**
+     * Rolling hills of stone with a layer of dirt and grass on top.
+     */
+    fn terrain() -> L6Node<u8> {
+        let mut tree = L6Node::<u8>::Empty;
+        for y in 0..64 {
+            for x in 0..64 {
+                let height =
+                    24 + ((x as f32 * 0.2).sin() * 6.0 + (y as f32 * 0.15).cos() * 5.0) as usize;
+                for z in 0..height {
+                    let m = match height - z {
+                        1 => 3,
+                        2..=4 => 2,
+                        _ => 1,
+                    };
+                    tree.set(x, y, z, m)
+                }
+            }
+        }
+        tree
+    }
+
+    fn noise() -> L6Node<u8> {
+        let mut tree = L6Node::<u8>::Empty;
+        let mut random = StdRng::seed_from_u64(17);
+        for _ in 0..RAW_SIZE / 8 {
+            let x = random.gen_range(0..64);
+            let y = random.gen_range(0..64);
+            let z = random.gen_range(0..64);
+            tree.set(x, y, z, random.gen_range(1..16));
+        }
+        tree
+    }
 
     #[test]
     fn can_find_voxel() {
@@ -285,6 +329,54 @@ mod tests {
         });
     }
 
+    #[test]
+    fn encoded_size() {
+        assert!(terrain().encode().len() < RAW_SIZE / 32);
+        assert!(noise().encode().len() < RAW_SIZE / 4);
+    }
+
+    // Throughput is reported relative to a raw 64³ array of bytes.
+    // Sizes of chunks from the world generator are checked by the chunk codec of the world.
+    #[bench]
+    fn raw_array_copy(b: &mut Bencher) {
+        let raw = vec![1u8; RAW_SIZE];
+        b.bytes = RAW_SIZE as u64;
+
+        b.iter(|| test::black_box(raw.clone()));
+    }
+
+    #[bench]
+    fn terrain_encode(b: &mut Bencher) {
+        let tree = terrain();
+        b.bytes = RAW_SIZE as u64;
+
+        b.iter(|| test::black_box(tree.encode()));
+    }
+
+    #[bench]
+    fn terrain_decode(b: &mut Bencher) {
+        let bytes = terrain().encode();
+        b.bytes = RAW_SIZE as u64;
+
+        b.iter(|| test::black_box(L6Node::<u8>::decode(&bytes)));
+    }
+
+    #[bench]
+    fn noise_encode(b: &mut Bencher) {
+        let tree = noise();
+        b.bytes = RAW_SIZE as u64;
+
+        b.iter(|| test::black_box(tree.encode()));
+    }
+
+    #[bench]
+    fn noise_decode(b: &mut Bencher) {
+        let bytes = noise().encode();
+        b.bytes = RAW_SIZE as u64;
+
+        b.iter(|| test::black_box(L6Node::<u8>::decode(&bytes)));
+    }
+
     #[test]
     fn enum_transitions_1() {
         let mut vd1 = L1Node::<u8>::Empty;
@@ -0,0 +1,101 @@
+use crate::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node, LeafAccess};
+
+#[inline]
+fn child_origin(origin: [usize; 3], i: usize, half: usize) -> [usize; 3] {
+    [
+        origin[0] + (i & 1) * half,
+        origin[1] + ((i >> 1) & 1) * half,
+        origin[2] + ((i >> 2) & 1) * half,
+    ]
+}
+
+/**
+ * Traversal of the uniform regions of a tree: full and empty nodes and the voxels of sparse leaves.
+ * The interior of uniform regions is never visited.
+ */
+pub trait Regions<T: Copy + PartialEq> {
+    /**
+     * Calls f with the origin, size and value of every region, children in index order.
+     */
+    fn for_each_region<F>(&self, origin: [usize; 3], f: &mut F)
+    where
+        F: FnMut([usize; 3], usize, Option<T>);
+}
+
+impl<T: Copy + PartialEq> Regions<T> for L1Node<T> {
+    fn for_each_region<F>(&self, origin: [usize; 3], f: &mut F)
+    where
+        F: FnMut([usize; 3], usize, Option<T>),
+    {
+        match self {
+            Self::Full(value) => f(origin, Self::SIZE, Some(*value)),
+            Self::Sparse(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    f(child_origin(origin, i, 1), 1, *value);
+                }
+            }
+            Self::Empty => f(origin, Self::SIZE, None),
+        }
+    }
+}
+
+macro_rules! impl_regions {
+    ($node:ident) => {
+        impl<T: Copy + PartialEq> Regions<T> for $node<T> {
+            fn for_each_region<F>(&self, origin: [usize; 3], f: &mut F)
+            where
+                F: FnMut([usize; 3], usize, Option<T>),
+            {
+                match self {
+                    Self::Full(value) => f(origin, Self::SIZE, Some(*value)),
+                    Self::Sparse(children) => {
+                        for (i, child) in children.iter().enumerate() {
+                            child.for_each_region(child_origin(origin, i, Self::SIZE / 2), f);
+                        }
+                    }
+                    Self::Empty => f(origin, Self::SIZE, None),
+                }
+            }
+        }
+    };
+}
+
+impl_regions!(L2Node);
+impl_regions!(L3Node);
+impl_regions!(L4Node);
+impl_regions!(L5Node);
+impl_regions!(L6Node);
+
+#[cfg(test)]
+mod test {
+    use super::Regions;
+    use crate::{L6Node, LeafAccess};
+
+    #[test]
+    fn regions_cover_the_tree() {
+        let mut tree = L6Node::<u8>::Full(1);
+        tree.set(63, 0, 5, 2);
+        tree.set(10, 20, 30, 3);
+
+        let mut volume = 0;
+        let mut regions = 0;
+        tree.for_each_region([0, 0, 0], &mut |origin, size, value| {
+            volume += size.pow(3);
+            regions += 1;
+            for p in [[0, 0, 0], [size - 1, size - 1, size - 1]] {
+                let (x, y, z) = (origin[0] + p[0], origin[1] + p[1], origin[2] + p[2]);
+                assert_eq!(tree.get(x, y, z), value);
+            }
+        });
+        assert_eq!(volume, 64 * 64 * 64);
+        // 6 full top level children, then 7 full siblings per level and 8 voxels per changed voxel
+        assert_eq!(regions, 6 + 2 * (4 * 7 + 8));
+
+        let mut count = 0;
+        L6Node::<u8>::Empty.for_each_region([0, 0, 0], &mut |origin, size, value| {
+            assert_eq!((origin, size, value), ([0, 0, 0], 64, None));
+            count += 1;
+        });
+        assert_eq!(count, 1);
+    }
+}
@@ -4,12 +4,16 @@ pub const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
 pub const RED: [u8; 4] = [255, 0, 0, 255];
 pub const WHITE: [u8; 4] = [255, 255, 255, 255];
 pub const WATER: [u8; 4] = [10, 98, 225, 200];
+pub const GLASS: [u8; 4] = [200, 225, 235, 90];
 pub const STONE: [u8; 4] = [91, 93, 108, 255];
 pub const GRASS: [u8; 4] = [130, 186, 23, 255];
 pub const SAND: [u8; 4] = [195, 194, 155, 255];
 pub const WOOD: [u8; 4] = [133, 97, 56, 255];
 pub const LEAVES: [u8; 4] = [37, 95, 36, 255];
 pub const DIRT: [u8; 4] = [155, 132, 69, 255];
+pub const LAMP: [u8; 4] = [255, 214, 120, 255];
+pub const CRYSTAL: [u8; 4] = [140, 90, 230, 255];
+pub const GRAVEL: [u8; 4] = [128, 122, 116, 255];
 pub const SKY: [u8; 4] = [80, 120, 254, 255];
 
 macro_rules! color {
@@ -30,6 +34,7 @@ color!(transparent, TRANSPARENT);
 color!(red, RED);
 color!(white, WHITE);
 color!(water, WATER);
+color!(glass, GLASS);
 color!(stone, STONE);
 color!(grass, GRASS);
 color!(sand, SAND);
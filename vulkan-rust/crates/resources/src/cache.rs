@@ -48,7 +48,7 @@ fn load_all_vox_models() -> HashMap<String, VoxModels<Model>> {
 }
 
 fn load_all_images() -> HashMap<String, (OutputInfo, Vec<u8>)> {
-    let img_paths = ["\\assets\\palette.png", "\\assets\\tileset.png"];
+    let img_paths = ["\\assets\\palette.png", "\\assets\\atlas.png"];
     let mut img = HashMap::with_capacity(img_paths.len());
     let working_dir = env::current_dir().unwrap().to_str().unwrap().to_owned();
     for path in img_paths {
@@ -8,8 +8,8 @@ use crate::{
     constants::{MAX_FRAMES_IN_FLIGHT, VALIDATION_ENABLED},
     device::{physical, pick_device},
     image::{
-        create_depth_objects, create_texture_image, create_texture_image_view,
-        create_texture_sampler,
+        create_atlas_image, create_atlas_image_view, create_depth_objects, create_texture_image,
+        create_texture_image_view, create_texture_sampler,
     },
     instance::create_instance,
     pipeline::create_pipeline,
@@ -89,6 +89,13 @@ impl App {
             CACHE.get_img("\\assets\\palette.png"),
         )?;
         create_texture_image_view(&logical_device.device, &mut data)?;
+        create_atlas_image(
+            &instance,
+            &logical_device.device,
+            &mut data,
+            CACHE.get_img("\\assets\\atlas.png"),
+        )?;
+        create_atlas_image_view(&logical_device.device, &mut data)?;
         create_texture_sampler(&logical_device.device, &mut data)?;
         // load_model(&mut data)?;
         // load_world(&mut data, voxels)?;
@@ -151,7 +158,14 @@ impl App {
 
         let vp = self.calculate_vp(cam);
 
-        self.update_command_buffer(image_index, meshes, &vp, prepared_meshes, deletion_queue)?;
+        self.update_command_buffer(
+            image_index,
+            meshes,
+            &vp,
+            &cam.cam.position,
+            prepared_meshes,
+            deletion_queue,
+        )?;
         self.update_uniform_buffer(image_index, vp, glm::vec3_to_vec4(&cam.cam.position))?;
 
         let wait_semaphores = &[self.data.image_available_semaphores[self.frame]];
@@ -200,6 +214,7 @@ impl App {
         image_index: usize,
         meshes: &mut BTreeMap<MeshId, usize>,
         vp: &(glm::Mat4, glm::Mat4),
+        eye: &glm::Vec3,
         prepared_meshes: &mut Vec<(MeshId, PreparedMesh)>,
         deletion_queue: &mut VecDeque<Delete>,
     ) -> Result<()> {
@@ -274,9 +289,18 @@ impl App {
         // println!("Rendering {} meshes", meshes.len());
         let (view, proj) = vp;
         let frustum = Frustum::from_mat4(&(proj * view));
-        let secondary_command_buffers = meshes
+        // opaque and cutout layers first, then translucent ones from back to front. Only whole
+        // chunks are sorted, by their centers, the faces of a chunk keep the order of the mesher
+        // and can blend in the wrong order where they overlap
+        let (mut translucent, solid): (Vec<_>, Vec<_>) = meshes
             .iter()
             .filter(|(id, ..)| frustum.intersects_aabb(&AABB::from(id.chunk_id())))
+            .partition(|(id, ..)| id.is_translucent());
+        let distance = |id: &MeshId| glm::distance2(&id.chunk_id().center(), eye);
+        translucent.sort_by(|(a, ..), (b, ..)| distance(b).total_cmp(&distance(a)));
+        let secondary_command_buffers = solid
+            .into_iter()
+            .chain(translucent)
             .filter_map(|(id, index_count)| {
                 match self.update_secondary_command_buffer(image_index, id, *index_count) {
                     Ok(cmd_buffer) => Some(cmd_buffer),
@@ -315,6 +339,11 @@ impl App {
         prepared_mesh: &PreparedMesh,
         deletion_queue: &mut VecDeque<Delete>,
     ) {
+        match prepared_mesh.smooth {
+            true => self.data.smooth_meshes.insert(mesh_id),
+            false => self.data.smooth_meshes.remove(&mesh_id),
+        };
+
         let prev_buffer = self
             .data
             .chunk_vertex_buffers
@@ -372,11 +401,7 @@ impl App {
         let start = WorldPosition::from(id.chunk_id());
         let x = start.x as f32;
         let y = start.y as f32;
-        let mut z = start.z as f32;
-
-        if id.is_water() {
-            z -= 2.0 / 12.0;
-        }
+        let z = start.z as f32;
 
         // let x = start.x as f32 * 1.05;
         // let y = start.y as f32 * 1.05;
@@ -399,11 +424,14 @@ impl App {
 
         self.device.begin_command_buffer(cmd_buffer, &info)?;
 
-        self.device.cmd_bind_pipeline(
-            cmd_buffer,
-            vk::PipelineBindPoint::GRAPHICS,
-            self.data.pipeline,
-        );
+        let pipeline = match (id.is_translucent(), self.data.smooth_meshes.contains(id)) {
+            (true, true) => self.data.translucent_smooth_pipeline,
+            (true, false) => self.data.translucent_pipeline,
+            (false, true) => self.data.smooth_pipeline,
+            (false, false) => self.data.pipeline,
+        };
+        self.device
+            .cmd_bind_pipeline(cmd_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
         self.device
             .cmd_bind_vertex_buffers(cmd_buffer, 0, &[vertex_buffer], &[0]);
         self.device.cmd_bind_index_buffer(
@@ -512,6 +540,10 @@ impl App {
         self.device.destroy_image(self.data.texture_image, None);
         self.device
             .free_memory(self.data.texture_image_memory, None);
+        self.device
+            .destroy_image_view(self.data.atlas_image_view, None);
+        self.device.destroy_image(self.data.atlas_image, None);
+        self.device.free_memory(self.data.atlas_image_memory, None);
 
         self.device
             .destroy_descriptor_set_layout(self.data.descriptor_set_layout, None);
@@ -571,6 +603,7 @@ impl App {
         self.data.chunk_index_buffer_memory.clear();
         self.data.chunk_vertex_buffers.clear();
         self.data.chunk_vertex_buffers_memory.clear();
+        self.data.smooth_meshes.clear();
     }
 
     pub unsafe fn unload_single_chunk(&mut self, mesh_id: &MeshId) {
@@ -587,6 +620,7 @@ impl App {
         if let Some(memory) = self.data.chunk_vertex_buffers_memory.remove(mesh_id) {
             self.device.free_memory(memory, None);
         }
+        self.data.smooth_meshes.remove(mesh_id);
     }
 
     unsafe fn destroy_swapchain(&mut self) {
@@ -609,6 +643,12 @@ impl App {
             .iter()
             .for_each(|f| self.device.destroy_framebuffer(*f, None));
         self.device.destroy_pipeline(self.data.pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.translucent_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.smooth_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.translucent_smooth_pipeline, None);
         self.device
             .destroy_pipeline_layout(self.data.pipeline_layout, None);
         self.device.destroy_render_pass(self.data.render_pass, None);
@@ -4,7 +4,10 @@ use crate::{
 };
 use anyhow::Result;
 use graphics::Vertex;
-use std::{collections::HashMap, mem::size_of};
+use std::{
+    collections::{HashMap, HashSet},
+    mem::size_of,
+};
 use vulkanalia::prelude::v1_0::*;
 use world::MeshId;
 
@@ -25,6 +28,9 @@ pub struct AppData {
     pub descriptor_set_layout: vk::DescriptorSetLayout,
     pub pipeline_layout: vk::PipelineLayout,
     pub pipeline: vk::Pipeline,
+    pub translucent_pipeline: vk::Pipeline,
+    pub smooth_pipeline: vk::Pipeline,
+    pub translucent_smooth_pipeline: vk::Pipeline,
     pub framebuffers: Vec<vk::Framebuffer>,
     pub command_pool: vk::CommandPool,
     pub command_pools: Vec<vk::CommandPool>,
@@ -48,6 +54,8 @@ pub struct AppData {
     pub chunk_vertex_buffers_memory: HashMap<MeshId, vk::DeviceMemory>,
     pub chunk_index_buffer: HashMap<MeshId, vk::Buffer>,
     pub chunk_index_buffer_memory: HashMap<MeshId, vk::DeviceMemory>,
+    /// Chunk meshes with full vertices, drawn with the smooth pipeline
+    pub smooth_meshes: HashSet<MeshId>,
 
     pub uniform_buffers: Vec<vk::Buffer>,
     pub uniform_buffers_memory: Vec<vk::DeviceMemory>,
@@ -57,6 +65,9 @@ pub struct AppData {
     pub texture_image_memory: vk::DeviceMemory,
     pub texture_image_view: vk::ImageView,
     pub texture_sampler: vk::Sampler,
+    pub atlas_image: vk::Image,
+    pub atlas_image_memory: vk::DeviceMemory,
+    pub atlas_image_view: vk::ImageView,
     pub depth_image: vk::Image,
     pub depth_image_memory: vk::DeviceMemory,
     pub depth_image_view: vk::ImageView,
@@ -110,9 +121,10 @@ pub unsafe fn create_descriptor_pool(device: &Device, data: &mut AppData) -> Res
         .type_(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(data.swapchain_images.len() as u32);
 
+    // palette and atlas
     let sampler_size = vk::DescriptorPoolSize::builder()
         .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(data.swapchain_images.len() as u32);
+        .descriptor_count(data.swapchain_images.len() as u32 * 2);
 
     let pool_sizes = &[ubo_size, sampler_size];
     let info = vk::DescriptorPoolCreateInfo::builder()
@@ -159,8 +171,20 @@ pub unsafe fn create_descriptor_sets(device: &Device, data: &mut AppData) -> Res
             .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
             .image_info(image_info);
 
+        let info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(data.atlas_image_view)
+            .sampler(data.texture_sampler);
+        let atlas_info = &[info];
+        let atlas_sampler_write = vk::WriteDescriptorSet::builder()
+            .dst_set(data.descriptor_sets[i])
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(atlas_info);
+
         device.update_descriptor_sets(
-            &[ubo_write, palette_sampler_write],
+            &[ubo_write, palette_sampler_write, atlas_sampler_write],
             &[] as &[vk::CopyDescriptorSet],
         );
     }
@@ -4,7 +4,7 @@ use crate::{
     image::{begin_single_time_commands, end_single_time_commands},
 };
 use anyhow::Result;
-use graphics::{Mesh, Vertex};
+use graphics::{ChunkMesh, Mesh, Vertex};
 use std::{mem::size_of, ptr::copy_nonoverlapping as memcpy};
 use vulkanalia::{
     prelude::v1_0::*,
@@ -288,24 +288,39 @@ pub struct PreparedBuffer {
 pub struct PreparedMesh {
     pub vertex: PreparedBuffer,
     pub index: PreparedBuffer,
+    /// Whether the vertices are full instead of packed
+    pub smooth: bool,
 }
 
 pub fn prepare_mesh(
     instance: &Instance,
     device: &Device,
-    mesh: &Mesh,
+    mesh: &ChunkMesh,
     physical_device: &vk::PhysicalDevice,
 ) -> PreparedMesh {
+    let (vertex, indices, smooth) = match mesh {
+        ChunkMesh::Packed(mesh) => (
+            prepare_vertex_buffer(instance, device, &mesh.vertices, physical_device),
+            &mesh.indices,
+            false,
+        ),
+        ChunkMesh::Smooth(mesh) => (
+            prepare_vertex_buffer(instance, device, &mesh.vertices, physical_device),
+            &mesh.indices,
+            true,
+        ),
+    };
     PreparedMesh {
-        vertex: prepare_vertex_buffer(instance, device, &mesh.vertices, physical_device),
-        index: prepare_index_buffer(instance, device, &mesh.indices, physical_device),
+        vertex,
+        index: prepare_index_buffer(instance, device, indices, physical_device),
+        smooth,
     }
 }
 
-pub fn prepare_vertex_buffer(
+pub fn prepare_vertex_buffer<V>(
     instance: &Instance,
     device: &Device,
-    items: &[Vertex],
+    items: &[V],
     physical_device: &vk::PhysicalDevice,
 ) -> PreparedBuffer {
     unsafe {
@@ -14,6 +14,26 @@ pub unsafe fn create_texture_image(
     data: &mut AppData,
     image: &(OutputInfo, Vec<u8>),
 ) -> Result<()> {
+    (data.texture_image, data.texture_image_memory) = upload_image(instance, device, data, image)?;
+    Ok(())
+}
+
+pub unsafe fn create_atlas_image(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    image: &(OutputInfo, Vec<u8>),
+) -> Result<()> {
+    (data.atlas_image, data.atlas_image_memory) = upload_image(instance, device, data, image)?;
+    Ok(())
+}
+
+unsafe fn upload_image(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    image: &(OutputInfo, Vec<u8>),
+) -> Result<(vk::Image, vk::DeviceMemory)> {
     let (info, pixels) = image;
 
     log!(
@@ -52,13 +72,10 @@ pub unsafe fn create_texture_image(
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     )?;
 
-    data.texture_image = texture_image;
-    data.texture_image_memory = texture_image_memory;
-
     transition_image_layout(
         device,
         data,
-        data.texture_image,
+        texture_image,
         vk::Format::R8G8B8A8_SRGB,
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
@@ -68,7 +85,7 @@ pub unsafe fn create_texture_image(
         device,
         data,
         staging_buffer,
-        data.texture_image,
+        texture_image,
         info.width,
         info.height,
     )?;
@@ -76,7 +93,7 @@ pub unsafe fn create_texture_image(
     transition_image_layout(
         device,
         data,
-        data.texture_image,
+        texture_image,
         vk::Format::R8G8B8A8_SRGB,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
@@ -85,7 +102,7 @@ pub unsafe fn create_texture_image(
     device.destroy_buffer(staging_buffer, None);
     device.free_memory(staging_buffer_memory, None);
 
-    Ok(())
+    Ok((texture_image, texture_image_memory))
 }
 
 pub unsafe fn create_image(
@@ -303,6 +320,17 @@ pub unsafe fn create_texture_image_view(device: &Device, data: &mut AppData) ->
     Ok(())
 }
 
+pub unsafe fn create_atlas_image_view(device: &Device, data: &mut AppData) -> Result<()> {
+    data.atlas_image_view = create_image_view(
+        device,
+        data.atlas_image,
+        vk::Format::R8G8B8A8_SRGB,
+        vk::ImageAspectFlags::COLOR,
+    )?;
+
+    Ok(())
+}
+
 pub unsafe fn create_image_view(
     device: &Device,
     image: vk::Image,
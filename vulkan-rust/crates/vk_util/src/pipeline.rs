@@ -1,21 +1,24 @@
 use crate::{appdata::AppData, shader::create_shader_module};
 use anyhow::Result;
-use graphics::Vertex;
+use graphics::{PackedVertex, Vertex};
 use vulkanalia::prelude::v1_0::*;
 
 // #[cfg(target_os = "windows")]
 pub const SHADER_DIR: &str = "shader/";
 pub const VERT_PATH: &str = "shader/vert.spv";
+pub const SMOOTH_VERT_PATH: &str = "shader/smooth_vert.spv";
 pub const FRAG_PATH: &str = "shader/frag.spv";
 
 pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()> {
     let vert = std::fs::read(VERT_PATH).unwrap();
+    let smooth_vert = std::fs::read(SMOOTH_VERT_PATH).unwrap();
     let frag = std::fs::read(FRAG_PATH).unwrap();
 
     // let vert = include_bytes!("D:/Projects/rust-stuff/vulkan-rust/shader/vert.spv");
     // let frag = include_bytes!("D:/Projects/rust-stuff/vulkan-rust/shader/frag.spv");
 
     let vert_shader_module = create_shader_module(device, &vert[..])?;
+    let smooth_vert_shader_module = create_shader_module(device, &smooth_vert[..])?;
     let frag_shader_module = create_shader_module(device, &frag[..])?;
 
     let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
@@ -23,17 +26,29 @@ pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()>
         .module(vert_shader_module)
         .name(b"main\0");
 
+    let smooth_vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(smooth_vert_shader_module)
+        .name(b"main\0");
+
     let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::FRAGMENT)
         .module(frag_shader_module)
         .name(b"main\0");
 
-    let binding_descriptions = &[Vertex::binding_description()];
-    let attribute_descriptions = Vertex::attribute_descriptions();
+    // block meshes are packed, smooth meshes keep their full vertices
+    let binding_descriptions = &[PackedVertex::binding_description()];
+    let attribute_descriptions = PackedVertex::attribute_descriptions();
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
         .vertex_binding_descriptions(binding_descriptions)
         .vertex_attribute_descriptions(&attribute_descriptions);
 
+    let smooth_binding_descriptions = &[Vertex::binding_description()];
+    let smooth_attribute_descriptions = Vertex::attribute_descriptions();
+    let smooth_vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(smooth_binding_descriptions)
+        .vertex_attribute_descriptions(&smooth_attribute_descriptions);
+
     let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
         .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
         .primitive_restart_enable(false);
@@ -129,11 +144,70 @@ pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()>
         .render_pass(data.render_pass)
         .subpass(0);
 
+    // translucent faces are tested against the depth of everything else, but do not hide what
+    // is drawn behind them later
+    let translucent_depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(false)
+        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    let translucent_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&translucent_depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
+    let smooth_stages = &[smooth_vert_stage, frag_stage];
+    let smooth_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(smooth_stages)
+        .vertex_input_state(&smooth_vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
+    let translucent_smooth_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(smooth_stages)
+        .vertex_input_state(&smooth_vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&translucent_depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
     data.pipeline = device
         .create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)?
         .0;
+    data.translucent_pipeline = device
+        .create_graphics_pipelines(vk::PipelineCache::null(), &[translucent_info], None)?
+        .0;
+    data.smooth_pipeline = device
+        .create_graphics_pipelines(vk::PipelineCache::null(), &[smooth_info], None)?
+        .0;
+    data.translucent_smooth_pipeline = device
+        .create_graphics_pipelines(vk::PipelineCache::null(), &[translucent_smooth_info], None)?
+        .0;
 
     device.destroy_shader_module(vert_shader_module, None);
+    device.destroy_shader_module(smooth_vert_shader_module, None);
     device.destroy_shader_module(frag_shader_module, None);
 
     Ok(())
@@ -0,0 +1,425 @@
+use gamedata::material::Material;
+use graphics::Mesh;
+
+use crate::{
+    chunk_id::ChunkId,
+    light::Light,
+    mesh_generator::{corner_ao, push_quad, Face},
+    traits::Data3D,
+    CHUNK_SIZE, CHUNK_SIZE_SAFE, CHUNK_SIZE_SQUARED,
+};
+
+/// Voxels of the chunk along one axis, bit i of the row at (u, v) is the padded voxel i + 1
+type Rows = [u64; CHUNK_SIZE_SQUARED];
+/// Faces in a layer, one row of u bits per v
+type Plane = [u64; CHUNK_SIZE];
+type Range = std::ops::Range<usize>;
+
+/**
+ * Same surface as the greedy mesher, in full sky light.
+ */
+pub fn generate_binary_mesh<T>(_id: &ChunkId, data: &T) -> Mesh
+where
+    T: Data3D<Material>,
+{
+    binary_mesh(data, |_, _, _| Light::SKY)
+}
+
+/**
+ * Same surface as the lit greedy mesher.
+ */
+pub fn generate_lit_binary_mesh<T, L>(_id: &ChunkId, data: &T, light: &L) -> Mesh
+where
+    T: Data3D<Material>,
+    L: Data3D<Light>,
+{
+    binary_mesh(data, |x, y, z| light.get(x, y, z))
+}
+
+/**
+ * Every row of 64 voxels is a bit mask per material, faces are found with shifts and merged with
+ * bit tricks. Like in the greedy mesher, faces only merge if their material, light and
+ * occlusion agree.
+ */
+fn binary_mesh<T>(data: &T, light: impl Fn(usize, usize, usize) -> Light) -> Mesh
+where
+    T: Data3D<Material>,
+{
+    // opaque voxels in padded columns along each axis, indexed by the padded (u, v) of the column
+    let mut columns = vec![[0_u128; CHUNK_SIZE_SAFE * CHUNK_SIZE_SAFE]; 3];
+    // rows along each axis of the opaque materials in the chunk, indexed by material id
+    let mut materials: Vec<Option<Vec<Rows>>> = vec![None; Material::ALL.len()];
+    for x in 0..CHUNK_SIZE_SAFE {
+        for y in 0..CHUNK_SIZE_SAFE {
+            for z in 0..CHUNK_SIZE_SAFE {
+                let m = data.get(x, y, z);
+                if !m.is_opaque() {
+                    continue;
+                }
+
+                columns[0][z * CHUNK_SIZE_SAFE + y] |= 1 << x;
+                columns[1][x * CHUNK_SIZE_SAFE + z] |= 1 << y;
+                columns[2][y * CHUNK_SIZE_SAFE + x] |= 1 << z;
+
+                if [x, y, z].iter().all(|p| (1..=CHUNK_SIZE).contains(p)) {
+                    let rows = materials[u8::from(m) as usize]
+                        .get_or_insert_with(|| vec![[0; CHUNK_SIZE_SQUARED]; 3]);
+                    let (x, y, z) = (x - 1, y - 1, z - 1);
+                    rows[0][z * CHUNK_SIZE + y] |= 1 << x;
+                    rows[1][x * CHUNK_SIZE + z] |= 1 << y;
+                    rows[2][y * CHUNK_SIZE + x] |= 1 << z;
+                }
+            }
+        }
+    }
+
+    let mut mesh = Mesh::default();
+    // faces of one material, by padded layer of the voxels after them and side
+    let mut planes = vec![[0_u64; CHUNK_SIZE]; CHUNK_SIZE_SAFE * 2];
+    let mut groups = Vec::new();
+
+    for d in 0..3 {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+        let column = |pu: usize, pv: usize| columns[d][pv * CHUNK_SIZE_SAFE + pu];
+
+        // material, light and occlusion of the face in front of a padded voxel
+        let face = |m: Material, back: bool, front: [usize; 3]| -> Face {
+            let ao = corner_ao(|du, dv| {
+                let pu = front[u].wrapping_add_signed(du);
+                let pv = front[v].wrapping_add_signed(dv);
+                column(pu, pv) >> front[d] & 1 == 1
+            });
+            (m, back, light(front[0], front[1], front[2]), ao)
+        };
+        let front_of = |layer: usize, back: bool, column: usize, row: usize| {
+            let mut front = [0; 3];
+            front[d] = layer - back as usize;
+            front[u] = column + 1;
+            front[v] = row + 1;
+            front
+        };
+
+        for (id, rows) in materials.iter().enumerate() {
+            let Some(rows) = rows else {
+                continue;
+            };
+            let m = Material::from(id as u8);
+
+            for row in 0..CHUNK_SIZE {
+                for col in 0..CHUNK_SIZE {
+                    let voxels = rows[d][row * CHUNK_SIZE + col];
+                    if voxels == 0 {
+                        continue;
+                    }
+
+                    // faces toward the voxels after and before, the first back faces belong
+                    // to the chunk before
+                    let opaque = column(col + 1, row + 1);
+                    let front = voxels & !((opaque >> 2) as u64);
+                    let back = voxels & !(opaque as u64) & !1;
+                    for (side, mut bits, offset) in [(0, front, 2), (1, back, 1)] {
+                        while bits != 0 {
+                            let i = bits.trailing_zeros() as usize;
+                            bits &= bits - 1;
+                            planes[(i + offset) * 2 + side][row] |= 1 << col;
+                        }
+                    }
+                }
+            }
+
+            for (i, plane) in planes.iter_mut().enumerate() {
+                let (layer, back) = (i / 2, i % 2 == 1);
+                if plane.iter().all(|bits| *bits == 0) {
+                    continue;
+                }
+
+                let key = |column, row| face(m, back, front_of(layer, back, column, row));
+                mesh_plane(plane, d, layer - 1, key, &mut groups, &mut mesh);
+                *plane = [0; CHUNK_SIZE];
+            }
+        }
+
+        // back faces of the padding voxels after the chunk
+        let last = CHUNK_SIZE_SAFE - 1;
+        let mut plane = [0_u64; CHUNK_SIZE];
+        for (row, bits) in plane.iter_mut().enumerate() {
+            for col in 0..CHUNK_SIZE {
+                if column(col + 1, row + 1) >> (last - 1) & 0b11 == 0b10 {
+                    *bits |= 1 << col;
+                }
+            }
+        }
+        let key = |column, row| {
+            let front = front_of(last, true, column, row);
+            let mut voxel = front;
+            voxel[d] = last;
+            face(data.get(voxel[0], voxel[1], voxel[2]), true, front)
+        };
+        mesh_plane(&plane, d, last - 1, key, &mut groups, &mut mesh);
+    }
+
+    mesh
+}
+
+/**
+ * Splits the faces of a plane by their key and merges each part into quads.
+ * The position is the unpadded coordinate of the plane on axis d.
+ */
+fn mesh_plane(
+    plane: &Plane,
+    d: usize,
+    position: usize,
+    key: impl Fn(usize, usize) -> Face,
+    groups: &mut Vec<(Face, Plane)>,
+    mesh: &mut Mesh,
+) {
+    let u = (d + 1) % 3;
+    let v = (d + 2) % 3;
+
+    groups.clear();
+    let mut last = 0;
+    for (row, bits) in plane.iter().enumerate() {
+        let mut bits = *bits;
+        while bits != 0 {
+            let column = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+
+            // neighboring faces mostly share their key
+            let face = key(column, row);
+            if groups.get(last).map(|(key, _)| *key) != Some(face) {
+                last = match groups.iter().position(|(key, _)| *key == face) {
+                    Some(i) => i,
+                    None => {
+                        groups.push((face, [0; CHUNK_SIZE]));
+                        groups.len() - 1
+                    }
+                };
+            }
+            groups[last].1[row] |= 1 << column;
+        }
+    }
+
+    for (face, rows) in groups.iter_mut() {
+        merge(rows, |u_range, v_range| {
+            let mut start = [0; 3];
+            start[d] = position;
+            start[u] = u_range.start;
+            start[v] = v_range.start;
+            let mut end = start;
+            end[u] = u_range.end;
+            end[v] = v_range.end;
+            push_quad(mesh, *face, d, start, end);
+        });
+    }
+}
+
+/**
+ * Greedily merges the set bits of a plane into rectangles, clearing the plane.
+ */
+fn merge(rows: &mut Plane, mut quad: impl FnMut(Range, Range)) {
+    for v in 0..CHUNK_SIZE {
+        while rows[v] != 0 {
+            let start = rows[v].trailing_zeros() as usize;
+            let width = (rows[v] >> start).trailing_ones() as usize;
+            let run = match width {
+                CHUNK_SIZE => u64::MAX,
+                _ => ((1 << width) - 1) << start,
+            };
+
+            let mut end = v + 1;
+            rows[v] &= !run;
+            while end < CHUNK_SIZE && rows[end] & run == run {
+                rows[end] &= !run;
+                end += 1;
+            }
+            quad(start..start + width, v..end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use test::Bencher;
+
+    use gamedata::material::Material;
+    use graphics::{validate::validate, Mesh};
+
+    use super::{generate_binary_mesh, generate_lit_binary_mesh};
+    use crate::{
+        gen::chunk::Chunk,
+        light::Light,
+        mesh_generator::{generate_greedy_mesh, generate_lit_greedy_mesh},
+        slice::CubeSlice,
+        traits::{Data3D, Generate, Voxelize},
+        ChunkId, ChunkSeed, WorldSeed, CHUNK_SIZE_SAFE,
+    };
+
+    const WORLD_SEED: WorldSeed = WorldSeed::new(17);
+
+    /// Corner, normal and material of a unit face, with the bits of its light and occlusion
+    type UnitFace = ([i32; 3], [i32; 3], i32, [u32; 2], [u32; 4]);
+
+    /**
+     * Unit faces covered by the quads of a mesh. Merged faces share their light and occlusion,
+     * so every unit face carries those of its quad.
+     */
+    fn faces(mesh: &Mesh) -> BTreeSet<UnitFace> {
+        let mut faces = BTreeSet::new();
+        for quad in mesh.vertices.chunks(4) {
+            let corner = |f: fn(f32, f32) -> f32| {
+                quad.iter()
+                    .map(|v| v.pos_mat.xyz())
+                    .reduce(|a, b| a.zip_map(&b, f))
+                    .unwrap()
+                    .map(|c| c as i32)
+            };
+            let (min, max) = (corner(f32::min), corner(f32::max));
+            let normal = quad[0].normal.map(|n| n as i32);
+            let material = quad[0].pos_mat.w as i32;
+            let light = [quad[0].light.x.to_bits(), quad[0].light.y.to_bits()];
+            let ao = [0, 1, 2, 3].map(|i| quad[i].ao.to_bits());
+
+            let size = (max - min).map(|s| s.max(1));
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    for z in 0..size.z {
+                        let p = [min.x + x, min.y + y, min.z + z];
+                        faces.insert((p, [normal.x, normal.y, normal.z], material, light, ao));
+                    }
+                }
+            }
+        }
+        faces
+    }
+
+    fn assert_same_surface(data: &CubeSlice<Material, CHUNK_SIZE_SAFE>) {
+        let id = ChunkId::new(0, 0, 0);
+        let greedy = generate_greedy_mesh(&id, data);
+        let binary = generate_binary_mesh(&id, data);
+        assert_eq!(validate(&binary), vec![]);
+        assert_eq!(faces(&binary), faces(&greedy));
+    }
+
+    fn noise(seed: u64, materials: &[Material]) -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut data = CubeSlice::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    data.set(x, y, z, materials[rng.gen_range(0..materials.len())]);
+                }
+            }
+        }
+        data
+    }
+
+    /**
+     * Hills of stone with a grass top that reach into the padding.
+     */
+    fn hills() -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut data = CubeSlice::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                let height = 30 + ((x as f32 * 0.3).sin() * 12.0 + y as f32 * 0.4) as usize;
+                for z in 0..height.min(CHUNK_SIZE_SAFE) {
+                    let m = match z + 1 == height {
+                        true => Material::Grass,
+                        false => Material::Stone,
+                    };
+                    data.set(x, y, z, m);
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn same_surface_as_greedy() {
+        assert_same_surface(&CubeSlice::default());
+        assert_same_surface(&hills());
+
+        let mut full = CubeSlice::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    full.set(x, y, z, Material::Stone);
+                }
+            }
+        }
+        assert_same_surface(&full);
+        full.set(1, 1, 1, Material::Air);
+        full.set(64, 64, 64, Material::Water);
+        assert_same_surface(&full);
+    }
+
+    #[test]
+    fn same_surface_as_greedy_for_noise() {
+        use Material::*;
+        assert_same_surface(&noise(1, &[Air, Stone]));
+        assert_same_surface(&noise(2, &[Air, Stone, Dirt, Water, Glass, Grass]));
+    }
+
+    #[test]
+    fn same_lit_surface_as_greedy() {
+        let id = ChunkId::new(0, 0, 0);
+        let data = hills();
+        let mut light = Box::<CubeSlice<Light, CHUNK_SIZE_SAFE>>::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    light.set(x, y, z, Light::new((x % 16) as u8, (z / 5) as u8));
+                }
+            }
+        }
+
+        let greedy = generate_lit_greedy_mesh(&id, &data, light.as_ref());
+        let binary = generate_lit_binary_mesh(&id, &data, light.as_ref());
+        assert_eq!(faces(&binary), faces(&greedy));
+    }
+
+    #[bench]
+    fn hills_meshing_greedy(b: &mut Bencher) {
+        let id = ChunkId::new(0, 0, 0);
+        let data = hills();
+
+        b.iter(|| {
+            test::black_box(generate_greedy_mesh(&id, &data));
+        });
+    }
+
+    #[bench]
+    fn hills_meshing_binary(b: &mut Bencher) {
+        let id = ChunkId::new(0, 0, 0);
+        let data = hills();
+
+        b.iter(|| {
+            test::black_box(generate_binary_mesh(&id, &data));
+        });
+    }
+
+    #[bench]
+    fn single_chunk_meshing17(b: &mut Bencher) {
+        let id = ChunkId::new(17, 17, 17);
+        let chunk_seed = ChunkSeed::new(&WORLD_SEED, &id);
+        let data = Chunk::generate(chunk_seed).voxelize();
+
+        b.iter(|| {
+            test::black_box(generate_binary_mesh(&id, &data.voxels));
+        });
+    }
+
+    #[bench]
+    fn single_chunk_meshing0(b: &mut Bencher) {
+        let id = ChunkId::new(0, 0, 0);
+        let chunk_seed = ChunkSeed::new(&WORLD_SEED, &id);
+        let data = Chunk::generate(chunk_seed).voxelize();
+
+        b.iter(|| {
+            test::black_box(generate_binary_mesh(&id, &data.voxels));
+        });
+    }
+}
@@ -1,3 +1,5 @@
+use gamedata::material::RenderLayer;
+
 use crate::{WorldPosition, CHUNK_SIZE_I, HALF_CHUNK_I};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -78,24 +80,25 @@ mod tests {
     }
 }
 
+/**
+ * Mesh of one render layer of a chunk.
+ */
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum MeshId {
-    Opaque(ChunkId),
-    Transparent(ChunkId),
+pub struct MeshId {
+    pub chunk: ChunkId,
+    pub layer: RenderLayer,
 }
 
 impl MeshId {
+    pub fn new(chunk: ChunkId, layer: RenderLayer) -> Self {
+        Self { chunk, layer }
+    }
+
     pub fn chunk_id(&self) -> &ChunkId {
-        match self {
-            MeshId::Opaque(x) => x,
-            MeshId::Transparent(x) => x,
-        }
+        &self.chunk
     }
 
-    pub fn is_water(&self) -> bool {
-        match self {
-            MeshId::Opaque(_) => false,
-            MeshId::Transparent(_) => true,
-        }
+    pub fn is_translucent(&self) -> bool {
+        self.layer == RenderLayer::Translucent
     }
 }
@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+use gamedata::material::Material;
+
+use crate::{ChunkId, ChunkManager, WorldPosition};
+
+/// Level of flowing water, it loses one level per voxel it spreads sideways.
+pub const MAX_WATER_LEVEL: u8 = 7;
+/// Level of water voxels without a flow level, like the generated oceans.
+pub const SOURCE_LEVEL: u8 = MAX_WATER_LEVEL + 1;
+pub const DEFAULT_TICK_BUDGET: usize = 1024;
+
+const BELOW: [i32; 3] = [0, 0, -1];
+const ABOVE: [i32; 3] = [0, 0, 1];
+const SIDES: [[i32; 3]; 4] = [[-1, 0, 0], [1, 0, 0], [0, -1, 0], [0, 1, 0]];
+
+fn offset_position(position: &WorldPosition, offset: &[i32; 3]) -> WorldPosition {
+    WorldPosition::new(
+        position.x + offset[0],
+        position.y + offset[1],
+        position.z + offset[2],
+    )
+}
+
+/**
+ * Cellular automaton for water. Water voxels are sources unless they have a flow level.
+ * Scheduled voxels are updated in order, at most `budget` per tick.
+ * Voxels changed by an update schedule their neighbors for the next tick.
+ */
+#[derive(Debug)]
+pub struct FluidSimulation {
+    levels: BTreeMap<WorldPosition, u8>,
+    pending: VecDeque<WorldPosition>,
+    queued: BTreeSet<WorldPosition>,
+    budget: usize,
+}
+
+impl FluidSimulation {
+    pub fn new(budget: usize) -> Self {
+        Self::with_levels(budget, BTreeMap::new())
+    }
+
+    /**
+     * Simulation that continues from stored flow levels.
+     */
+    pub fn with_levels(budget: usize, levels: BTreeMap<WorldPosition, u8>) -> Self {
+        Self {
+            levels,
+            pending: VecDeque::new(),
+            queued: BTreeSet::new(),
+            budget,
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    /**
+     * Flow levels of all water that is not a source, including water in unloaded chunks.
+     */
+    pub fn levels(&self) -> &BTreeMap<WorldPosition, u8> {
+        &self.levels
+    }
+
+    /**
+     * Number of voxels waiting for an update.
+     */
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /**
+     * Water level of a voxel, 0 for voxels without water or in unloaded chunks.
+     */
+    pub fn level(&self, chunks: &ChunkManager, position: &WorldPosition) -> u8 {
+        match chunks.get_block(position) {
+            Some(Material::Water) => self.levels.get(position).copied().unwrap_or(SOURCE_LEVEL),
+            _ => 0,
+        }
+    }
+
+    pub fn schedule(&mut self, position: WorldPosition) {
+        if self.queued.insert(position.clone()) {
+            self.pending.push_back(position);
+        }
+    }
+
+    /**
+     * Schedules a changed voxel and all voxels that may flow into or out of it.
+     */
+    pub fn schedule_around(&mut self, position: &WorldPosition) {
+        self.schedule(position.clone());
+        for offset in SIDES.iter().chain([&BELOW, &ABOVE]) {
+            self.schedule(offset_position(position, offset));
+        }
+    }
+
+    /**
+     * Schedules the flowing water of a loaded chunk, its neighbors may have changed meanwhile.
+     */
+    pub fn load(&mut self, id: &ChunkId) {
+        let flowing = self
+            .levels
+            .keys()
+            .filter(|position| ChunkId::from(*position) == *id)
+            .cloned()
+            .collect::<Vec<_>>();
+        flowing
+            .iter()
+            .for_each(|position| self.schedule_around(position));
+    }
+
+    /**
+     * Forgets scheduled updates of an unloaded chunk. Its flow levels are kept, like its water
+     * in the journal.
+     */
+    pub fn unload(&mut self, id: &ChunkId) {
+        self.queued
+            .retain(|position| ChunkId::from(position) != *id);
+        self.pending
+            .retain(|position| ChunkId::from(position) != *id);
+    }
+
+    /**
+     * Updates the voxels that were scheduled before this tick, up to the budget.
+     * Returns the chunks that need to be remeshed.
+     */
+    pub fn tick(&mut self, chunks: &mut ChunkManager) -> HashSet<ChunkId> {
+        let mut dirty = HashSet::new();
+        let count = self.pending.len().min(self.budget);
+        for _ in 0..count {
+            let Some(position) = self.pending.pop_front() else {
+                break;
+            };
+            self.queued.remove(&position);
+            self.update(chunks, position, &mut dirty);
+        }
+        dirty
+    }
+
+    fn update(
+        &mut self,
+        chunks: &mut ChunkManager,
+        position: WorldPosition,
+        dirty: &mut HashSet<ChunkId>,
+    ) {
+        let Some(material) = chunks.get_block(&position) else {
+            return;
+        };
+
+        let current = match material {
+            Material::Water => self.levels.get(&position).copied().unwrap_or(SOURCE_LEVEL),
+            m if m.is_fillable() => 0,
+            _ => {
+                // replaced by an edit
+                self.levels.remove(&position);
+                return;
+            }
+        };
+        if current == SOURCE_LEVEL {
+            return;
+        }
+
+        let desired = self.inflow(chunks, &position);
+        if desired == current {
+            return;
+        }
+
+        if desired == 0 {
+            self.levels.remove(&position);
+            chunks
+                .journal_block(position.x, position.y, position.z, Material::Air)
+                .expect("Chunk of updated voxel must be loaded");
+        } else {
+            self.levels.insert(position.clone(), desired);
+            if current == 0 {
+                chunks
+                    .journal_block(position.x, position.y, position.z, Material::Water)
+                    .expect("Chunk of updated voxel must be loaded");
+            }
+        }
+
+        if (current == 0) != (desired == 0) {
+            dirty.extend(chunks.chunks_to_remesh(&position));
+        }
+        for offset in SIDES.iter().chain([&BELOW, &ABOVE]) {
+            self.schedule(offset_position(&position, offset));
+        }
+    }
+
+    /**
+     * Level a voxel gets from its neighbors. Water falls at the maximum level
+     * and only spreads sideways on top of solid blocks or sources.
+     */
+    fn inflow(&self, chunks: &ChunkManager, position: &WorldPosition) -> u8 {
+        if self.level(chunks, &offset_position(position, &ABOVE)) > 0 {
+            return MAX_WATER_LEVEL;
+        }
+
+        SIDES
+            .iter()
+            .map(|offset| offset_position(position, offset))
+            .filter(|side| {
+                let below = offset_position(side, &BELOW);
+                match chunks.get_block(&below) {
+                    Some(Material::Water) => !self.levels.contains_key(&below),
+                    Some(m) => !m.is_fillable(),
+                    None => false,
+                }
+            })
+            .map(|side| self.level(chunks, &side).min(MAX_WATER_LEVEL + 1))
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(1)
+    }
+}
+
+impl Default for FluidSimulation {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{FluidSimulation, DEFAULT_TICK_BUDGET, MAX_WATER_LEVEL, SOURCE_LEVEL};
+    use crate::{
+        mgmt::brush::Brush, storage::fluids, ChunkData, ChunkId, ChunkManager, WorldPosition,
+        CHUNK_SIZE_I,
+    };
+
+    /**
+     * Two chunks side by side with a stone floor at z = 0.
+     */
+    fn basin() -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+        for id in [ChunkId::new(0, 0, 0), ChunkId::new(1, 0, 0)] {
+            chunks.insert(&id, ChunkData::default());
+        }
+        chunks.fill(
+            &Brush::Box {
+                min: WorldPosition::new(0, 0, 0),
+                max: WorldPosition::new(2 * CHUNK_SIZE_I - 1, CHUNK_SIZE_I - 1, 0),
+            },
+            Material::Stone,
+        );
+        chunks
+    }
+
+    fn run(fluids: &mut FluidSimulation, chunks: &mut ChunkManager) -> usize {
+        let mut ticks = 0;
+        while fluids.pending() > 0 {
+            fluids.tick(chunks);
+            ticks += 1;
+            assert!(ticks < 1000, "Water must settle");
+        }
+        ticks
+    }
+
+    fn place_source(
+        fluids: &mut FluidSimulation,
+        chunks: &mut ChunkManager,
+        x: i32,
+        y: i32,
+        z: i32,
+    ) {
+        chunks.set_block(x, y, z, Material::Water).unwrap();
+        fluids.schedule_around(&WorldPosition::new(x, y, z));
+    }
+
+    #[test]
+    fn water_falls_and_spreads() {
+        let mut chunks = basin();
+        let mut fluids = FluidSimulation::default();
+        place_source(&mut fluids, &mut chunks, 10, 10, 5);
+        run(&mut fluids, &mut chunks);
+
+        let level = |x, y, z| fluids.level(&chunks, &WorldPosition::new(x, y, z));
+        assert_eq!(level(10, 10, 5), SOURCE_LEVEL);
+        assert_eq!(level(10, 10, 3), MAX_WATER_LEVEL);
+        assert_eq!(level(11, 10, 3), 0);
+        assert_eq!(level(10, 10, 1), MAX_WATER_LEVEL);
+        assert_eq!(level(12, 10, 1), MAX_WATER_LEVEL - 2);
+        assert_eq!(level(13, 11, 1), MAX_WATER_LEVEL - 4);
+        assert_eq!(level(16, 10, 1), 1);
+        assert_eq!(level(17, 10, 1), 0);
+    }
+
+    #[test]
+    fn water_crosses_chunk_borders() {
+        let mut chunks = basin();
+        let mut fluids = FluidSimulation::default();
+        place_source(&mut fluids, &mut chunks, 62, 5, 1);
+        fluids.tick(&mut chunks);
+
+        let dirty = fluids.tick(&mut chunks);
+        assert!(dirty.contains(&ChunkId::new(1, 0, 0)));
+        run(&mut fluids, &mut chunks);
+        assert_eq!(
+            fluids.level(&chunks, &WorldPosition::new(68, 5, 1)),
+            MAX_WATER_LEVEL - 5
+        );
+    }
+
+    #[test]
+    fn water_recedes_without_source() {
+        let mut chunks = basin();
+        let mut fluids = FluidSimulation::default();
+        place_source(&mut fluids, &mut chunks, 10, 10, 1);
+        run(&mut fluids, &mut chunks);
+        assert!(fluids.level(&chunks, &WorldPosition::new(14, 10, 1)) > 0);
+
+        chunks.set_block(10, 10, 1, Material::Air).unwrap();
+        fluids.schedule_around(&WorldPosition::new(10, 10, 1));
+        run(&mut fluids, &mut chunks);
+
+        for x in 3..18 {
+            let position = WorldPosition::new(x, 10, 1);
+            assert_eq!(chunks.get_block(&position), Some(Material::Air));
+        }
+    }
+
+    #[test]
+    fn flow_survives_reload() {
+        let mut chunks = basin();
+        let mut before = FluidSimulation::default();
+        chunks.edit_block(10, 10, 1, Material::Water).unwrap();
+        before.schedule_around(&WorldPosition::new(10, 10, 1));
+        run(&mut before, &mut chunks);
+
+        // chunks come back from the journal and levels from their file, like after a restart
+        let ids = [ChunkId::new(0, 0, 0), ChunkId::new(1, 0, 0)];
+        let mut reloaded = ChunkManager::new();
+        for id in &ids {
+            let mut data = ChunkData::default();
+            chunks.journal().replay(id, &mut data);
+            reloaded.insert(id, data);
+        }
+        let mut bytes = vec![];
+        fluids::encode(before.levels(), &mut bytes);
+        let levels = fluids::decode(&bytes).unwrap();
+        let mut after = FluidSimulation::with_levels(DEFAULT_TICK_BUDGET, levels);
+        ids.iter().for_each(|id| after.load(id));
+        run(&mut after, &mut reloaded);
+
+        for x in 3..18 {
+            let position = WorldPosition::new(x, 10, 1);
+            assert_eq!(
+                after.level(&reloaded, &position),
+                before.level(&chunks, &position)
+            );
+        }
+
+        // the spread water still depends on its source
+        reloaded.edit_block(10, 10, 1, Material::Air).unwrap();
+        after.schedule_around(&WorldPosition::new(10, 10, 1));
+        run(&mut after, &mut reloaded);
+        let position = WorldPosition::new(14, 10, 1);
+        assert_eq!(reloaded.get_block(&position), Some(Material::Air));
+    }
+
+    #[test]
+    fn budget_limits_updates_per_tick() {
+        let simulate = |budget| {
+            let mut chunks = basin();
+            let mut fluids = FluidSimulation::new(budget);
+            place_source(&mut fluids, &mut chunks, 30, 30, 4);
+            place_source(&mut fluids, &mut chunks, 33, 30, 1);
+            let ticks = run(&mut fluids, &mut chunks);
+
+            let mut water = vec![];
+            for x in 20..45 {
+                for y in 20..40 {
+                    for z in 1..5 {
+                        let position = WorldPosition::new(x, y, z);
+                        water.push(fluids.level(&chunks, &position));
+                    }
+                }
+            }
+            (ticks, water)
+        };
+
+        let (fast_ticks, fast) = simulate(10_000);
+        let (slow_ticks, slow) = simulate(3);
+        assert!(slow_ticks > fast_ticks * 3);
+        assert_eq!(fast, slow);
+        assert_eq!(simulate(3).1, slow);
+    }
+}
@@ -0,0 +1,210 @@
+use std::mem::variant_count;
+
+use gamedata::material::Material;
+
+/**
+ * Biomes of the world. Land biomes are picked by the climate, the ocean by the terrain below sea level.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Ocean,
+    Desert,
+    Tundra,
+    Forest,
+    Plains,
+    Swamp,
+    Mountains,
+}
+
+/**
+ * Structures placed on top of the terrain besides trees.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Structure {
+    Boulder,
+}
+
+/**
+ * Everything generation needs to know about a biome.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiomeDef {
+    /// Top block of the terrain
+    pub surface: Material,
+    /// Blocks below the surface
+    pub subsurface: Material,
+    /// Factor on the height of the terrain above sea level
+    pub height_scale: f32,
+    /// Chance of a tree at each spot where one could grow
+    pub vegetation: f32,
+    pub structures: &'static [Structure],
+    /// Water at sea level freezes to ice
+    pub frozen: bool,
+    /// Surfaces this high above sea level are covered in snow
+    pub snow_line: Option<i32>,
+}
+
+#[rustfmt::skip]
+const BIOMES: [BiomeDef; variant_count::<Biome>()] = [
+    // Ocean
+    BiomeDef { surface: Material::Sand, subsurface: Material::Gravel, height_scale: 1.0, vegetation: 0.0, structures: &[], frozen: false, snow_line: None },
+    // Desert
+    BiomeDef { surface: Material::Sand, subsurface: Material::Sand, height_scale: 0.6, vegetation: 0.0, structures: &[Structure::Boulder], frozen: false, snow_line: None },
+    // Tundra
+    BiomeDef { surface: Material::Snow, subsurface: Material::Dirt, height_scale: 0.8, vegetation: 0.02, structures: &[Structure::Boulder], frozen: true, snow_line: None },
+    // Forest
+    BiomeDef { surface: Material::Grass, subsurface: Material::Dirt, height_scale: 1.0, vegetation: 0.3, structures: &[Structure::Boulder], frozen: false, snow_line: None },
+    // Plains
+    BiomeDef { surface: Material::Grass, subsurface: Material::Dirt, height_scale: 0.5, vegetation: 0.02, structures: &[Structure::Boulder], frozen: false, snow_line: None },
+    // Swamp
+    BiomeDef { surface: Material::Grass, subsurface: Material::Dirt, height_scale: 0.15, vegetation: 0.15, structures: &[], frozen: false, snow_line: None },
+    // Mountains
+    BiomeDef { surface: Material::Stone, subsurface: Material::Stone, height_scale: 2.5, vegetation: 0.0, structures: &[Structure::Boulder], frozen: true, snow_line: Some(150) },
+];
+
+/// Land biomes by temperature from cold to hot and rainfall from dry to wet
+#[rustfmt::skip]
+const CLIMATE: [[Biome; 4]; 4] = [
+    [Biome::Tundra, Biome::Tundra, Biome::Mountains, Biome::Mountains],
+    [Biome::Plains, Biome::Forest, Biome::Forest,    Biome::Mountains],
+    [Biome::Plains, Biome::Plains, Biome::Forest,    Biome::Swamp],
+    [Biome::Desert, Biome::Desert, Biome::Plains,    Biome::Swamp],
+];
+
+/// Temperature and rainfall noise mostly stays within plus and minus this
+const CLIMATE_RANGE: f32 = 0.1;
+
+impl Biome {
+    pub const ALL: [Biome; variant_count::<Biome>()] = [
+        Self::Ocean,
+        Self::Desert,
+        Self::Tundra,
+        Self::Forest,
+        Self::Plains,
+        Self::Swamp,
+        Self::Mountains,
+    ];
+
+    pub fn def(&self) -> &'static BiomeDef {
+        &BIOMES[*self as usize]
+    }
+}
+
+/**
+ * How much each land biome contributes to a column, blended across the borders of the climate table.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeWeights([f32; variant_count::<Biome>()]);
+
+impl BiomeWeights {
+    /**
+     * Interpolates the climate table between the centers of its cells.
+     */
+    pub fn from_climate(temperature: f32, rainfall: f32) -> Self {
+        let cell = |value: f32| {
+            let last = (CLIMATE.len() - 1) as f32;
+            let position =
+                ((value / CLIMATE_RANGE + 1.0) * 0.5 * CLIMATE.len() as f32 - 0.5).clamp(0.0, last);
+            let low = position.floor() as usize;
+            (low, (low + 1).min(CLIMATE.len() - 1), position.fract())
+        };
+        let (t0, t1, t) = cell(temperature);
+        let (r0, r1, r) = cell(rainfall);
+
+        let mut weights = [0.0; variant_count::<Biome>()];
+        weights[CLIMATE[t0][r0] as usize] += (1.0 - t) * (1.0 - r);
+        weights[CLIMATE[t0][r1] as usize] += (1.0 - t) * r;
+        weights[CLIMATE[t1][r0] as usize] += t * (1.0 - r);
+        weights[CLIMATE[t1][r1] as usize] += t * r;
+        Self(weights)
+    }
+
+    pub fn get(&self, biome: Biome) -> f32 {
+        self.0[biome as usize]
+    }
+
+    /**
+     * Biome with the highest weight.
+     */
+    pub fn dominant(&self) -> Biome {
+        Biome::ALL
+            .into_iter()
+            .max_by(|a, b| self.get(*a).total_cmp(&self.get(*b)))
+            .unwrap()
+    }
+
+    /**
+     * Biome the weights reach at a value in 0..1, random values dither the borders between biomes.
+     */
+    pub fn pick(&self, value: f32) -> Biome {
+        let mut sum = 0.0;
+        for biome in Biome::ALL {
+            sum += self.get(biome);
+            if value < sum {
+                return biome;
+            }
+        }
+        self.dominant()
+    }
+
+    /**
+     * Blends a value of the biome definitions.
+     */
+    pub fn blend(&self, value: impl Fn(&BiomeDef) -> f32) -> f32 {
+        Biome::ALL
+            .into_iter()
+            .map(|biome| self.get(biome) * value(biome.def()))
+            .sum()
+    }
+
+    /**
+     * Terrain height with the height above sea level scaled by the biomes.
+     */
+    pub fn height(&self, height: f32, sea_level: i32) -> f32 {
+        let sea_level = sea_level as f32;
+        match height > sea_level {
+            true => sea_level + (height - sea_level) * self.blend(|b| b.height_scale),
+            false => height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Biome, BiomeWeights, CLIMATE_RANGE};
+
+    #[test]
+    fn weights_add_up() {
+        for t in -10..=10 {
+            for r in -10..=10 {
+                let weights = BiomeWeights::from_climate(t as f32 * 0.02, r as f32 * 0.02);
+                let sum = Biome::ALL.into_iter().map(|b| weights.get(b)).sum::<f32>();
+                assert!((sum - 1.0).abs() < 1e-5, "{} at {} {}", sum, t, r);
+                assert_eq!(weights.get(Biome::Ocean), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn extremes_pick_single_biomes() {
+        let cold_dry = BiomeWeights::from_climate(-CLIMATE_RANGE, -CLIMATE_RANGE);
+        assert_eq!(cold_dry.get(Biome::Tundra), 1.0);
+        let hot_dry = BiomeWeights::from_climate(CLIMATE_RANGE, -CLIMATE_RANGE);
+        assert_eq!(hot_dry.get(Biome::Desert), 1.0);
+        let hot_wet = BiomeWeights::from_climate(CLIMATE_RANGE, CLIMATE_RANGE);
+        assert_eq!(hot_wet.pick(0.99), Biome::Swamp);
+    }
+
+    #[test]
+    fn borders_blend() {
+        // halfway between the cold and the cool row
+        let weights = BiomeWeights::from_climate(-0.5 * CLIMATE_RANGE, -CLIMATE_RANGE);
+        assert!((weights.get(Biome::Tundra) - 0.5).abs() < 1e-5);
+        assert!((weights.get(Biome::Plains) - 0.5).abs() < 1e-5);
+        assert_eq!(weights.pick(0.25), Biome::Tundra);
+        assert_eq!(weights.pick(0.75), Biome::Plains);
+        assert!((weights.height(100.0, 0) - 65.0).abs() < 1e-3);
+        assert!((weights.height(110.0, 10) - 75.0).abs() < 1e-3);
+        assert_eq!(weights.height(-20.0, 0), -20.0);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{storage::bytes::invalid_data, CHUNK_SIZE};
+
+/**
+ * Version of the configuration file format, bumped when parameters change their meaning.
+ */
+pub const CONFIG_VERSION: u32 = 1;
+
+/**
+ * Tuning of the terrain generator. Every world stores the configuration it was generated with,
+ * missing values fall back to the defaults.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldGenConfig {
+    pub version: u32,
+    /// Height up to which the world is filled with water
+    pub sea_level: i32,
+    pub height: HeightConfig,
+    pub caves: CaveConfig,
+    pub climate: ClimateConfig,
+    pub trees: TreeConfig,
+    pub boulders: BoulderConfig,
+}
+
+/**
+ * Terrain height as a large scale gradient plus a small scale variation.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeightConfig {
+    pub base_frequency: f32,
+    pub base_amplitude: f32,
+    pub variation_frequency: f32,
+    pub variation_octaves: u8,
+    pub variation_gain: f32,
+    pub variation_lacunarity: f32,
+    pub variation_amplitude: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveConfig {
+    pub frequency: f32,
+    pub octaves: u8,
+    pub gain: f32,
+    pub lacunarity: f32,
+    /// Blocks where the cave noise is below this are carved out
+    pub threshold: f32,
+}
+
+/**
+ * Frequencies of the temperature and rainfall noise, sampled once per chunk corner.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClimateConfig {
+    pub temperature_frequency: f32,
+    pub rainfall_frequency: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeConfig {
+    /// Spots where trees can grow along each side of a chunk
+    pub per_side: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoulderConfig {
+    /// Spots where boulders can lie along each side of a chunk
+    pub per_side: usize,
+    /// Chance of a boulder at each spot
+    pub chance: f32,
+}
+
+impl WorldGenConfig {
+    /**
+     * Reads a configuration file, files of newer versions are rejected.
+     */
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_toml()?)
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        let config: Self = toml::from_str(text).map_err(|e| invalid_data(&e.to_string()))?;
+        if config.version > CONFIG_VERSION {
+            return Err(invalid_data(&format!(
+                "generator config version {} is newer than {}",
+                config.version, CONFIG_VERSION
+            )));
+        }
+        // structures are placed at least one voxel inside cells of two or more voxels
+        let spots = 1..=CHUNK_SIZE / 3;
+        for (name, per_side) in [
+            ("trees", config.trees.per_side),
+            ("boulders", config.boulders.per_side),
+        ] {
+            if !spots.contains(&per_side) {
+                return Err(invalid_data(&format!(
+                    "{}.per_side must be in {:?}, got {}",
+                    name, spots, per_side
+                )));
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> io::Result<String> {
+        toml::to_string(self).map_err(|e| invalid_data(&e.to_string()))
+    }
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            sea_level: 0,
+            height: HeightConfig::default(),
+            caves: CaveConfig::default(),
+            climate: ClimateConfig::default(),
+            trees: TreeConfig::default(),
+            boulders: BoulderConfig::default(),
+        }
+    }
+}
+
+impl Default for HeightConfig {
+    fn default() -> Self {
+        Self {
+            base_frequency: 0.000017,
+            base_amplitude: 12000.0,
+            variation_frequency: 0.000003,
+            variation_octaves: 14,
+            variation_gain: 1.0,
+            variation_lacunarity: 2.0,
+            variation_amplitude: 100.0,
+        }
+    }
+}
+
+impl Default for CaveConfig {
+    fn default() -> Self {
+        Self {
+            frequency: 0.005,
+            octaves: 2,
+            gain: 2.0,
+            lacunarity: 0.5,
+            threshold: 0.002,
+        }
+    }
+}
+
+impl Default for ClimateConfig {
+    fn default() -> Self {
+        Self {
+            temperature_frequency: 0.08,
+            rainfall_frequency: 0.2,
+        }
+    }
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self { per_side: 16 }
+    }
+}
+
+impl Default for BoulderConfig {
+    fn default() -> Self {
+        Self {
+            per_side: 2,
+            chance: 0.8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{WorldGenConfig, CONFIG_VERSION};
+    use crate::CHUNK_SIZE;
+
+    #[test]
+    fn round_trip() {
+        let mut config = WorldGenConfig::default();
+        config.sea_level = -12;
+        config.height.variation_octaves = 3;
+        config.caves.threshold = 0.01;
+        config.boulders.chance = 0.0;
+
+        let text = config.to_toml().unwrap();
+        assert_eq!(WorldGenConfig::from_toml(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn missing_values_use_defaults() {
+        let text = "sea_level = 20\n\n[trees]\nper_side = 4\n";
+        let config = WorldGenConfig::from_toml(text).unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.sea_level, 20);
+        assert_eq!(config.trees.per_side, 4);
+        assert_eq!(config.caves, WorldGenConfig::default().caves);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let text = format!("version = {}\n", CONFIG_VERSION + 1);
+        assert!(WorldGenConfig::from_toml(&text).is_err());
+    }
+
+    #[test]
+    fn spots_per_side_are_validated() {
+        for per_side in [0, CHUNK_SIZE / 3 + 1] {
+            let text = format!("[boulders]\nper_side = {}\n", per_side);
+            assert!(WorldGenConfig::from_toml(&text).is_err());
+            let text = format!("[trees]\nper_side = {}\n", per_side);
+            assert!(WorldGenConfig::from_toml(&text).is_err());
+        }
+        let text = format!("[trees]\nper_side = {}\n", CHUNK_SIZE / 3);
+        assert!(WorldGenConfig::from_toml(&text).is_ok());
+    }
+}
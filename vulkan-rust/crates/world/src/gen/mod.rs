@@ -1,4 +1,14 @@
+pub mod biome;
 pub(crate) mod boulder;
 pub mod chunk;
+pub mod config;
+pub mod pipeline;
+pub mod placement;
+pub mod stages;
 pub(crate) mod town;
 pub(crate) mod tree;
+
+/**
+ * Bumped whenever generation changes so that existing worlds would look different.
+ */
+pub const GENERATOR_VERSION: u32 = 3;
@@ -0,0 +1,270 @@
+use gamedata::material::Material;
+
+use super::{
+    biome::{Biome, BiomeWeights},
+    chunk::chunk_bilerp,
+    config::WorldGenConfig,
+    stages::{Boulders, Caves, Height, Surface, Trees, Water},
+};
+use crate::{
+    seed::{ChunkSeed, PositionalSeed},
+    slice::CubeSlice,
+    WorldPosition, CHUNK_SIZE_SAFE, CHUNK_SIZE_SAFE_SQUARED,
+};
+
+/**
+ * One step of generating a chunk. Stages work on the padded chunk, one voxel larger than the
+ * chunk on every side, and share what they found out through the context.
+ */
+pub trait GenerationStage: Send + Sync {
+    /**
+     * Name to find the stage by in a pipeline.
+     */
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext);
+}
+
+/**
+ * Everything the stages of one chunk share.
+ */
+pub struct StageContext<'a> {
+    pub seed: &'a ChunkSeed,
+    /// World position of the first voxel of the chunk, the padding starts one voxel lower
+    pub start: WorldPosition,
+    /// Biomes of every column of the padded chunk
+    pub biomes: Vec<BiomeWeights>,
+    /// World z above the top block of every column, once the terrain height is generated
+    pub heights: Option<Vec<i32>>,
+    /// Solid voxels inside the chunk, without the padding
+    pub voxel_count: usize,
+    /// Whether all voxels inside the chunk block the view
+    pub opaque: bool,
+}
+
+impl<'a> StageContext<'a> {
+    /**
+     * Context of a chunk with the climate at its corners, biomes are blended in between.
+     */
+    pub fn new(seed: &'a ChunkSeed, temperature: &[f32; 4], rainfall: &[f32; 4]) -> Self {
+        let mut biomes = Vec::with_capacity(CHUNK_SIZE_SAFE_SQUARED);
+        for y in 0..CHUNK_SIZE_SAFE {
+            for x in 0..CHUNK_SIZE_SAFE {
+                let temperature = chunk_bilerp(temperature, x as i32 - 1, y as i32 - 1);
+                let rainfall = chunk_bilerp(rainfall, x as i32 - 1, y as i32 - 1);
+                biomes.push(BiomeWeights::from_climate(temperature, rainfall));
+            }
+        }
+
+        Self {
+            seed,
+            start: WorldPosition::from(seed.id()),
+            biomes,
+            heights: None,
+            voxel_count: 0,
+            opaque: true,
+        }
+    }
+
+    pub fn config(&self) -> &'a WorldGenConfig {
+        self.seed.config()
+    }
+
+    /**
+     * Biome weights of a column of the padded chunk.
+     */
+    pub fn weights(&self, x: usize, y: usize) -> &BiomeWeights {
+        &self.biomes[y * CHUNK_SIZE_SAFE + x]
+    }
+
+    /**
+     * Biome whose surface covers a column, columns near biome borders are picked at random.
+     */
+    pub fn column_biome(&self, x: usize, y: usize) -> Biome {
+        let position =
+            WorldPosition::new(self.start.x - 1 + x as i32, self.start.y - 1 + y as i32, 0);
+        let seed = PositionalSeed::new(self.seed.world_seed(), &position);
+        self.weights(x, y)
+            .pick(fastrand::Rng::with_seed(seed.value()).f32())
+    }
+}
+
+/**
+ * Ordered stages that generate a chunk. The default pipeline is the terrain of the game,
+ * stages can be added, removed and reordered by their names.
+ */
+pub struct Pipeline {
+    stages: Vec<Box<dyn GenerationStage>>,
+}
+
+impl Pipeline {
+    pub fn empty() -> Self {
+        Self { stages: vec![] }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|stage| stage.name() == name)
+    }
+
+    pub fn push(&mut self, stage: impl GenerationStage + 'static) -> &mut Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn insert(&mut self, index: usize, stage: impl GenerationStage + 'static) -> &mut Self {
+        self.stages.insert(index, Box::new(stage));
+        self
+    }
+
+    /**
+     * Inserts a stage before the stage with the given name, or at the end if there is none.
+     */
+    pub fn insert_before(
+        &mut self,
+        name: &str,
+        stage: impl GenerationStage + 'static,
+    ) -> &mut Self {
+        let index = self.position(name).unwrap_or(self.stages.len());
+        self.insert(index, stage)
+    }
+
+    /**
+     * Inserts a stage after the stage with the given name, or at the end if there is none.
+     */
+    pub fn insert_after(&mut self, name: &str, stage: impl GenerationStage + 'static) -> &mut Self {
+        let index = self.position(name).map_or(self.stages.len(), |i| i + 1);
+        self.insert(index, stage)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn GenerationStage>> {
+        self.position(name).map(|index| self.stages.remove(index))
+    }
+
+    /**
+     * Moves a stage to a new index, returns whether the stage was found.
+     */
+    pub fn reorder(&mut self, name: &str, index: usize) -> bool {
+        let Some(stage) = self.remove(name) else {
+            return false;
+        };
+        self.stages.insert(index.min(self.stages.len()), stage);
+        true
+    }
+
+    pub fn run(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        for stage in &self.stages {
+            stage.apply(data, context);
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        let mut pipeline = Self::empty();
+        pipeline
+            .push(Height)
+            .push(Water)
+            .push(Surface)
+            .push(Caves)
+            .push(Trees)
+            .push(Boulders);
+        pipeline
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{GenerationStage, Pipeline, StageContext};
+    use crate::{
+        gen::{
+            chunk::{compress, Chunk},
+            stages::{Caves, Frame, Full},
+        },
+        slice::CubeSlice,
+        traits::{Data3D, Generate},
+        ChunkId, ChunkSeed, WorldSeed, CHUNK_SIZE, CHUNK_SIZE_SAFE,
+    };
+
+    const WORLD_SEED: WorldSeed = WorldSeed::new(17);
+
+    struct Fill(Material);
+
+    impl GenerationStage for Fill {
+        fn name(&self) -> &'static str {
+            "fill"
+        }
+
+        fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, _: &mut StageContext) {
+            data.set(1, 1, 1, self.0);
+        }
+    }
+
+    #[test]
+    fn stages_are_rearranged_by_name() {
+        let mut pipeline = Pipeline::default();
+        assert_eq!(
+            pipeline.names(),
+            ["height", "water", "surface", "caves", "trees", "boulders"]
+        );
+
+        assert!(pipeline.remove("trees").is_some());
+        assert!(pipeline.remove("trees").is_none());
+        pipeline
+            .insert_before("height", Frame)
+            .insert_after("caves", Full);
+        assert!(pipeline.reorder("caves", 0));
+        assert!(!pipeline.reorder("trees", 0));
+        assert_eq!(
+            pipeline.names(),
+            ["caves", "frame", "height", "water", "surface", "full", "boulders"]
+        );
+    }
+
+    #[test]
+    fn later_stages_win() {
+        let seed = ChunkSeed::new(&WORLD_SEED, &ChunkId::new(0, 0, 0));
+        let mut context = StageContext::new(&seed, &[0.0; 4], &[0.0; 4]);
+        let mut data = CubeSlice::default();
+
+        let mut pipeline = Pipeline::empty();
+        pipeline
+            .push(Fill(Material::Stone))
+            .push(Fill(Material::Dirt));
+        pipeline.run(&mut data, &mut context);
+        assert_eq!(data.get(1, 1, 1), Material::Dirt);
+
+        pipeline.reorder("fill", 1);
+        pipeline.run(&mut data, &mut context);
+        assert_eq!(data.get(1, 1, 1), Material::Stone);
+    }
+
+    #[test]
+    fn custom_pipelines_generate_chunks() {
+        // below sea level, the default pipeline would fill the caves with water
+        let chunk = Chunk::generate(ChunkSeed::new(&WORLD_SEED, &ChunkId::new(2, 0, -2)));
+        let mut pipeline = Pipeline::empty();
+        pipeline.push(Full).push(Caves);
+
+        let generated = chunk.voxelize_with(&pipeline);
+        assert!(generated.needs_mesh);
+        let data = compress(&generated.voxels);
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let material = data.get(x, y, z);
+                    assert!(
+                        matches!(material, Material::Air | Material::Stone),
+                        "{:?}",
+                        material
+                    );
+                }
+            }
+        }
+    }
+}
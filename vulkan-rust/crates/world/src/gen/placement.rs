@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use gamedata::material::Material;
+
+use super::{
+    biome::BiomeWeights,
+    chunk::{chunk_bilerp, in_chunk_data},
+    config::WorldGenConfig,
+    pipeline::StageContext,
+};
+use crate::{
+    seed::PositionalSeed, slice::CubeSlice, terrain_noise, traits::Data3D, ChunkId, WorldPosition,
+    WorldSeed, CHUNK_SIZE_I, CHUNK_SIZE_SAFE, CHUNK_SIZE_SAFE_I,
+};
+
+/**
+ * Terrain of single columns, computed the way the chunk containing the column generates it.
+ * Structures are placed by the columns so that every chunk they reach places them the same way.
+ */
+pub struct ColumnSampler<'a> {
+    seed: &'a WorldSeed,
+    config: &'a WorldGenConfig,
+    /// Temperature and rainfall at the corners of the chunk columns sampled so far
+    climates: HashMap<(i32, i32), ([f32; 4], [f32; 4])>,
+}
+
+impl<'a> ColumnSampler<'a> {
+    pub fn new(seed: &'a WorldSeed, config: &'a WorldGenConfig) -> Self {
+        Self {
+            seed,
+            config,
+            climates: HashMap::new(),
+        }
+    }
+
+    pub fn weights(&mut self, x: i32, y: i32) -> BiomeWeights {
+        let id = ChunkId::from(&WorldPosition::new(x, y, 0));
+        let start = WorldPosition::from(&id);
+        let (seed, climate) = (self.seed, &self.config.climate);
+        let (temperature, rainfall) = self.climates.entry((id.x, id.y)).or_insert_with(|| {
+            let corners = |noise: Vec<f32>| [noise[0], noise[1], noise[2], noise[3]];
+            (
+                corners(terrain_noise::chunk_temperature(seed, climate, &start)),
+                corners(terrain_noise::chunk_rainfall(seed, climate, &start)),
+            )
+        });
+
+        BiomeWeights::from_climate(
+            chunk_bilerp(temperature, x - start.x, y - start.y),
+            chunk_bilerp(rainfall, x - start.x, y - start.y),
+        )
+    }
+
+    /**
+     * World z above the top block of a column, like the heights of the height stage.
+     */
+    pub fn top(&mut self, x: i32, y: i32) -> i32 {
+        let height = terrain_noise::height(self.seed, &self.config.height, x, y, 1)[0];
+        self.weights(x, y).height(height, self.config.sea_level) as i32 - 1
+    }
+
+    /**
+     * Whether the caves carve out the block at a position.
+     */
+    pub fn is_cave(&self, x: i32, y: i32, z: i32) -> bool {
+        let caves = &self.config.caves;
+        terrain_noise::caves(self.seed, caves, x, y, z, 1)[0] < caves.threshold
+    }
+}
+
+/**
+ * Random numbers of one cell of a placement grid, the same in every chunk.
+ * Structure kinds use their own salt so that they do not line up.
+ */
+pub fn cell_rng(seed: &WorldSeed, cell: (i32, i32), salt: i32) -> fastrand::Rng {
+    let position = WorldPosition::new(cell.0, cell.1, salt);
+    fastrand::Rng::with_seed(PositionalSeed::new(seed, &position).value())
+}
+
+/**
+ * Cells of a grid with the given spacing whose structures could reach into the padded chunk,
+ * if they reach at most `reach` voxels away from their cell along x and y.
+ */
+pub fn cells(start: &WorldPosition, spacing: i32, reach: i32) -> Vec<(i32, i32)> {
+    let range = |start: i32| {
+        let first = (start - 1 - reach).div_euclid(spacing);
+        let last = (start + CHUNK_SIZE_I + reach).div_euclid(spacing);
+        first..=last
+    };
+    range(start.x)
+        .flat_map(|x| range(start.y).map(move |y| (x, y)))
+        .collect()
+}
+
+/**
+ * Whether a box of voxels in world coordinates reaches into the padded chunk.
+ */
+pub fn reaches(start: &WorldPosition, min: [i32; 3], size: [i32; 3]) -> bool {
+    let start = [start.x - 1, start.y - 1, start.z - 1];
+    (0..3).all(|d| min[d] < start[d] + CHUNK_SIZE_SAFE_I && min[d] + size[d] > start[d])
+}
+
+/**
+ * Voxels of a structure in world coordinates.
+ */
+pub struct PlacedStructure {
+    pub voxels: Vec<(WorldPosition, Material)>,
+}
+
+impl PlacedStructure {
+    /**
+     * Writes the part of the structure inside the padded chunk.
+     */
+    pub fn stamp(
+        &self,
+        data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>,
+        context: &mut StageContext,
+    ) {
+        let inside = 0..CHUNK_SIZE_SAFE_I;
+        for (position, material) in &self.voxels {
+            let x = position.x - context.start.x + 1;
+            let y = position.y - context.start.y + 1;
+            let z = position.z - context.start.z + 1;
+            if !(inside.contains(&x) && inside.contains(&y) && inside.contains(&z)) {
+                continue;
+            }
+
+            let (x, y, z) = (x as usize, y as usize, z as usize);
+            if in_chunk_data(x, y, z) {
+                match (data.get(x, y, z).is_solid(), material.is_solid()) {
+                    (false, true) => context.voxel_count += 1,
+                    (true, false) => context.voxel_count -= 1,
+                    _ => {}
+                }
+                context.opaque &= material.is_opaque();
+            }
+            data.set(x, y, z, *material);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use gamedata::material::Material;
+
+    use super::{cells, reaches, ColumnSampler};
+    use crate::{
+        gen::{
+            pipeline::{GenerationStage, Pipeline, StageContext},
+            stages::{Boulders, Height},
+        },
+        slice::CubeSlice,
+        terrain_noise,
+        traits::Data3D,
+        ChunkId, ChunkSeed, WorldGenConfig, WorldPosition, WorldSeed, CHUNK_SIZE, CHUNK_SIZE_SAFE,
+    };
+
+    const WORLD_SEED: WorldSeed = WorldSeed::new(17);
+
+    #[test]
+    fn columns_match_generated_terrain() {
+        let id = ChunkId::new(3, -2, 0);
+        let seed = ChunkSeed::new(&WORLD_SEED, &id);
+        let temperature = terrain_noise::chunk_temperature(
+            &WORLD_SEED,
+            &seed.config().climate,
+            &WorldPosition::from(&id),
+        );
+        let rainfall = terrain_noise::chunk_rainfall(
+            &WORLD_SEED,
+            &seed.config().climate,
+            &WorldPosition::from(&id),
+        );
+        let corners = |noise: Vec<f32>| [noise[0], noise[1], noise[2], noise[3]];
+        let mut context = StageContext::new(&seed, &corners(temperature), &corners(rainfall));
+        let mut data = CubeSlice::default();
+        Height.apply(&mut data, &mut context);
+        let heights = context.heights.unwrap();
+
+        let mut sampler = ColumnSampler::new(&WORLD_SEED, seed.config());
+        for y in (1..=CHUNK_SIZE).step_by(7) {
+            for x in (1..=CHUNK_SIZE).step_by(5) {
+                let top = sampler.top(
+                    context.start.x - 1 + x as i32,
+                    context.start.y - 1 + y as i32,
+                );
+                assert_eq!(top, heights[y * CHUNK_SIZE_SAFE + x], "{} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn cells_cover_reach() {
+        let start = WorldPosition::new(64, -64, 0);
+        let cells = cells(&start, 32, 7);
+        assert_eq!(cells.first(), Some(&(1, -3)));
+        assert_eq!(cells.last(), Some(&(4, 0)));
+        assert_eq!(cells.len(), 16);
+
+        assert!(reaches(&start, [63, -65, -1], [1, 1, 1]));
+        assert!(!reaches(&start, [62, -65, -1], [1, 1, 1]));
+        assert!(!reaches(&start, [129, 0, 0], [4, 4, 4]));
+    }
+
+    #[test]
+    fn structures_match_across_chunk_borders() {
+        let mut config = WorldGenConfig::default();
+        config.boulders.per_side = 8;
+        config.boulders.chance = 1.0;
+        let config = Arc::new(config);
+
+        // chunks holding the terrain surface
+        let top = ColumnSampler::new(&WORLD_SEED, &config).top(0, 0);
+        let z = (top - 4).div_euclid(CHUNK_SIZE as i32);
+        let mut pipeline = Pipeline::empty();
+        pipeline.push(Boulders);
+        let generate = |x| {
+            let id = ChunkId::new(x, 0, z);
+            let seed = ChunkSeed::with_config(&WORLD_SEED, &id, config.clone());
+            let mut context = StageContext::new(&seed, &[0.0; 4], &[0.0; 4]);
+            let mut data = Box::<CubeSlice<Material, CHUNK_SIZE_SAFE>>::default();
+            pipeline.run(&mut data, &mut context);
+            data
+        };
+
+        let mut shared = 0;
+        let mut left = generate(0);
+        for x in 1..4 {
+            let right = generate(x);
+            // the last layer of the left chunk is the padding of the right chunk and the other way around
+            for z in 0..CHUNK_SIZE_SAFE {
+                for y in 0..CHUNK_SIZE_SAFE {
+                    for (l, r) in [(CHUNK_SIZE, 0), (CHUNK_SIZE + 1, 1)] {
+                        assert_eq!(left.get(l, y, z), right.get(r, y, z));
+                        shared += left.get(l, y, z).is_solid() as usize;
+                    }
+                }
+            }
+            left = right;
+        }
+        assert!(shared > 0);
+    }
+}
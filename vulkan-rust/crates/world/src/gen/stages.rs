@@ -0,0 +1,490 @@
+use gamedata::material::Material;
+use resources::prelude::CACHE;
+
+use super::{
+    biome::{Biome, Structure},
+    boulder::Boulder,
+    chunk::in_chunk_data,
+    pipeline::{GenerationStage, StageContext},
+    placement::{cell_rng, cells, reaches, ColumnSampler, PlacedStructure},
+};
+use crate::{
+    slice::CubeSlice,
+    storage::vox::ColorMapping,
+    terrain_noise,
+    traits::{Data3D, Generate, Voxelize},
+    WorldPosition, CHUNK_SIZE, CHUNK_SIZE_SAFE, CHUNK_SIZE_SAFE_I, CHUNK_SIZE_SAFE_SQUARED,
+};
+
+const TREE_PATH: &str = "\\assets\\tree.vox";
+
+/**
+ * Fills the terrain with stone and records the world z above the top block of every column.
+ */
+pub struct Height;
+
+impl GenerationStage for Height {
+    fn name(&self) -> &'static str {
+        "height"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        let config = context.config();
+        let height_noise = terrain_noise::height(
+            context.seed.world_seed(),
+            &config.height,
+            context.start.x - 1,
+            context.start.y - 1,
+            CHUNK_SIZE_SAFE,
+        );
+        // let height_noise = terrain_noise::wave(chunk_start.x - 1, chunk_start.y - 1, CHUNK_SIZE_SAFE);
+        let height_slice = &height_noise[..];
+        let mut heights = vec![0; CHUNK_SIZE_SAFE_SQUARED];
+        for y in 0..CHUNK_SIZE_SAFE {
+            for x in 0..CHUNK_SIZE_SAFE {
+                let i = y * CHUNK_SIZE_SAFE + x;
+                let height = context.biomes[i].height(height_slice[i], config.sea_level);
+                // the top block lies below the height, in the padded slice z starts one block lower
+                heights[i] = height as i32 - 1;
+                let height_in_chunk =
+                    ((height) as i32 - context.start.z).clamp(0, CHUNK_SIZE_SAFE as i32) as usize;
+                for z in 0..height_in_chunk {
+                    data.set(x, y, z, Material::Stone);
+                    if in_chunk_data(x, y, z) {
+                        context.voxel_count += 1;
+                    }
+                }
+            }
+        }
+        context.heights = Some(heights);
+    }
+}
+
+/**
+ * Fills everything below sea level with water, the top layer freezes in cold biomes.
+ */
+pub struct Water;
+
+impl GenerationStage for Water {
+    fn name(&self) -> &'static str {
+        "water"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        let sea_level = context.config().sea_level;
+        if context.start.z < sea_level {
+            // padded z of the highest voxel below sea level
+            let surface = sea_level - context.start.z;
+            let top = (surface as usize).min(CHUNK_SIZE_SAFE - 2);
+            for y in 0..CHUNK_SIZE_SAFE {
+                for x in 0..CHUNK_SIZE_SAFE {
+                    let frozen = context.weights(x, y).dominant().def().frozen;
+                    for z in 1..=top {
+                        if data.get(x, y, z).is_fillable() {
+                            let material = match frozen && z as i32 == surface {
+                                true => Material::Ice,
+                                false => Material::Water,
+                            };
+                            data.set(x, y, z, material);
+                            if in_chunk_data(x, y, z) {
+                                context.voxel_count += 1;
+                                context.opaque &= material.is_opaque();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Covers the top blocks of the terrain in the materials of their biome.
+ * Terrain under water is covered like the ocean floor. Needs the heights of [`Height`].
+ */
+pub struct Surface;
+
+impl GenerationStage for Surface {
+    fn name(&self) -> &'static str {
+        "surface"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        const DEPTH: i32 = 4;
+
+        let Some(heights) = &context.heights else {
+            return;
+        };
+        let sea_level = context.config().sea_level;
+        for y in 0..CHUNK_SIZE_SAFE {
+            for x in 0..CHUNK_SIZE_SAFE {
+                let top = heights[y * CHUNK_SIZE_SAFE + x];
+                let biome = match top <= sea_level {
+                    true => Biome::Ocean,
+                    false => context.column_biome(x, y),
+                };
+                let def = biome.def();
+                let surface = match def.snow_line {
+                    Some(snow_line) if top > sea_level + snow_line => Material::Snow,
+                    _ => def.surface,
+                };
+
+                for world_z in top - DEPTH..top {
+                    let z = world_z - context.start.z + 1;
+                    if !(0..CHUNK_SIZE_SAFE_I).contains(&z) {
+                        continue;
+                    }
+                    let z = z as usize;
+                    if data.get(x, y, z).is_solid() {
+                        match world_z == top - 1 {
+                            true => data.set(x, y, z, surface),
+                            false => data.set(x, y, z, def.subsurface),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Carves caves out of solid blocks.
+ */
+pub struct Caves;
+
+impl GenerationStage for Caves {
+    fn name(&self) -> &'static str {
+        "caves"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        let config = &context.config().caves;
+        let cave_noise = terrain_noise::caves(
+            context.seed.world_seed(),
+            config,
+            context.start.x - 1,
+            context.start.y - 1,
+            context.start.z - 1,
+            CHUNK_SIZE_SAFE,
+        );
+        let cave_slice = &cave_noise[..];
+        for z in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for x in 0..CHUNK_SIZE_SAFE {
+                    if cave_slice[z * CHUNK_SIZE_SAFE_SQUARED + y * CHUNK_SIZE_SAFE + x]
+                        < config.threshold
+                    {
+                        let material = data.get(x, y, z);
+                        if material.is_solid() {
+                            data.set(x, y, z, Material::Air);
+                            if in_chunk_data(x, y, z) {
+                                context.voxel_count -= 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Largest tree model, models start two voxels before the tree along x and y
+const TREE_SIZE: [i32; 3] = [16, 16, 32];
+const TREE_OFFSET: i32 = 2;
+const TREE_SALT: i32 = 1;
+/// Boulders are at most this large along every axis
+const BOULDER_SIZE: i32 = 14;
+const BOULDER_SALT: i32 = 2;
+
+/**
+ * Grows trees on the surface, as dense as the vegetation of the biomes.
+ * Trees are placed per cell of a grid across the world, every chunk they reach gets its part.
+ */
+pub struct Trees;
+
+impl GenerationStage for Trees {
+    fn name(&self) -> &'static str {
+        "trees"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        let seed = context.seed;
+        let config = seed.config();
+        let spacing = (CHUNK_SIZE / config.trees.per_side) as i32;
+        let mut columns = ColumnSampler::new(seed.world_seed(), config);
+        let mapping = ColorMapping::by_material_id();
+        let any_z = [context.start.z - 1, CHUNK_SIZE_SAFE_I];
+
+        for cell in cells(&context.start, spacing, TREE_SIZE[0]) {
+            let mut rng = cell_rng(seed.world_seed(), cell, TREE_SALT);
+            let x = cell.0 * spacing + rng.i32(1..spacing - 1);
+            let y = cell.1 * spacing + rng.i32(1..spacing - 1);
+            let growth = rng.f32();
+            let model = rng.usize(..);
+
+            let min = [x - TREE_OFFSET, y - TREE_OFFSET];
+            if !reaches(
+                &context.start,
+                [min[0], min[1], any_z[0]],
+                [TREE_SIZE[0], TREE_SIZE[1], any_z[1]],
+            ) || growth >= columns.weights(x, y).blend(|b| b.vegetation)
+            {
+                continue;
+            }
+
+            // no trees under water or above caves
+            let top = columns.top(x, y);
+            if top <= config.sea_level
+                || !reaches(&context.start, [min[0], min[1], top], TREE_SIZE)
+                || columns.is_cave(x, y, top - 1)
+            {
+                continue;
+            }
+
+            let vox = CACHE.get_vox(TREE_PATH);
+            let model = &vox.models[model % vox.models.len()];
+            let voxels = model
+                .voxels
+                .iter()
+                .filter_map(|voxel| {
+                    let position = WorldPosition::new(
+                        min[0] + voxel.point.x as i32,
+                        min[1] + voxel.point.y as i32,
+                        top + voxel.point.z as i32,
+                    );
+                    Some((position, mapping.get(voxel.color_index)?))
+                })
+                .collect();
+            PlacedStructure { voxels }.stamp(data, context);
+        }
+    }
+}
+
+/**
+ * Drops boulders on the ground of biomes that have them, placed like [`Trees`].
+ */
+pub struct Boulders;
+
+impl GenerationStage for Boulders {
+    fn name(&self) -> &'static str {
+        "boulders"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        let seed = context.seed;
+        let config = seed.config();
+        let spacing = (CHUNK_SIZE / config.boulders.per_side) as i32;
+        let mut columns = ColumnSampler::new(seed.world_seed(), config);
+        let any_z = [context.start.z - 1, CHUNK_SIZE_SAFE_I];
+
+        for cell in cells(&context.start, spacing, BOULDER_SIZE) {
+            let mut rng = cell_rng(seed.world_seed(), cell, BOULDER_SALT);
+            let x = cell.0 * spacing + rng.i32(1..spacing - 1);
+            let y = cell.1 * spacing + rng.i32(1..spacing - 1);
+            let roll = rng.f32();
+            let shape = rng.u64(..);
+
+            let (half, size) = (BOULDER_SIZE / 2, BOULDER_SIZE + 1);
+            if roll >= config.boulders.chance
+                || !reaches(
+                    &context.start,
+                    [x - half, y - half, any_z[0]],
+                    [size, size, any_z[1]],
+                )
+            {
+                continue;
+            }
+            let biome = columns.weights(x, y).dominant();
+            if !biome.def().structures.contains(&Structure::Boulder) {
+                continue;
+            }
+
+            // boulders lie on the ground, half buried
+            let top = columns.top(x, y);
+            if columns.is_cave(x, y, top - 1) {
+                continue;
+            }
+            let boulder = Boulder::generate(shape);
+            let size = [boulder.width, boulder.depth, boulder.height].map(|s| s as i32);
+            let min = [x - size[0] / 2, y - size[1] / 2, top - size[2] / 2];
+            if !reaches(&context.start, min, size) {
+                continue;
+            }
+
+            let boulder_voxels = boulder.voxelize();
+            let mut voxels = vec![];
+            for z in 0..boulder.height {
+                for y in 0..boulder.depth {
+                    for x in 0..boulder.width {
+                        let material = boulder_voxels.get(x, y, z);
+                        if material != Material::default() {
+                            let position = WorldPosition::new(
+                                min[0] + x as i32,
+                                min[1] + y as i32,
+                                min[2] + z as i32,
+                            );
+                            voxels.push((position, material));
+                        }
+                    }
+                }
+            }
+            PlacedStructure { voxels }.stamp(data, context);
+        }
+    }
+}
+
+/**
+ * Outlines the edges of the chunk in stone, for debugging.
+ */
+pub struct Frame;
+
+impl GenerationStage for Frame {
+    fn name(&self) -> &'static str {
+        "frame"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        for d in 0..3 {
+            let u = (d + 1) % 3;
+            let v = (d + 2) % 3;
+            let mut x = [1, 1, 1];
+            for i in 1..65 {
+                x[d] = i;
+
+                x[u] = 1;
+                x[v] = 1;
+                data.set(x[0], x[1], x[2], Material::Stone);
+                x[u] = 1;
+                x[v] = 64;
+                data.set(x[0], x[1], x[2], Material::Stone);
+                x[u] = 64;
+                x[v] = 1;
+                data.set(x[0], x[1], x[2], Material::Stone);
+                x[u] = 64;
+                x[v] = 64;
+                data.set(x[0], x[1], x[2], Material::Stone);
+
+                context.voxel_count += 4;
+            }
+        }
+    }
+}
+
+/**
+ * Fills the whole chunk with stone, for debugging.
+ */
+pub struct Full;
+
+impl GenerationStage for Full {
+    fn name(&self) -> &'static str {
+        "full"
+    }
+
+    fn apply(&self, data: &mut CubeSlice<Material, CHUNK_SIZE_SAFE>, context: &mut StageContext) {
+        for x in 1..(CHUNK_SIZE_SAFE - 1) {
+            for y in 1..(CHUNK_SIZE_SAFE - 1) {
+                for z in 1..(CHUNK_SIZE_SAFE - 1) {
+                    data.set(x, y, z, Material::Stone);
+                    context.voxel_count += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use gamedata::material::Material;
+
+    use super::{Caves, Full, Height, Surface, Water};
+    use crate::{
+        gen::{
+            chunk::in_chunk_data,
+            pipeline::{GenerationStage, StageContext},
+        },
+        slice::CubeSlice,
+        traits::Data3D,
+        ChunkId, ChunkSeed, WorldGenConfig, WorldSeed, CHUNK_SIZE_CUBED, CHUNK_SIZE_SAFE,
+        CHUNK_SIZE_SQUARED,
+    };
+
+    const WORLD_SEED: WorldSeed = WorldSeed::new(17);
+
+    fn count(data: &CubeSlice<Material, CHUNK_SIZE_SAFE>, test: fn(Material) -> bool) -> usize {
+        let mut count = 0;
+        for z in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for x in 0..CHUNK_SIZE_SAFE {
+                    if in_chunk_data(x, y, z) && test(data.get(x, y, z)) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn height_counts_its_voxels() {
+        let seed = ChunkSeed::new(&WORLD_SEED, &ChunkId::new(0, 0, 0));
+        let mut context = StageContext::new(&seed, &[0.0; 4], &[0.0; 4]);
+        let mut data = CubeSlice::default();
+
+        Height.apply(&mut data, &mut context);
+        assert!(context.heights.is_some());
+        assert_eq!(context.voxel_count, count(&data, |m| m == Material::Stone));
+    }
+
+    #[test]
+    fn water_fills_empty_chunks_below_sea_level() {
+        let seed = ChunkSeed::new(&WORLD_SEED, &ChunkId::new(0, 0, -1));
+        let mut context = StageContext::new(&seed, &[0.05; 4], &[0.0; 4]);
+        let mut data = CubeSlice::default();
+
+        Water.apply(&mut data, &mut context);
+        assert_eq!(context.voxel_count, CHUNK_SIZE_CUBED);
+        assert_eq!(count(&data, |m| m == Material::Water), CHUNK_SIZE_CUBED);
+        assert!(!context.opaque);
+    }
+
+    #[test]
+    fn water_stops_at_sea_level() {
+        let mut config = WorldGenConfig::default();
+        config.sea_level = 20;
+        let id = ChunkId::new(0, 0, 0);
+        let seed = ChunkSeed::with_config(&WORLD_SEED, &id, Arc::new(config));
+        let mut context = StageContext::new(&seed, &[0.05; 4], &[0.0; 4]);
+        let mut data = CubeSlice::default();
+
+        Water.apply(&mut data, &mut context);
+        assert_eq!(context.voxel_count, 20 * CHUNK_SIZE_SQUARED);
+        assert_eq!(data.get(1, 1, 20), Material::Water);
+        assert!(data.get(1, 1, 21).is_fillable());
+    }
+
+    #[test]
+    fn surface_needs_heights() {
+        let seed = ChunkSeed::new(&WORLD_SEED, &ChunkId::new(0, 0, 0));
+        let mut context = StageContext::new(&seed, &[0.0; 4], &[0.0; 4]);
+        let mut data = CubeSlice::default();
+        Full.apply(&mut data, &mut context);
+
+        Surface.apply(&mut data, &mut context);
+        assert_eq!(count(&data, |m| m == Material::Stone), CHUNK_SIZE_CUBED);
+    }
+
+    #[test]
+    fn caves_carve_solid_blocks() {
+        let seed = ChunkSeed::new(&WORLD_SEED, &ChunkId::new(1, 0, -4));
+        let mut context = StageContext::new(&seed, &[0.0; 4], &[0.0; 4]);
+        let mut data = CubeSlice::default();
+        Full.apply(&mut data, &mut context);
+        assert_eq!(context.voxel_count, CHUNK_SIZE_CUBED);
+
+        Caves.apply(&mut data, &mut context);
+        assert!(context.voxel_count < CHUNK_SIZE_CUBED);
+        assert_eq!(context.voxel_count, count(&data, |m| m.is_solid()));
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+
+use gamedata::material::Material;
+
+use crate::{ChunkId, ChunkManager, WorldPosition};
+
+fn is_support(material: Option<Material>) -> bool {
+    match material {
+        Some(m) => m.is_solid(),
+        // unloaded chunks hold everything above them
+        None => true,
+    }
+}
+
+/**
+ * Lets gravity blocks at and above a changed voxel fall until they rest on a block.
+ * Whole columns of gravity blocks fall together, blocks of other materials stay in place.
+ * Whatever the column falls through, like water, moves up above it.
+ * The moves are player edits and grouped into one transaction.
+ * Returns the chunks that need to be remeshed.
+ */
+pub fn settle(chunks: &mut ChunkManager, position: &WorldPosition) -> HashSet<ChunkId> {
+    let mut dirty = HashSet::new();
+    chunks.begin_transaction();
+    for z in [position.z, position.z + 1] {
+        fall(
+            chunks,
+            &WorldPosition::new(position.x, position.y, z),
+            &mut dirty,
+        );
+    }
+    chunks.commit_transaction();
+    dirty
+}
+
+/**
+ * Settles around every changed voxel of an edit, in one transaction.
+ */
+pub fn settle_all<'a>(
+    chunks: &mut ChunkManager,
+    positions: impl IntoIterator<Item = &'a WorldPosition>,
+) -> HashSet<ChunkId> {
+    let mut dirty = HashSet::new();
+    chunks.begin_transaction();
+    for position in positions {
+        dirty.extend(settle(chunks, position));
+    }
+    chunks.commit_transaction();
+    dirty
+}
+
+fn fall(chunks: &mut ChunkManager, bottom: &WorldPosition, dirty: &mut HashSet<ChunkId>) {
+    let at = |z| WorldPosition::new(bottom.x, bottom.y, z);
+
+    let mut column = vec![];
+    while let Some(m) = chunks.get_block(&at(bottom.z + column.len() as i32)) {
+        if !m.has_gravity() {
+            break;
+        }
+        column.push(m);
+    }
+
+    let mut landing = bottom.z;
+    while !is_support(chunks.get_block(&at(landing - 1))) {
+        landing -= 1;
+    }
+    if column.is_empty() || landing == bottom.z {
+        return;
+    }
+
+    // the column and the voxels it falls through swap places, empty voxels become unset like
+    // removed blocks
+    let displaced = (landing..bottom.z).map(|z| match chunks.get_block(&at(z)) {
+        Some(m) if !m.is_invisible() => m,
+        _ => Material::Unset,
+    });
+    let settled = column.iter().copied().chain(displaced).collect::<Vec<_>>();
+    for (i, m) in settled.into_iter().enumerate() {
+        let to = at(landing + i as i32);
+        if chunks.get_block(&to) != Some(m) {
+            chunks.edit_block(to.x, to.y, to.z, m);
+            dirty.extend(chunks.chunks_to_remesh(&to));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{settle, settle_all};
+    use crate::{ChunkData, ChunkId, ChunkManager, WorldPosition};
+
+    /**
+     * Chunk (0, 0, 0) and the one above it, with a stone floor at z = 0.
+     */
+    fn ground() -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+        for id in [ChunkId::new(0, 0, 0), ChunkId::new(0, 0, 1)] {
+            chunks.insert(&id, ChunkData::default());
+        }
+        chunks.set_block(5, 5, 0, Material::Stone).unwrap();
+        chunks
+    }
+
+    fn column(chunks: &ChunkManager, from: i32, to: i32) -> Vec<Material> {
+        (from..=to)
+            .map(|z| chunks.get_block(&WorldPosition::new(5, 5, z)).unwrap())
+            .collect()
+    }
+
+    fn dig(chunks: &mut ChunkManager, z: i32) -> usize {
+        chunks.begin_transaction();
+        chunks.edit_block(5, 5, z, Material::Unset).unwrap();
+        let dirty = settle(chunks, &WorldPosition::new(5, 5, z));
+        chunks.commit_transaction();
+        dirty.len()
+    }
+
+    #[test]
+    fn sand_column_falls_when_dug_under() {
+        let mut chunks = ground();
+        chunks.set_block(5, 5, 4, Material::Stone).unwrap();
+        for z in 5..8 {
+            chunks.set_block(5, 5, z, Material::Sand).unwrap();
+        }
+
+        assert_eq!(dig(&mut chunks, 4), 1);
+        use Material::*;
+        assert_eq!(
+            column(&chunks, 0, 8),
+            vec![Stone, Sand, Sand, Sand, Unset, Unset, Unset, Unset, Unset]
+        );
+    }
+
+    #[test]
+    fn only_gravity_blocks_fall() {
+        let mut chunks = ground();
+        use Material::*;
+        for (z, m) in [(3, Stone), (4, Gravel), (5, Sand), (6, Wood), (7, Sand)] {
+            chunks.set_block(5, 5, z, m).unwrap();
+        }
+
+        dig(&mut chunks, 3);
+        assert_eq!(
+            column(&chunks, 0, 7),
+            vec![Stone, Gravel, Sand, Unset, Unset, Unset, Wood, Sand]
+        );
+    }
+
+    #[test]
+    fn sand_falls_across_chunks() {
+        let mut chunks = ground();
+        chunks.set_block(5, 5, 63, Material::Stone).unwrap();
+        chunks.set_block(5, 5, 64, Material::Sand).unwrap();
+        chunks.set_block(5, 5, 65, Material::Sand).unwrap();
+
+        assert_eq!(dig(&mut chunks, 63), 2);
+        assert_eq!(column(&chunks, 1, 2), vec![Material::Sand; 2]);
+        assert_eq!(column(&chunks, 64, 65), vec![Material::Unset; 2]);
+    }
+
+    #[test]
+    fn placed_sand_falls_and_undo_restores() {
+        let mut chunks = ground();
+        chunks.begin_transaction();
+        chunks.edit_block(5, 5, 10, Material::Sand).unwrap();
+        settle(&mut chunks, &WorldPosition::new(5, 5, 10));
+        chunks.commit_transaction();
+        assert_eq!(column(&chunks, 1, 1), vec![Material::Sand]);
+        assert_eq!(chunks.history().undo_len(), 1);
+
+        chunks.undo();
+        assert_eq!(column(&chunks, 1, 1), vec![Material::Unset]);
+        assert_eq!(column(&chunks, 10, 10), vec![Material::Unset]);
+    }
+
+    #[test]
+    fn sand_sinks_through_water() {
+        let mut chunks = ground();
+        for z in 1..4 {
+            chunks.set_block(5, 5, z, Material::Water).unwrap();
+        }
+        chunks.set_block(5, 5, 4, Material::Sand).unwrap();
+
+        settle_all(&mut chunks, [WorldPosition::new(5, 5, 4)].iter());
+        use Material::*;
+        assert_eq!(
+            column(&chunks, 1, 5),
+            vec![Sand, Water, Water, Water, Unset]
+        );
+    }
+
+    #[test]
+    fn undo_and_redo_keep_fallen_sand() {
+        let mut chunks = ground();
+        chunks.set_block(5, 5, 3, Material::Stone).unwrap();
+        chunks.set_block(5, 5, 4, Material::Sand).unwrap();
+        dig(&mut chunks, 3);
+        use Material::*;
+        let fallen = vec![Sand, Unset, Unset, Unset];
+        assert_eq!(column(&chunks, 1, 4), fallen);
+
+        chunks.undo();
+        assert_eq!(column(&chunks, 1, 4), vec![Unset, Unset, Stone, Sand]);
+        chunks.redo();
+        assert_eq!(column(&chunks, 1, 4), fallen);
+        assert_eq!(chunks.history().undo_len(), 1);
+        assert_eq!(chunks.history().redo_len(), 0);
+
+        chunks.undo();
+        assert_eq!(chunks.history().redo_len(), 1);
+        assert_eq!(column(&chunks, 1, 4), vec![Unset, Unset, Stone, Sand]);
+    }
+}
@@ -1,17 +1,28 @@
 #![feature(test)]
+#![feature(variant_count)]
 
 extern crate nalgebra_glm as glm;
 extern crate test;
 
+pub mod binary_mesh;
+pub mod fluid;
 pub mod gen;
+pub mod gravity;
+pub mod light;
+pub mod lod;
 pub mod mesh_generator; // TODO: extract
 pub mod mesh_manager; // TODO: extract
 pub mod mgmt;
+pub mod neighborhood;
+pub mod octree_mesh;
 pub mod slice;
+pub mod storage;
+pub mod surface_nets;
 pub mod traits;
 
 pub use chunk_id::{ChunkId, MeshId};
-pub use mgmt::chunk::ChunkManager;
+pub use gen::config::WorldGenConfig;
+pub use mgmt::{brush::Brush, chunk::ChunkManager, history::EditHistory, journal::EditJournal};
 pub use seed::{ChunkSeed, PositionalSeed, WorldSeed};
 pub use world_parameters::*;
 pub use world_position::WorldPosition;
@@ -23,12 +34,13 @@ mod world_parameters;
 mod world_position;
 
 use gamedata::material::Material;
+use gen::chunk::{compress, Chunk};
 use geometry::{Ray, AABB};
 use glm::Vec3;
-use octree::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node, LeafAccess};
+use octree::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node, LeafAccess, Regions};
 use rand::{thread_rng, Rng};
 use std::{ops::Range, sync::Arc};
-use traits::Data3D;
+use traits::{Data3D, Generate, Voxelize};
 
 pub struct ChunkIdAndData {
     pub id: ChunkId,
@@ -69,24 +81,42 @@ impl From<&ChunkId> for WorldPosition {
 
 pub struct World {
     pub seed: WorldSeed,
+    pub gen_config: Arc<WorldGenConfig>,
     pub chunk_manager: ChunkManager,
 }
 
 impl World {
     pub fn random() -> Self {
-        Self {
-            seed: WorldSeed::new(thread_rng().gen()),
-            chunk_manager: ChunkManager::new(),
-        }
+        Self::new(WorldSeed::new(thread_rng().gen()))
     }
 
     pub fn new(seed: WorldSeed) -> Self {
+        Self::with_config(seed, WorldGenConfig::default())
+    }
+
+    pub fn with_config(seed: WorldSeed, gen_config: WorldGenConfig) -> Self {
         Self {
             seed,
+            gen_config: Arc::new(gen_config),
             chunk_manager: ChunkManager::new(),
         }
     }
 
+    /**
+     * Seed for generating a chunk of this world.
+     */
+    pub fn chunk_seed(&self, id: &ChunkId) -> ChunkSeed {
+        ChunkSeed::with_config(&self.seed, id, self.gen_config.clone())
+    }
+
+    /**
+     * Chunk as the generator creates it, without any edits.
+     */
+    pub fn generate_chunk(&self, id: &ChunkId) -> ChunkData {
+        let generated = Chunk::generate(self.chunk_seed(id)).voxelize();
+        compress(&generated.voxels)
+    }
+
     pub fn intersects_point(&self, p: [f32; 3]) -> bool {
         let id = ChunkId::new(
             p[0] as i32 / CHUNK_SIZE as i32,
@@ -174,7 +204,7 @@ impl Raycast for World {
 
 type TopNode<T> = L6Node<T>;
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq, Debug)]
 pub struct ChunkData(TopNode<Material>, usize);
 
 impl Data3D<Material> for ChunkData {
@@ -213,6 +243,16 @@ impl ChunkData {
             TopNode::Sparse(_) => self.1 != CHUNK_SIZE_CUBED,
         }
     }
+
+    /**
+     * Calls f with the origin, size and material of every uniform region of the chunk.
+     */
+    pub fn for_each_region(&self, mut f: impl FnMut([usize; 3], usize, Material)) {
+        self.0
+            .for_each_region([0, 0, 0], &mut |origin, size, value| {
+                f(origin, size, value.unwrap_or_default())
+            });
+    }
 }
 
 fn get_child_aabbs(size: usize) -> [AABB; 8] {
@@ -416,9 +456,13 @@ mod tests {
     use gamedata::material::Material;
     use geometry::Ray;
     use nalgebra_glm::Vec3;
-    use octree::{L1Node, L2Node, L3Node, L4Node, L5Node, L6Node};
+    use octree::{Codec, DecodeError, L1Node, L2Node, L3Node, L4Node, L5Node, L6Node};
 
-    use crate::{ChunkData, ChunkId, Raycast, World, CHUNK_SIZE_CUBED};
+    use crate::{
+        gen::chunk::{compress, Chunk},
+        traits::{Generate, Voxelize},
+        ChunkData, ChunkId, ChunkSeed, Raycast, World, WorldSeed, CHUNK_SIZE_CUBED,
+    };
 
     mod nodes {
 
@@ -718,4 +762,22 @@ mod tests {
             .map(|distance| ray.point_on_ray(distance));
         assert_eq!(hit, Some(Vec3::new(-64.0, -64.0, -64.0)));
     }
+
+    #[test]
+    fn chunks_round_trip_through_codec() {
+        // underground chunks, so no tree models are required
+        for id in [ChunkId::new(0, 0, -1), ChunkId::new(-5, 7, -2)] {
+            let generated = Chunk::generate(ChunkSeed::new(&WorldSeed::new(17), &id)).voxelize();
+            let data = compress(&generated.voxels);
+            assert_eq!(L6Node::decode(&data.0.encode()), Ok(data.0));
+        }
+
+        // the table holds one value that is not a material
+        let mut bytes = L6Node::Full(Material::Stone).encode();
+        bytes[5] = u8::MAX;
+        assert_eq!(
+            L6Node::<Material>::decode(&bytes),
+            Err(DecodeError::InvalidValue)
+        );
+    }
 }
@@ -0,0 +1,633 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use gamedata::material::Material;
+use octree::{L6Node, LeafAccess};
+
+use crate::{
+    mgmt::brush::chunks_touching, traits::Data3D, ChunkData, ChunkId, WorldPosition, CHUNK_SIZE,
+    CHUNK_SIZE_CUBED, CHUNK_SIZE_I,
+};
+
+pub const MAX_LIGHT: u8 = 15;
+
+/**
+ * Sky light in the upper and block light in the lower 4 bits.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Light(u8);
+
+impl Light {
+    pub const DARK: Self = Self(0);
+    pub const SKY: Self = Self(MAX_LIGHT << 4);
+
+    pub fn new(sky: u8, block: u8) -> Self {
+        debug_assert!(sky <= MAX_LIGHT && block <= MAX_LIGHT);
+        Self(sky << 4 | block)
+    }
+
+    pub fn sky(&self) -> u8 {
+        self.0 >> 4
+    }
+
+    pub fn block(&self) -> u8 {
+        self.0 & MAX_LIGHT
+    }
+
+    /**
+     * Both channels scaled to 0..=1, as expected by the vertex light attribute.
+     */
+    pub fn normalized(&self) -> glm::Vec2 {
+        glm::vec2(
+            self.sky() as f32 / MAX_LIGHT as f32,
+            self.block() as f32 / MAX_LIGHT as f32,
+        )
+    }
+
+    fn channel(&self, channel: Channel) -> u8 {
+        match channel {
+            Channel::Sky => self.sky(),
+            Channel::Block => self.block(),
+        }
+    }
+
+    fn with_channel(self, channel: Channel, level: u8) -> Self {
+        match channel {
+            Channel::Sky => Self::new(level, self.block()),
+            Channel::Block => Self::new(self.sky(), level),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Channel {
+    Sky = 0,
+    Block = 1,
+}
+
+const CHANNELS: [Channel; 2] = [Channel::Sky, Channel::Block];
+
+const OFFSETS: [[i32; 3]; 6] = [
+    [-1, 0, 0],
+    [1, 0, 0],
+    [0, -1, 0],
+    [0, 1, 0],
+    [0, 0, -1],
+    [0, 0, 1],
+];
+
+/**
+ * Light lost when entering a voxel of this material, None if it blocks light.
+ */
+fn absorption(material: Material) -> Option<u8> {
+    if material.is_opaque() {
+        None
+    } else if material == Material::Water {
+        Some(1)
+    } else {
+        Some(0)
+    }
+}
+
+/**
+ * Level of light after moving into a voxel. Full sky light travels down without falling off.
+ */
+fn propagate(channel: Channel, level: u8, down: bool, into: Material) -> u8 {
+    let Some(absorption) = absorption(into) else {
+        return 0;
+    };
+
+    if channel == Channel::Sky && down && level == MAX_LIGHT {
+        MAX_LIGHT - absorption
+    } else {
+        level.saturating_sub(1 + absorption)
+    }
+}
+
+fn is_down(offset: &[i32; 3]) -> bool {
+    offset[2] == -1
+}
+
+fn offset_position(position: &WorldPosition, offset: &[i32; 3]) -> WorldPosition {
+    WorldPosition::new(
+        position.x + offset[0],
+        position.y + offset[1],
+        position.z + offset[2],
+    )
+}
+
+fn material_at(
+    materials: &HashMap<ChunkId, ChunkData>,
+    position: &WorldPosition,
+) -> Option<Material> {
+    let local = position.rem_euclid(CHUNK_SIZE_I);
+    materials
+        .get(&ChunkId::from(position))
+        .map(|data| data.get(local.x as usize, local.y as usize, local.z as usize))
+}
+
+/**
+ * Light of all voxels in a chunk.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkLight {
+    node: L6Node<Light>,
+    open_sky_assumed: bool,
+}
+
+impl Data3D<Light> for ChunkLight {
+    fn set(&mut self, x: usize, y: usize, z: usize, value: Light) {
+        self.node.set(x, y, z, value);
+    }
+
+    fn get(&self, x: usize, y: usize, z: usize) -> Light {
+        self.node.get(x, y, z).unwrap_or_default()
+    }
+}
+
+/**
+ * Sky light entering a chunk from above.
+ */
+pub enum SkyBoundary<'a> {
+    /// Nothing is known about the chunk above, it is corrected once the chunk is inserted.
+    Open,
+    Dark,
+    Chunk(&'a ChunkLight),
+}
+
+/**
+ * Lights a single chunk. Light from neighbors other than the sky above
+ * is exchanged when the chunk is inserted into a `LightMap`.
+ */
+pub fn compute(data: &ChunkData, sky: SkyBoundary) -> ChunkLight {
+    let index = |x: usize, y: usize, z: usize| (z * CHUNK_SIZE + y) * CHUNK_SIZE + x;
+
+    let mut materials = vec![Material::default(); CHUNK_SIZE_CUBED];
+    let mut levels = [vec![0; CHUNK_SIZE_CUBED], vec![0; CHUNK_SIZE_CUBED]];
+    let mut queues = [VecDeque::new(), VecDeque::new()];
+
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let i = index(x, y, z);
+                materials[i] = data.get(x, y, z);
+                let emission = materials[i].emission();
+                if emission > 0 {
+                    levels[Channel::Block as usize][i] = emission;
+                    queues[Channel::Block as usize].push_back(i);
+                }
+            }
+        }
+    }
+
+    for y in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let mut level = match sky {
+                SkyBoundary::Open => MAX_LIGHT,
+                SkyBoundary::Dark => 0,
+                SkyBoundary::Chunk(above) => above.get(x, y, 0).sky(),
+            };
+            for z in (0..CHUNK_SIZE).rev() {
+                let i = index(x, y, z);
+                level = propagate(Channel::Sky, level, true, materials[i]);
+                if level == 0 {
+                    break;
+                }
+                levels[Channel::Sky as usize][i] = level;
+                queues[Channel::Sky as usize].push_back(i);
+            }
+        }
+    }
+
+    for channel in CHANNELS {
+        let levels = &mut levels[channel as usize];
+        let queue = &mut queues[channel as usize];
+        while let Some(i) = queue.pop_front() {
+            let position = [
+                i % CHUNK_SIZE,
+                i / CHUNK_SIZE % CHUNK_SIZE,
+                i / CHUNK_SIZE / CHUNK_SIZE,
+            ]
+            .map(|p| p as i32);
+            for offset in &OFFSETS {
+                let [x, y, z] = [0, 1, 2].map(|d| position[d] + offset[d]);
+                if [x, y, z].iter().any(|p| *p < 0 || *p >= CHUNK_SIZE_I) {
+                    continue;
+                }
+
+                let n = index(x as usize, y as usize, z as usize);
+                let next = propagate(channel, levels[i], is_down(offset), materials[n]);
+                if next > levels[n] {
+                    levels[n] = next;
+                    queue.push_back(n);
+                }
+            }
+        }
+    }
+
+    let mut light = ChunkLight {
+        node: L6Node::Empty,
+        open_sky_assumed: matches!(sky, SkyBoundary::Open),
+    };
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let i = index(x, y, z);
+                let value = Light::new(levels[0][i], levels[1][i]);
+                if value != Light::DARK {
+                    light.set(x, y, z, value);
+                }
+            }
+        }
+    }
+    light
+}
+
+/**
+ * Light of all loaded chunks. Changes are propagated across chunk borders,
+ * chunks with changed light are collected for remeshing.
+ */
+#[derive(Debug, Default)]
+pub struct LightMap {
+    chunks: HashMap<ChunkId, ChunkLight>,
+    relit: HashSet<ChunkId>,
+}
+
+impl LightMap {
+    pub fn get(&self, id: &ChunkId) -> Option<&ChunkLight> {
+        self.chunks.get(id)
+    }
+
+    pub fn light_at(&self, position: &WorldPosition) -> Option<Light> {
+        let local = position.rem_euclid(CHUNK_SIZE_I);
+        self.chunks
+            .get(&ChunkId::from(position))
+            .map(|light| light.get(local.x as usize, local.y as usize, local.z as usize))
+    }
+
+    /**
+     * Adds the light of a chunk and exchanges light with its loaded neighbors.
+     */
+    pub fn insert(
+        &mut self,
+        id: ChunkId,
+        light: ChunkLight,
+        materials: &HashMap<ChunkId, ChunkData>,
+    ) {
+        self.chunks.insert(id, light);
+
+        let mut queues = [VecDeque::new(), VecDeque::new()];
+        let above = ChunkId::new(id.x, id.y, id.z + 1);
+        let below = ChunkId::new(id.x, id.y, id.z - 1);
+        self.resolve_sky(&id, &above, materials, &mut queues);
+        self.resolve_sky(&below, &id, materials, &mut queues);
+
+        let origin = WorldPosition::from(&id);
+        for offset in &OFFSETS {
+            let neighbor = ChunkId::new(id.x + offset[0], id.y + offset[1], id.z + offset[2]);
+            if !self.chunks.contains_key(&neighbor) {
+                continue;
+            }
+
+            let d = offset.iter().position(|o| *o != 0).unwrap();
+            let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+            let inner = if offset[d] > 0 { CHUNK_SIZE_I - 1 } else { 0 };
+            let reverse = offset.map(|o| -o);
+            for a in 0..CHUNK_SIZE_I {
+                for b in 0..CHUNK_SIZE_I {
+                    let mut local = [0; 3];
+                    local[d] = inner;
+                    local[u] = a;
+                    local[v] = b;
+                    let inside = WorldPosition::new(
+                        origin.x + local[0],
+                        origin.y + local[1],
+                        origin.z + local[2],
+                    );
+                    let outside = offset_position(&inside, offset);
+                    self.seed(&inside, &outside, offset, materials, &mut queues);
+                    self.seed(&outside, &inside, &reverse, materials, &mut queues);
+                }
+            }
+        }
+
+        self.flood(queues, materials);
+    }
+
+    /**
+     * Updates the light around a voxel after its material was changed.
+     */
+    pub fn update_block(
+        &mut self,
+        position: &WorldPosition,
+        materials: &HashMap<ChunkId, ChunkData>,
+    ) {
+        let (Some(light), Some(material)) =
+            (self.light_at(position), material_at(materials, position))
+        else {
+            return;
+        };
+
+        let mut queues = [VecDeque::new(), VecDeque::new()];
+        for channel in CHANNELS {
+            let level = light.channel(channel);
+            let queue = &mut queues[channel as usize];
+            if level > 0 {
+                self.set_level(position, channel, 0);
+                self.unlight(channel, position.clone(), level, materials, queue);
+            }
+            queue.extend(
+                OFFSETS
+                    .iter()
+                    .map(|offset| offset_position(position, offset)),
+            );
+        }
+
+        if material.emission() > 0 {
+            self.set_level(position, Channel::Block, material.emission());
+            queues[Channel::Block as usize].push_back(position.clone());
+        }
+
+        self.flood(queues, materials);
+    }
+
+    pub fn remove(&mut self, id: &ChunkId) {
+        self.chunks.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.relit.clear();
+    }
+
+    /**
+     * Loaded chunks whose light changed since the last call.
+     */
+    pub fn take_relit(&mut self) -> HashSet<ChunkId> {
+        let mut relit = std::mem::take(&mut self.relit);
+        relit.retain(|id| self.chunks.contains_key(id));
+        relit
+    }
+
+    fn set_level(&mut self, position: &WorldPosition, channel: Channel, level: u8) {
+        let local = position.rem_euclid(CHUNK_SIZE_I);
+        let (x, y, z) = (local.x as usize, local.y as usize, local.z as usize);
+        if let Some(light) = self.chunks.get_mut(&ChunkId::from(position)) {
+            light.set(x, y, z, light.get(x, y, z).with_channel(channel, level));
+            self.relit.extend(chunks_touching(position));
+        }
+    }
+
+    /**
+     * Darkens the chunk below where the sky light assumed for it is blocked by the chunk above.
+     */
+    fn resolve_sky(
+        &mut self,
+        below: &ChunkId,
+        above: &ChunkId,
+        materials: &HashMap<ChunkId, ChunkData>,
+        queues: &mut [VecDeque<WorldPosition>; 2],
+    ) {
+        let (Some(above_light), Some(below_light)) = (self.chunks.get(above), self.chunks.get(below))
+        else {
+            return;
+        };
+        if !below_light.open_sky_assumed {
+            return;
+        }
+
+        let origin = WorldPosition::from(below);
+        let mut blocked = vec![];
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let position = WorldPosition::new(
+                    origin.x + x as i32,
+                    origin.y + y as i32,
+                    origin.z + CHUNK_SIZE_I - 1,
+                );
+                let Some(material) = material_at(materials, &position) else {
+                    continue;
+                };
+                let incoming =
+                    propagate(Channel::Sky, above_light.get(x, y, 0).sky(), true, material);
+                let current = below_light.get(x, y, CHUNK_SIZE - 1).sky();
+                if current > incoming {
+                    blocked.push((position, current));
+                }
+            }
+        }
+
+        if let Some(light) = self.chunks.get_mut(below) {
+            light.open_sky_assumed = false;
+        }
+        for (position, level) in blocked {
+            if self.light_at(&position).map(|l| l.sky()) == Some(level) {
+                self.set_level(&position, Channel::Sky, 0);
+                self.unlight(Channel::Sky, position, level, materials, &mut queues[0]);
+            }
+        }
+    }
+
+    /**
+     * Queues the light flowing from one voxel into another if it brightens the target.
+     */
+    fn seed(
+        &mut self,
+        from: &WorldPosition,
+        to: &WorldPosition,
+        offset: &[i32; 3],
+        materials: &HashMap<ChunkId, ChunkData>,
+        queues: &mut [VecDeque<WorldPosition>; 2],
+    ) {
+        let (Some(source), Some(target), Some(material)) = (
+            self.light_at(from),
+            self.light_at(to),
+            material_at(materials, to),
+        ) else {
+            return;
+        };
+
+        for channel in CHANNELS {
+            let next = propagate(channel, source.channel(channel), is_down(offset), material);
+            if next > target.channel(channel) {
+                self.set_level(to, channel, next);
+                queues[channel as usize].push_back(to.clone());
+            }
+        }
+    }
+
+    /**
+     * Removes all light that originated from a voxel which was already set to dark.
+     * Voxels lit by other sources are queued to refill the removed area.
+     */
+    fn unlight(
+        &mut self,
+        channel: Channel,
+        position: WorldPosition,
+        level: u8,
+        materials: &HashMap<ChunkId, ChunkData>,
+        refill: &mut VecDeque<WorldPosition>,
+    ) {
+        let mut removals = VecDeque::from([(position, level)]);
+        while let Some((position, level)) = removals.pop_front() {
+            for offset in &OFFSETS {
+                let neighbor = offset_position(&position, offset);
+                let (Some(light), Some(material)) =
+                    (self.light_at(&neighbor), material_at(materials, &neighbor))
+                else {
+                    continue;
+                };
+
+                let neighbor_level = light.channel(channel);
+                if neighbor_level == 0 {
+                    continue;
+                }
+
+                if neighbor_level <= propagate(channel, level, is_down(offset), material) {
+                    self.set_level(&neighbor, channel, 0);
+                    removals.push_back((neighbor, neighbor_level));
+                } else {
+                    refill.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    fn flood(
+        &mut self,
+        mut queues: [VecDeque<WorldPosition>; 2],
+        materials: &HashMap<ChunkId, ChunkData>,
+    ) {
+        for channel in CHANNELS {
+            let queue = &mut queues[channel as usize];
+            while let Some(position) = queue.pop_front() {
+                let Some(level) = self.light_at(&position).map(|l| l.channel(channel)) else {
+                    continue;
+                };
+                if level == 0 {
+                    continue;
+                }
+
+                for offset in &OFFSETS {
+                    let neighbor = offset_position(&position, offset);
+                    let (Some(light), Some(material)) =
+                        (self.light_at(&neighbor), material_at(materials, &neighbor))
+                    else {
+                        continue;
+                    };
+
+                    let next = propagate(channel, level, is_down(offset), material);
+                    if next > light.channel(channel) {
+                        self.set_level(&neighbor, channel, next);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{compute, Light, SkyBoundary, MAX_LIGHT};
+    use crate::{traits::Data3D, ChunkData, ChunkId, ChunkManager, WorldPosition};
+
+    fn lit_manager(ids: &[ChunkId], sky: impl Fn(&ChunkId) -> bool) -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+        for id in ids {
+            let data = ChunkData::default();
+            let boundary = if sky(id) {
+                SkyBoundary::Open
+            } else {
+                SkyBoundary::Dark
+            };
+            let light = compute(&data, boundary);
+            chunks.insert_lit(id, data, light);
+        }
+        chunks
+    }
+
+    fn light_at(chunks: &ChunkManager, x: i32, y: i32, z: i32) -> Light {
+        chunks.light_at(&WorldPosition::new(x, y, z)).unwrap()
+    }
+
+    #[test]
+    fn sky_column_stops_at_opaque_block() {
+        let mut data = ChunkData::default();
+        data.set(5, 5, 40, Material::Stone);
+        data.set(9, 9, 40, Material::Water);
+        let light = compute(&data, SkyBoundary::Open);
+
+        assert_eq!(light.get(5, 5, 41).sky(), MAX_LIGHT);
+        assert_eq!(light.get(5, 5, 40).sky(), 0);
+        // lit from the side
+        assert_eq!(light.get(5, 5, 39).sky(), MAX_LIGHT - 1);
+        assert_eq!(light.get(9, 9, 40).sky(), MAX_LIGHT - 1);
+        assert_eq!(light.get(9, 9, 0).sky(), MAX_LIGHT - 1);
+
+        let light = compute(&data, SkyBoundary::Dark);
+        assert_eq!(light.get(9, 9, 63), Light::DARK);
+    }
+
+    #[test]
+    fn emitter_falls_off_with_distance() {
+        let mut data = ChunkData::default();
+        data.set(20, 20, 20, Material::Lamp);
+        let light = compute(&data, SkyBoundary::Dark);
+
+        assert_eq!(light.get(20, 20, 20).block(), 15);
+        assert_eq!(light.get(21, 20, 20).block(), 14);
+        assert_eq!(light.get(22, 21, 19).block(), 11);
+        assert_eq!(light.get(20, 20, 35).block(), 0);
+        assert_eq!(light.get(21, 20, 20).sky(), 0);
+    }
+
+    #[test]
+    fn placing_and_removing_blocks_updates_light() {
+        let id = ChunkId::new(0, 0, -1);
+        let mut chunks = lit_manager(&[id], |_| true);
+        chunks.take_relit_chunks();
+
+        chunks.set_block(3, 3, -10, Material::Stone).unwrap();
+        assert_eq!(light_at(&chunks, 3, 3, -11).sky(), MAX_LIGHT - 1);
+        assert_eq!(light_at(&chunks, 3, 3, -9).sky(), MAX_LIGHT);
+        assert!(chunks.take_relit_chunks().contains(&id));
+
+        chunks.set_block(3, 3, -20, Material::Crystal).unwrap();
+        assert_eq!(light_at(&chunks, 3, 3, -21).block(), 9);
+
+        chunks.set_block(3, 3, -10, Material::Air).unwrap();
+        chunks.set_block(3, 3, -20, Material::Air).unwrap();
+        assert_eq!(light_at(&chunks, 3, 3, -11).sky(), MAX_LIGHT);
+        assert_eq!(light_at(&chunks, 3, 3, -21), Light::SKY);
+    }
+
+    #[test]
+    fn light_crosses_chunk_borders() {
+        let lower = ChunkId::new(0, 0, -1);
+        let upper = ChunkId::new(0, 0, 0);
+        let side = ChunkId::new(1, 0, -1);
+
+        // the lower chunks assume open sky until the upper chunk arrives
+        let mut chunks = lit_manager(&[lower, side], |_| true);
+        let mut roof = ChunkData::default();
+        for x in 0..64 {
+            for y in 0..64 {
+                roof.set(x, y, 0, Material::Stone);
+            }
+        }
+        let light = compute(&roof, SkyBoundary::Open);
+        chunks.insert_lit(&upper, roof, light);
+
+        // only the side chunk below open sky still lights the lower chunk
+        assert_eq!(light_at(&chunks, 64, 10, -1).sky(), MAX_LIGHT);
+        assert_eq!(light_at(&chunks, 63, 10, -1).sky(), MAX_LIGHT - 1);
+        assert_eq!(light_at(&chunks, 62, 10, -40).sky(), MAX_LIGHT - 2);
+        assert_eq!(light_at(&chunks, 10, 10, -1).sky(), 0);
+        assert_eq!(light_at(&chunks, 0, 10, -1).sky(), 0);
+
+        chunks.set_block(65, 10, -5, Material::Lamp).unwrap();
+        assert_eq!(light_at(&chunks, 63, 10, -5).block(), MAX_LIGHT - 2);
+    }
+}
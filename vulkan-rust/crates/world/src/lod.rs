@@ -0,0 +1,185 @@
+use gamedata::material::Material;
+use octree::Collapse;
+
+use crate::ChunkData;
+
+/// Coarsest level of detail, its voxels are 8 times the regular size.
+pub const MAX_LOD: u8 = 3;
+
+/**
+ * Level of detail of a chunk mesh. At level n, voxels are 2^n times their regular size.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Lod(u8);
+
+impl Lod {
+    pub const FULL: Self = Self(0);
+
+    pub fn new(level: u8) -> Self {
+        Self(level.min(MAX_LOD))
+    }
+
+    /**
+     * Level for a chunk at the given distance, one level per exceeded distance.
+     */
+    pub fn for_distance(distance: f32, distances: &[f32]) -> Self {
+        Self::new(distances.iter().filter(|d| distance > **d).count() as u8)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.0
+    }
+
+    pub fn voxel_size(&self) -> usize {
+        1 << self.0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/**
+ * Material of a coarse voxel. Opaque if most of its children are, so terrain keeps its volume,
+ * the most common material of the majority otherwise.
+ */
+pub fn representative(materials: &[Option<Material>; 8]) -> Option<Material> {
+    let opaque = materials.iter().flatten().filter(|m| m.is_opaque()).count();
+    let majority = opaque * 2 >= materials.len();
+
+    let mut counts = [0_u8; 64];
+    let mut best = (0, None);
+    for m in materials
+        .iter()
+        .flatten()
+        .filter(|m| m.is_opaque() == majority)
+    {
+        let count = &mut counts[u8::from(*m) as usize];
+        *count += 1;
+        if *count > best.0 {
+            best = (*count, Some(*m));
+        }
+    }
+    best.1
+}
+
+/**
+ * Copy of a chunk where every coarse voxel of the level is filled with its representative.
+ * Coarse voxels are aligned to the chunk, so neighbors at any level line up on their border.
+ */
+pub fn collapse(data: &ChunkData, lod: Lod) -> ChunkData {
+    if lod.is_full() {
+        return data.clone();
+    }
+    ChunkData(
+        data.0.collapse(lod.level() as usize, &representative),
+        data.1,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{collapse, representative, Lod, MAX_LOD};
+    use crate::{
+        mesh_generator::generate_greedy_mesh, slice::CubeSlice, traits::Data3D, ChunkData, ChunkId,
+        CHUNK_SIZE, CHUNK_SIZE_SAFE,
+    };
+
+    #[test]
+    fn levels_by_distance() {
+        let distances = [100.0, 200.0, 300.0, 400.0];
+        assert_eq!(Lod::for_distance(50.0, &distances), Lod::FULL);
+        assert_eq!(Lod::for_distance(150.0, &distances).voxel_size(), 2);
+        assert_eq!(Lod::for_distance(1000.0, &distances).level(), MAX_LOD);
+        assert_eq!(Lod::for_distance(1000.0, &[]), Lod::FULL);
+    }
+
+    #[test]
+    fn representative_keeps_volume() {
+        use Material::*;
+        let mixed = [Stone, Dirt, Dirt, Grass, Air, Air, Air, Water].map(Some);
+        assert_eq!(representative(&mixed), Some(Dirt));
+
+        let mostly_air = [
+            Some(Stone),
+            Some(Stone),
+            Some(Air),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ];
+        assert_eq!(representative(&mostly_air), Some(Air));
+        assert_eq!(representative(&[None; 8]), None);
+    }
+
+    /**
+     * Padded blocks of a chunk, with the +X border taken from a neighbor like in the engine.
+     */
+    fn padded(center: &ChunkData, neighbor: &ChunkData) -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut blocks = CubeSlice::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    blocks.set(x + 1, y + 1, z + 1, center.get(x, y, z));
+                }
+            }
+        }
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                blocks.set(CHUNK_SIZE_SAFE - 1, y + 1, z + 1, neighbor.get(0, y, z));
+            }
+        }
+        blocks
+    }
+
+    /**
+     * Sum of the oriented triangle areas, zero for closed surfaces.
+     */
+    fn vector_area(meshes: &[&graphics::Mesh]) -> glm::Vec3 {
+        let mut area = glm::Vec3::zeros();
+        for mesh in meshes {
+            let position = |i: u32| mesh.vertices[i as usize].pos_mat.xyz();
+            for triangle in mesh.indices.chunks(3) {
+                let a = position(triangle[0]);
+                let b = position(triangle[1]);
+                let c = position(triangle[2]);
+                area += (b - a).cross(&(c - a));
+            }
+        }
+        area
+    }
+
+    #[test]
+    fn seams_between_levels_are_closed() {
+        // sphere on the border between two chunks along x
+        let (mut near, mut far) = (ChunkData::default(), ChunkData::default());
+        for x in 48..80_usize {
+            for y in 16..48_usize {
+                for z in 16..48_usize {
+                    let d = [x as f32 - 63.5, y as f32 - 31.5, z as f32 - 31.5];
+                    if d.iter().map(|d| d * d).sum::<f32>() <= 9.5 * 9.5 {
+                        match x < CHUNK_SIZE {
+                            true => near.set(x, y, z, Material::Stone),
+                            false => far.set(x - CHUNK_SIZE, y, z, Material::Stone),
+                        }
+                    }
+                }
+            }
+        }
+
+        let id = ChunkId::new(0, 0, 0);
+        let coarse = collapse(&far, Lod::new(2));
+        assert_ne!(coarse, far);
+        let far_mesh = generate_greedy_mesh(&id, &padded(&coarse, &ChunkData::default()));
+
+        let near_mesh = generate_greedy_mesh(&id, &padded(&near, &coarse));
+        assert_eq!(vector_area(&[&near_mesh, &far_mesh]), glm::Vec3::zeros());
+
+        let unmatched = generate_greedy_mesh(&id, &padded(&near, &far));
+        assert_ne!(vector_area(&[&unmatched, &far_mesh]), glm::Vec3::zeros());
+    }
+}
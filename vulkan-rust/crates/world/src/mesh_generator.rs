@@ -1,23 +1,64 @@
 #![allow(unused)]
 
-use std::os::windows::prelude::MetadataExt;
-
 use nalgebra_glm as glm;
 
-use gamedata::material::Material;
+use gamedata::{
+    atlas::Side,
+    material::{Material, RenderLayer},
+};
 use graphics::{Mesh, Vertex};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    chunk_id::ChunkId, slice::CubeSlice, traits::Data3D, ChunkData, CHUNK_SIZE, CHUNK_SIZE_SAFE,
-    CHUNK_SIZE_SQUARED,
+    chunk_id::ChunkId, light::Light, slice::CubeSlice, traits::Data3D, ChunkData, CHUNK_SIZE,
+    CHUNK_SIZE_SAFE, CHUNK_SIZE_SQUARED,
 };
 
+/**
+ * Mesher for the opaque voxels of a world. Cutout and translucent layers are always meshed greedily.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mesher {
+    #[default]
+    Greedy,
+    /// Same surface as the greedy mesher, faces are found and merged with bit masks
+    Binary,
+    SurfaceNets,
+}
+
+/**
+ * Mesh of all opaque faces in full sky light, with ambient occlusion at the quad corners.
+ */
 pub fn generate_greedy_mesh<T>(id: &ChunkId, data: &T) -> Mesh
 where
     T: Data3D<Material>,
 {
+    greedy_mesh(data, |_, _, _| Light::SKY)
+}
+
+/**
+ * Mesh of all opaque faces, lit by the light of the voxel in front of each face.
+ * The light slice uses the same padded layout as the data.
+ */
+pub fn generate_lit_greedy_mesh<T, L>(id: &ChunkId, data: &T, light: &L) -> Mesh
+where
+    T: Data3D<Material>,
+    L: Data3D<Light>,
+{
+    greedy_mesh(data, |x, y, z| light.get(x, y, z))
+}
+
+/// Face in a layer of the greedy mesher: material, whether it faces backwards, light and occlusion
+pub(crate) type Face = (Material, bool, Light, [u8; 4]);
+pub(crate) type LayerMask = [Option<Face>; CHUNK_SIZE_SQUARED];
+
+fn greedy_mesh<T>(data: &T, light: impl Fn(usize, usize, usize) -> Light) -> Mesh
+where
+    T: Data3D<Material>,
+{
+    let material = |x, y, z| data.get(x, y, z);
     let mut mesh = Mesh::default();
-    let mut vertex_count = 0;
 
     // on each axis
     // from (x,y,z) to (d,u,v)
@@ -27,7 +68,6 @@ where
         let v = (d0 + 2) % 3;
 
         let mut x = [0; 3];
-        let mut x2 = [0; 3];
         x[d] = 2;
 
         // look at all layers
@@ -40,259 +80,324 @@ where
             while x[v] < (CHUNK_SIZE_SAFE - 1) {
                 x[u] = 1;
                 while x[u] < (CHUNK_SIZE_SAFE - 1) {
-                    x2 = x;
-                    x2[d] -= 1;
-                    let cur_mat = data.get(x[0], x[1], x[2]);
-                    let prev_mat = data.get(x2[0], x2[1], x2[2]);
-                    let face_type_c = match (cur_mat.is_opaque(), prev_mat.is_opaque()) {
-                        (true, true) => None,
-                        (true, false) => Some((cur_mat, true)),
-                        (false, true) => Some((prev_mat, false)),
-                        (false, false) => None,
-                    };
-
-                    mask[n] = face_type_c;
+                    mask[n] = face(&material, &light, x, d);
                     n += 1;
                     x[u] += 1;
                 }
                 x[v] += 1;
             }
 
-            let mut start = [0_usize; 3];
-            start[d] = x[d] - 1 as usize;
-            let mut n = 0;
+            mesh_layer(&mut mask, d, x[d], &mut mesh);
+            x[d] += 1;
+        }
+    }
 
-            for mask_v in 0..CHUNK_SIZE {
-                start[v] = mask_v;
-                start[u] = 0;
-                for mask_u in 0..CHUNK_SIZE {
-                    if mask[n] == None {
-                        n += 1;
-                        continue;
-                    }
+    mesh
+}
 
-                    start[u] = mask_u;
-                    let mut end = start;
+/**
+ * Face between the voxel at x and the one before it on axis d, if only one of them is opaque.
+ */
+pub(crate) fn face(
+    material: &impl Fn(usize, usize, usize) -> Material,
+    light: &impl Fn(usize, usize, usize) -> Light,
+    x: [usize; 3],
+    d: usize,
+) -> Option<Face> {
+    let u = (d + 1) % 3;
+    let v = (d + 2) % 3;
+    let mut x2 = x;
+    x2[d] -= 1;
+    let cur_mat = material(x[0], x[1], x[2]);
+    let prev_mat = material(x2[0], x2[1], x2[2]);
+    match (cur_mat.is_opaque(), prev_mat.is_opaque()) {
+        (true, true) => None,
+        (true, false) => Some((
+            cur_mat,
+            true,
+            light(x2[0], x2[1], x2[2]),
+            face_ao(material, x2, u, v),
+        )),
+        (false, true) => Some((
+            prev_mat,
+            false,
+            light(x[0], x[1], x[2]),
+            face_ao(material, x, u, v),
+        )),
+        (false, false) => None,
+    }
+}
 
-                    // find length in dim1
-                    while end[u] < CHUNK_SIZE
-                        && mask[end[v] * CHUNK_SIZE + end[u]]
-                            == mask[start[v] * CHUNK_SIZE + start[u]]
-                    {
-                        end[u] += 1;
-                    }
-                    let u_end = end[u];
-
-                    end = start;
-                    end[v] += 1;
-
-                    // find length in dim2
-                    while end[v] < CHUNK_SIZE
-                        && mask[end[v] * CHUNK_SIZE + end[u]] == mask[start[v] + start[u]]
-                    {
-                        while end[u] < u_end
-                            && mask[end[v] * CHUNK_SIZE + end[u]]
-                                == mask[start[v] * CHUNK_SIZE + start[u]]
-                        {
-                            end[u] += 1;
-                        }
-
-                        if (end[u] < u_end) {
-                            break;
-                        }
-
-                        end[u] = start[u];
-                        end[v] += 1;
-                    }
-                    end[u] = u_end;
-
-                    let (m, orientation) = mask[start[v] * CHUNK_SIZE + start[u]].unwrap();
-                    let mut normal = [0, 0, 0];
-                    normal[d] = 1 - 2 * (orientation as i32);
-                    let normal = glm::vec3(normal[0] as f32, normal[1] as f32, normal[2] as f32);
-
-                    let i0 = vertex_count as u32;
-
-                    mesh.indices.extend(if orientation {
-                        [i0 + 0, i0 + 3, i0 + 1, i0 + 0, i0 + 2, i0 + 3]
-                    } else {
-                        [i0 + 0, i0 + 1, i0 + 3, i0 + 0, i0 + 3, i0 + 2]
-                    });
-
-                    let ss = start;
-                    let mut se = start;
-                    se[u] = end[u];
-                    let mut es = start;
-                    es[v] = end[v];
-                    let ee = end;
-
-                    let vertices = [
-                        [ss[0] as f32, ss[1] as f32, ss[2] as f32],
-                        [se[0] as f32, se[1] as f32, se[2] as f32],
-                        [es[0] as f32, es[1] as f32, es[2] as f32],
-                        [ee[0] as f32, ee[1] as f32, ee[2] as f32],
-                    ]
-                    .map(|position| glm::vec3(position[0], position[1], position[2]))
-                    .map(|position| Vertex::from_material(position, m.into(), normal));
-
-                    mesh.vertices.extend(vertices);
-                    vertex_count += 4;
-
-                    for vm in start[v]..end[v] {
-                        for um in start[u]..end[u] {
-                            mask[vm * CHUNK_SIZE + um] = None;
-                        }
-                    }
-                    n += 1;
+/**
+ * Merges the faces of a layer into quads, the layer is the padded index of the voxels after it.
+ */
+pub(crate) fn mesh_layer(mask: &mut LayerMask, d: usize, layer: usize, mesh: &mut Mesh) {
+    let u = (d + 1) % 3;
+    let v = (d + 2) % 3;
+
+    let mut start = [0_usize; 3];
+    start[d] = layer - 1;
+    let mut n = 0;
+
+    for mask_v in 0..CHUNK_SIZE {
+        start[v] = mask_v;
+        start[u] = 0;
+        for mask_u in 0..CHUNK_SIZE {
+            if mask[n] == None {
+                n += 1;
+                continue;
+            }
+
+            start[u] = mask_u;
+            let mut end = start;
+
+            // find length in dim1
+            while end[u] < CHUNK_SIZE
+                && mask[end[v] * CHUNK_SIZE + end[u]] == mask[start[v] * CHUNK_SIZE + start[u]]
+            {
+                end[u] += 1;
+            }
+            let u_end = end[u];
+
+            end = start;
+            end[v] += 1;
+
+            // find length in dim2
+            while end[v] < CHUNK_SIZE
+                && mask[end[v] * CHUNK_SIZE + end[u]] == mask[start[v] * CHUNK_SIZE + start[u]]
+            {
+                while end[u] < u_end
+                    && mask[end[v] * CHUNK_SIZE + end[u]] == mask[start[v] * CHUNK_SIZE + start[u]]
+                {
+                    end[u] += 1;
+                }
+
+                if (end[u] < u_end) {
+                    break;
                 }
+
+                end[u] = start[u];
+                end[v] += 1;
             }
-            x[d] += 1;
+            end[u] = u_end;
+
+            let face = mask[start[v] * CHUNK_SIZE + start[u]].unwrap();
+            push_quad(mesh, face, d, start, end);
+
+            for vm in start[v]..end[v] {
+                for um in start[u]..end[u] {
+                    mask[vm * CHUNK_SIZE + um] = None;
+                }
+            }
+            n += 1;
         }
     }
+}
 
-    mesh
+/**
+ * Adds the quad of a merged face from start to end, in unpadded voxel coordinates.
+ */
+pub(crate) fn push_quad(mesh: &mut Mesh, face: Face, d: usize, start: [usize; 3], end: [usize; 3]) {
+    let u = (d + 1) % 3;
+    let v = (d + 2) % 3;
+    let (m, orientation, face_light, ao) = face;
+
+    let mut normal = [0, 0, 0];
+    normal[d] = 1 - 2 * (orientation as i32);
+    let normal = glm::vec3(normal[0] as f32, normal[1] as f32, normal[2] as f32);
+
+    let i0 = mesh.vertices.len() as u32;
+
+    // split along the brighter diagonal so the occlusion is interpolated evenly
+    let flip = ao[0] + ao[3] < ao[1] + ao[2];
+    mesh.indices.extend(match (orientation, flip) {
+        (true, false) => [i0, i0 + 3, i0 + 1, i0, i0 + 2, i0 + 3],
+        (true, true) => [i0, i0 + 2, i0 + 1, i0 + 1, i0 + 2, i0 + 3],
+        (false, false) => [i0, i0 + 1, i0 + 3, i0, i0 + 3, i0 + 2],
+        (false, true) => [i0, i0 + 1, i0 + 2, i0 + 1, i0 + 3, i0 + 2],
+    });
+
+    let ss = start;
+    let mut se = start;
+    se[u] = end[u];
+    let mut es = start;
+    es[v] = end[v];
+    let ee = end;
+
+    let tile = face_tile(m, d, orientation);
+    let uvs = quad_uvs(d, end[u] - start[u], end[v] - start[v]);
+    let vertices = [
+        [ss[0] as f32, ss[1] as f32, ss[2] as f32],
+        [se[0] as f32, se[1] as f32, se[2] as f32],
+        [es[0] as f32, es[1] as f32, es[2] as f32],
+        [ee[0] as f32, ee[1] as f32, ee[2] as f32],
+    ]
+    .map(|position| glm::vec3(position[0], position[1], position[2]))
+    .into_iter()
+    .zip(ao)
+    .zip(uvs)
+    .map(|((position, ao), uv)| {
+        Vertex::from_material(position, m.into(), normal)
+            .with_light(face_light.normalized())
+            .with_ao(ao as f32 / MAX_AO as f32)
+            .with_texture(uv, tile)
+    });
+
+    mesh.vertices.extend(vertices);
+}
+
+/**
+ * Atlas tile of a face on axis d, -1 for materials drawn in their palette color.
+ */
+pub(crate) fn face_tile(m: Material, d: usize, back: bool) -> f32 {
+    let side = match (d, back) {
+        (2, false) => Side::Top,
+        (2, true) => Side::Bottom,
+        _ => Side::Side,
+    };
+    m.tile(side).map_or(-1.0, |tile| tile.index() as f32)
 }
 
-/// TODO: cleanup
-pub fn generate_greedy_mesh_water<T>(id: &ChunkId, data: &T) -> Mesh
+/**
+ * Texture coordinates of the quad corners (-u, -v), (+u, -v), (-u, +v), (+u, +v),
+ * counted in voxels so tiles repeat across merged faces. The second coordinate of side faces
+ * runs along z, so their tiles stand upright.
+ */
+pub(crate) fn quad_uvs(d: usize, width: usize, height: usize) -> [glm::Vec2; 4] {
+    let (w, h) = (width as f32, height as f32);
+    let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)];
+    corners.map(|(a, b)| match d {
+        1 => glm::vec2(b, a),
+        _ => glm::vec2(a, b),
+    })
+}
+
+const MAX_AO: u8 = 3;
+
+/**
+ * Ambient occlusion at the corners of a face, from the opaque voxels around the voxel in front of it.
+ * Corners are ordered like the quad vertices: (-u, -v), (+u, -v), (-u, +v), (+u, +v).
+ */
+fn face_ao(
+    material: &impl Fn(usize, usize, usize) -> Material,
+    front: [usize; 3],
+    u: usize,
+    v: usize,
+) -> [u8; 4] {
+    corner_ao(|du, dv| {
+        let mut p = front;
+        p[u] = p[u].wrapping_add_signed(du);
+        p[v] = p[v].wrapping_add_signed(dv);
+        material(p[0], p[1], p[2]).is_opaque()
+    })
+}
+
+/**
+ * Occlusion of the corners of a face, from whether the voxels at (du, dv) around the voxel in
+ * front of it are opaque.
+ */
+pub(crate) fn corner_ao(opaque: impl Fn(isize, isize) -> bool) -> [u8; 4] {
+    let opaque = |du, dv| opaque(du, dv) as u8;
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(du, dv)| {
+        let side_u = opaque(du, 0);
+        let side_v = opaque(0, dv);
+        if side_u + side_v == 2 {
+            0
+        } else {
+            MAX_AO - side_u - side_v - opaque(du, dv)
+        }
+    })
+}
+
+/// Water surfaces sit below the top of their voxel
+const WATER_SURFACE_DROP: f32 = 2.0 / 12.0;
+
+/**
+ * Mesh of the faces of one render layer in full sky light.
+ */
+pub fn generate_layer_mesh<T>(id: &ChunkId, data: &T, layer: RenderLayer) -> Mesh
 where
     T: Data3D<Material>,
 {
-    let mut mesh = Mesh::default();
-    let mut vertex_count = 0;
+    match layer {
+        RenderLayer::Opaque => greedy_mesh(data, |_, _, _| Light::SKY),
+        _ => layer_mesh(data, |_, _, _| Light::SKY, layer),
+    }
+}
 
-    // on each axis
-    // from (x,y,z) to (d,u,v)
-    for d0 in 0..3 {
-        let d = d0 % 3;
-        let u = (d0 + 1) % 3;
-        let v = (d0 + 2) % 3;
+/**
+ * Mesh of the faces of one render layer, lit like the opaque faces of the lit greedy mesher.
+ */
+pub fn generate_lit_layer_mesh<T, L>(id: &ChunkId, data: &T, light: &L, layer: RenderLayer) -> Mesh
+where
+    T: Data3D<Material>,
+    L: Data3D<Light>,
+{
+    match layer {
+        RenderLayer::Opaque => greedy_mesh(data, |x, y, z| light.get(x, y, z)),
+        _ => layer_mesh(data, |x, y, z| light.get(x, y, z), layer),
+    }
+}
 
-        let mut x = [0; 3];
-        let mut x2 = [0; 3];
-        x[d] = 2;
+/**
+ * Whether the face of a voxel toward its neighbor can be seen. Faces between voxels of the same
+ * material are culled, except for cutouts whose gaps show the faces behind them.
+ */
+fn is_visible(m: Material, neighbor: Material) -> bool {
+    !neighbor.is_opaque() && (m != neighbor || m.render_layer() == Some(RenderLayer::Cutout))
+}
 
-        // look at all layers
-        while x[d] < CHUNK_SIZE_SAFE {
-            x[u] = 1;
-            x[v] = 1;
-            // build the mask
-            let mut mask = [None; CHUNK_SIZE_SQUARED];
-            let mut n = 0;
-            while x[v] < (CHUNK_SIZE_SAFE - 1) {
-                x[u] = 1;
-                while x[u] < (CHUNK_SIZE_SAFE - 1) {
-                    x2 = x;
+/**
+ * Greedy mesh of a non opaque layer. Both voxels at a face can show it, so faces looking forward
+ * and backward are merged in separate masks.
+ */
+fn layer_mesh<T>(data: &T, light: impl Fn(usize, usize, usize) -> Light, layer: RenderLayer) -> Mesh
+where
+    T: Data3D<Material>,
+{
+    let material = |x, y, z| data.get(x, y, z);
+    let mut mesh = Mesh::default();
+    let in_layer = |m: Material| m.render_layer() == Some(layer);
+
+    for d in 0..3 {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+
+        for layer_d in 2..CHUNK_SIZE_SAFE {
+            let mut back = [None; CHUNK_SIZE_SQUARED];
+            let mut front = [None; CHUNK_SIZE_SQUARED];
+            let mut x = [0; 3];
+            x[d] = layer_d;
+            for x_v in 1..CHUNK_SIZE_SAFE - 1 {
+                x[v] = x_v;
+                for x_u in 1..CHUNK_SIZE_SAFE - 1 {
+                    x[u] = x_u;
+                    let mut x2 = x;
                     x2[d] -= 1;
                     let cur_mat = data.get(x[0], x[1], x[2]);
                     let prev_mat = data.get(x2[0], x2[1], x2[2]);
-                    let face_type_c = if cur_mat == Material::Water && prev_mat.is_invisible() {
-                        Some((cur_mat, true))
-                    } else if cur_mat.is_invisible() && prev_mat == Material::Water {
-                        Some((prev_mat, false))
-                    } else {
-                        None
-                    };
-
-                    mask[n] = face_type_c;
-                    n += 1;
-                    x[u] += 1;
-                }
-                x[v] += 1;
-            }
-
-            let mut start = [0_usize; 3];
-            start[d] = x[d] - 1 as usize;
-            let mut n = 0;
-
-            for mask_v in 0..CHUNK_SIZE {
-                start[v] = mask_v;
-                start[u] = 0;
-                for mask_u in 0..CHUNK_SIZE {
-                    if mask[n] == None {
-                        n += 1;
-                        continue;
-                    }
-
-                    start[u] = mask_u;
-                    let mut end = start;
-
-                    // find length in dim1
-                    while end[u] < CHUNK_SIZE
-                        && mask[end[v] * CHUNK_SIZE + end[u]]
-                            == mask[start[v] * CHUNK_SIZE + start[u]]
-                    {
-                        end[u] += 1;
-                    }
-                    let u_end = end[u];
-
-                    end = start;
-                    end[v] += 1;
-
-                    // find length in dim2
-                    while end[v] < CHUNK_SIZE
-                        && mask[end[v] * CHUNK_SIZE + end[u]] == mask[start[v] + start[u]]
-                    {
-                        while end[u] < u_end
-                            && mask[end[v] * CHUNK_SIZE + end[u]]
-                                == mask[start[v] * CHUNK_SIZE + start[u]]
-                        {
-                            end[u] += 1;
-                        }
-
-                        if (end[u] < u_end) {
-                            break;
-                        }
-
-                        end[u] = start[u];
-                        end[v] += 1;
+                    let n = (x_v - 1) * CHUNK_SIZE + x_u - 1;
+                    if in_layer(cur_mat) && is_visible(cur_mat, prev_mat) {
+                        let ao = face_ao(&material, x2, u, v);
+                        back[n] = Some((cur_mat, true, light(x2[0], x2[1], x2[2]), ao));
                     }
-                    end[u] = u_end;
-
-                    let (m, orientation) = mask[start[v] * CHUNK_SIZE + start[u]].unwrap();
-                    let mut normal = [0, 0, 0];
-                    normal[d] = 1 - 2 * (orientation as i32);
-                    let normal = glm::vec3(normal[0] as f32, normal[1] as f32, normal[2] as f32);
-
-                    let i0 = vertex_count as u32;
-
-                    mesh.indices.extend(if orientation {
-                        [i0 + 0, i0 + 3, i0 + 1, i0 + 0, i0 + 2, i0 + 3]
-                    } else {
-                        [i0 + 0, i0 + 1, i0 + 3, i0 + 0, i0 + 3, i0 + 2]
-                    });
-
-                    let ss = start;
-                    let mut se = start;
-                    se[u] = end[u];
-                    let mut es = start;
-                    es[v] = end[v];
-                    let ee = end;
-
-                    let vertices = [
-                        [ss[0] as f32, ss[1] as f32, ss[2] as f32],
-                        [se[0] as f32, se[1] as f32, se[2] as f32],
-                        [es[0] as f32, es[1] as f32, es[2] as f32],
-                        [ee[0] as f32, ee[1] as f32, ee[2] as f32],
-                    ]
-                    .map(|position| glm::vec3(position[0], position[1], position[2]))
-                    .map(|position| Vertex::from_material(position, m.into(), normal));
-
-                    mesh.vertices.extend(vertices);
-                    vertex_count += 4;
-
-                    for vm in start[v]..end[v] {
-                        for um in start[u]..end[u] {
-                            mask[vm * CHUNK_SIZE + um] = None;
-                        }
+                    if in_layer(prev_mat) && is_visible(prev_mat, cur_mat) {
+                        let ao = face_ao(&material, x, u, v);
+                        front[n] = Some((prev_mat, false, light(x[0], x[1], x[2]), ao));
                     }
-                    n += 1;
                 }
             }
-            x[d] += 1;
+
+            mesh_layer(&mut back, d, layer_d, &mut mesh);
+            mesh_layer(&mut front, d, layer_d, &mut mesh);
         }
     }
 
+    let water = f32::from(u8::from(Material::Water));
+    for vertex in mesh.vertices.iter_mut().filter(|v| v.pos_mat.w == water) {
+        vertex.pos_mat.z -= WATER_SURFACE_DROP;
+    }
+
     mesh
 }
 
@@ -300,18 +405,30 @@ where
 mod test {
     use test::Bencher;
 
-    use gamedata::material::Material;
+    use gamedata::{
+        atlas::Tile,
+        material::{Material, RenderLayer},
+    };
 
     use crate::{
         chunk_id::ChunkId,
         gen::chunk::Chunk,
+        light::Light,
         seed::{PositionalSeed, WorldSeed},
         slice::CubeSlice,
         traits::{Data3D, Generate, Voxelize},
         ChunkSeed, CHUNK_SIZE_SAFE,
     };
 
-    use super::generate_greedy_mesh;
+    use graphics::{
+        validate::{validate, validate_closed},
+        Mesh, Vertex,
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{
+        generate_greedy_mesh, generate_layer_mesh, generate_lit_greedy_mesh, WATER_SURFACE_DROP,
+    };
 
     const WORLD_SEED: WorldSeed = WorldSeed::new(17);
 
@@ -394,6 +511,280 @@ mod test {
         }
     }
 
+    #[test]
+    fn light_splits_faces() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(2, 2, 2, Material::Stone);
+        data.set(3, 2, 2, Material::Stone);
+        let mut light = CubeSlice::<Light, CHUNK_SIZE_SAFE>::default();
+        light.set(2, 2, 3, Light::SKY);
+        light.set(3, 2, 3, Light::new(0, 6));
+
+        let mesh = generate_lit_greedy_mesh(&id, &data, &light);
+        let top = mesh
+            .vertices
+            .iter()
+            .filter(|v| v.normal.z == 1.0)
+            .collect::<Vec<_>>();
+        assert_eq!(top.len(), 8);
+        assert!(top.iter().any(|v| v.light == glm::vec2(1.0, 0.0)));
+        assert!(top.iter().any(|v| v.light == glm::vec2(0.0, 0.4)));
+
+        let bottom = mesh.vertices.iter().find(|v| v.normal.z == -1.0).unwrap();
+        assert_eq!(bottom.light, glm::vec2(0.0, 0.0));
+
+        let packed = mesh.pack().expect("lit meshes can be packed");
+        assert_eq!(packed.unpack(), mesh);
+    }
+
+    fn top_vertices(mesh: &Mesh) -> Vec<(usize, &Vertex)> {
+        mesh.vertices
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.normal.z == 1.0)
+            .collect()
+    }
+
+    #[test]
+    fn unoccluded_block() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(3, 3, 3, Material::Stone);
+        let mesh = generate_greedy_mesh(&id, &data);
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+        assert!(mesh.vertices.iter().all(|v| v.ao == 1.0));
+    }
+
+    #[test]
+    fn occluded_corners() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(3, 3, 3, Material::Stone);
+        data.set(4, 3, 4, Material::Stone);
+        data.set(3, 4, 4, Material::Stone);
+        let mesh = generate_greedy_mesh(&id, &data);
+
+        let top = top_vertices(&mesh)
+            .into_iter()
+            .filter(|(_, v)| v.pos_mat.z == 3.0)
+            .map(|(_, v)| ((v.pos_mat.x, v.pos_mat.y), v.ao))
+            .collect::<Vec<_>>();
+        assert_eq!(top.len(), 4);
+        for ((x, y), ao) in top {
+            let expected = match (x == 3.0, y == 3.0) {
+                (true, true) => 0.0,
+                (true, false) | (false, true) => 2.0 / 3.0,
+                (false, false) => 1.0,
+            };
+            assert_eq!(ao, expected, "corner ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn occlusion_limits_merging() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(3, 3, 3, Material::Stone);
+        data.set(4, 3, 3, Material::Stone);
+        let mesh = generate_greedy_mesh(&id, &data);
+        assert_eq!(top_vertices(&mesh).len(), 4);
+
+        data.set(5, 3, 4, Material::Stone);
+        let mesh = generate_greedy_mesh(&id, &data);
+        let top = top_vertices(&mesh)
+            .into_iter()
+            .filter(|(_, v)| v.pos_mat.z == 3.0)
+            .collect::<Vec<_>>();
+        assert_eq!(top.len(), 8);
+    }
+
+    #[test]
+    fn quads_split_along_brighter_diagonal() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(3, 3, 3, Material::Stone);
+        data.set(2, 2, 4, Material::Stone);
+        let mesh = generate_greedy_mesh(&id, &data);
+
+        let top = top_vertices(&mesh)
+            .into_iter()
+            .filter(|(_, v)| v.pos_mat.z == 3.0)
+            .collect::<Vec<_>>();
+        let (dark, _) = top.iter().find(|(_, v)| v.ao < 1.0).unwrap();
+        let first = top.iter().map(|(i, _)| *i).min().unwrap();
+        let quad = mesh
+            .indices
+            .chunks(6)
+            .find(|quad| quad.contains(&(first as u32)))
+            .unwrap();
+        let triangles_with_dark = quad
+            .chunks(3)
+            .filter(|triangle| triangle.contains(&(*dark as u32)))
+            .count();
+        assert_eq!(triangles_with_dark, 1);
+    }
+
+    #[test]
+    fn textures_repeat_across_quads() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        for x in 2..5 {
+            for y in 2..4 {
+                data.set(x, y, 2, Material::Grass);
+            }
+        }
+        data.set(20, 20, 20, Material::Lamp);
+
+        let mesh = generate_greedy_mesh(&id, &data);
+        let tile = |normal: glm::Vec3| {
+            let quad = mesh
+                .vertices
+                .iter()
+                .filter(|v| {
+                    v.normal == normal && v.pos_mat.w == f32::from(u8::from(Material::Grass))
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(quad.len(), 4);
+            let max_uv = quad.iter().fold(glm::Vec2::zeros(), |m, v| m.sup(&v.uv));
+            (quad[0].tile, max_uv)
+        };
+
+        assert_eq!(
+            tile(glm::vec3(0.0, 0.0, 1.0)),
+            (Tile::GrassTop.index() as f32, glm::vec2(3.0, 2.0))
+        );
+        assert_eq!(
+            tile(glm::vec3(0.0, 0.0, -1.0)),
+            (Tile::Dirt.index() as f32, glm::vec2(3.0, 2.0))
+        );
+        // side tiles stand upright, their second coordinate runs along z
+        assert_eq!(
+            tile(glm::vec3(1.0, 0.0, 0.0)),
+            (Tile::GrassSide.index() as f32, glm::vec2(2.0, 1.0))
+        );
+        assert_eq!(
+            tile(glm::vec3(0.0, -1.0, 0.0)),
+            (Tile::GrassSide.index() as f32, glm::vec2(3.0, 1.0))
+        );
+
+        let lamp = f32::from(u8::from(Material::Lamp));
+        assert!(mesh
+            .vertices
+            .iter()
+            .filter(|v| v.pos_mat.w == lamp)
+            .all(|v| v.tile == -1.0));
+    }
+
+    #[test]
+    fn glass_is_meshed_in_translucent_layer() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(2, 2, 2, Material::Glass);
+        data.set(3, 2, 2, Material::Glass);
+        data.set(4, 2, 2, Material::Stone);
+
+        let translucent = generate_layer_mesh(&id, &data, RenderLayer::Translucent);
+        // the panes merge into one box, the face toward the stone is hidden
+        assert_eq!(translucent.vertices.len(), 20);
+        assert_eq!(translucent.indices.len(), 30);
+        let glass = f32::from(u8::from(Material::Glass));
+        assert!(translucent.vertices.iter().all(|v| v.pos_mat.w == glass));
+
+        // the stone shows its face behind the glass
+        let opaque = generate_layer_mesh(&id, &data, RenderLayer::Opaque);
+        assert_eq!(opaque.vertices.len(), 24);
+        assert!(generate_layer_mesh(&id, &data, RenderLayer::Cutout)
+            .vertices
+            .is_empty());
+    }
+
+    #[test]
+    fn cutouts_keep_faces_between_them() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        data.set(2, 2, 2, Material::Leaves);
+        data.set(3, 2, 2, Material::Leaves);
+
+        let cutout = generate_layer_mesh(&id, &data, RenderLayer::Cutout);
+        // 6 sides of the merged box and both faces between the leaves
+        assert_eq!(cutout.vertices.len(), 32);
+        assert!(generate_layer_mesh(&id, &data, RenderLayer::Opaque)
+            .vertices
+            .is_empty());
+    }
+
+    #[test]
+    fn water_surface_is_lowered() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        for x in 2..5 {
+            data.set(x, 2, 2, Material::Water);
+        }
+        data.set(5, 2, 2, Material::Glass);
+
+        let translucent = generate_layer_mesh(&id, &data, RenderLayer::Translucent);
+        let top = translucent
+            .vertices
+            .iter()
+            .filter(|v| v.normal.z == 1.0 && v.pos_mat.w == f32::from(u8::from(Material::Water)))
+            .collect::<Vec<_>>();
+        assert_eq!(top.len(), 4);
+        assert!(top.iter().all(|v| v.pos_mat.z == 2.0 - WATER_SURFACE_DROP));
+        // water and glass both show their face between them
+        let between = translucent
+            .vertices
+            .iter()
+            .filter(|v| v.normal.x != 0.0 && v.pos_mat.x == 4.0)
+            .count();
+        assert_eq!(between, 8);
+    }
+
+    #[test]
+    fn blocks_are_closed() {
+        let id = ChunkId::new(17, 17, 17);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        for x in 3..5 {
+            for y in 3..5 {
+                for z in 3..5 {
+                    data.set(x, y, z, Material::Stone);
+                }
+            }
+        }
+        data.set(10, 10, 10, Material::Glass);
+        data.set(20, 20, 20, Material::Leaves);
+
+        for layer in RenderLayer::ALL {
+            let mesh = generate_layer_mesh(&id, &data, layer);
+            assert_eq!(validate_closed(&mesh), vec![], "{:?}", layer);
+        }
+    }
+
+    #[test]
+    fn layer_meshes_are_valid() {
+        use Material::*;
+        let id = ChunkId::new(17, 17, 17);
+        let materials = [Air, Air, Stone, Dirt, Water, Glass, Leaves];
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut data = CubeSlice::<Material, CHUNK_SIZE_SAFE>::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    data.set(x, y, z, materials[rng.gen_range(0..materials.len())]);
+                }
+            }
+        }
+
+        for layer in RenderLayer::ALL {
+            let mesh = generate_layer_mesh(&id, &data, layer);
+            assert!(!mesh.vertices.is_empty());
+            assert_eq!(validate(&mesh), vec![], "{:?}", layer);
+            let packed = mesh.pack().expect("block meshes can be packed");
+            assert_eq!(packed.unpack(), mesh);
+        }
+    }
+
     #[bench]
     fn single_chunk_meshing17(b: &mut Bencher) {
         let id = ChunkId::new(17, 17, 17);
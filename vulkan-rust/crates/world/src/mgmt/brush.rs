@@ -0,0 +1,226 @@
+use crate::{ChunkId, WorldPosition, CHUNK_SIZE_I};
+
+/**
+ * Volume of voxels affected by a bulk edit. All bounds are inclusive.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Brush {
+    Box {
+        min: WorldPosition,
+        max: WorldPosition,
+    },
+    Sphere {
+        center: WorldPosition,
+        radius: f32,
+    },
+    /// Upright cylinder standing on the center of its bottom layer.
+    Cylinder {
+        base: WorldPosition,
+        radius: f32,
+        height: i32,
+    },
+    Line {
+        from: WorldPosition,
+        to: WorldPosition,
+    },
+}
+
+impl Brush {
+    /**
+     * Same shape moved by the given offset, used to place brushes given relative to a block.
+     */
+    pub fn offset(&self, by: &WorldPosition) -> Self {
+        match self {
+            Self::Box { min, max } => Self::Box {
+                min: min + by,
+                max: max + by,
+            },
+            Self::Sphere { center, radius } => Self::Sphere {
+                center: center + by,
+                radius: *radius,
+            },
+            Self::Cylinder {
+                base,
+                radius,
+                height,
+            } => Self::Cylinder {
+                base: base + by,
+                radius: *radius,
+                height: *height,
+            },
+            Self::Line { from, to } => Self::Line {
+                from: from + by,
+                to: to + by,
+            },
+        }
+    }
+
+    pub fn for_each_position(&self, mut f: impl FnMut(WorldPosition)) {
+        match self {
+            Self::Box { min, max } => {
+                for z in min.z..=max.z {
+                    for y in min.y..=max.y {
+                        for x in min.x..=max.x {
+                            f(WorldPosition::new(x, y, z));
+                        }
+                    }
+                }
+            }
+            Self::Sphere { center, radius } => {
+                let r = radius.floor() as i32;
+                let r_squared = radius * radius;
+                for z in -r..=r {
+                    for y in -r..=r {
+                        for x in -r..=r {
+                            if (x * x + y * y + z * z) as f32 <= r_squared {
+                                f(WorldPosition::new(center.x + x, center.y + y, center.z + z));
+                            }
+                        }
+                    }
+                }
+            }
+            Self::Cylinder {
+                base,
+                radius,
+                height,
+            } => {
+                let r = radius.floor() as i32;
+                let r_squared = radius * radius;
+                for z in 0..*height {
+                    for y in -r..=r {
+                        for x in -r..=r {
+                            if (x * x + y * y) as f32 <= r_squared {
+                                f(WorldPosition::new(base.x + x, base.y + y, base.z + z));
+                            }
+                        }
+                    }
+                }
+            }
+            Self::Line { from, to } => {
+                let delta = [to.x - from.x, to.y - from.y, to.z - from.z];
+                let steps = delta.iter().map(|d| d.abs()).max().unwrap_or(0);
+                if steps == 0 {
+                    f(from.clone());
+                    return;
+                }
+
+                let lerp = |start: i32, d: i32, i: i32| {
+                    start + (d as f32 * i as f32 / steps as f32).round() as i32
+                };
+                for i in 0..=steps {
+                    f(WorldPosition::new(
+                        lerp(from.x, delta[0], i),
+                        lerp(from.y, delta[1], i),
+                        lerp(from.z, delta[2], i),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/**
+ * The chunk of a voxel and all chunks whose padded border contains it.
+ */
+pub fn chunks_touching(position: &WorldPosition) -> Vec<ChunkId> {
+    let id = ChunkId::from(position);
+    let local = position.rem_euclid(CHUNK_SIZE_I);
+    let offsets = |l: i32| match l {
+        0 => [0, -1],
+        l if l == CHUNK_SIZE_I - 1 => [0, 1],
+        _ => [0, 0],
+    };
+
+    let mut ids = Vec::with_capacity(8);
+    for dz in offsets(local.z) {
+        for dy in offsets(local.y) {
+            for dx in offsets(local.x) {
+                let neighbor = ChunkId::new(id.x + dx, id.y + dy, id.z + dz);
+                if !ids.contains(&neighbor) {
+                    ids.push(neighbor);
+                }
+            }
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chunks_touching, Brush};
+    use crate::{ChunkId, WorldPosition};
+
+    fn positions(brush: &Brush) -> Vec<WorldPosition> {
+        let mut positions = vec![];
+        brush.for_each_position(|p| positions.push(p));
+        positions
+    }
+
+    #[test]
+    fn shape_volumes() {
+        let origin = WorldPosition::new(0, 0, 0);
+        let cube = Brush::Box {
+            min: WorldPosition::new(-1, -1, -1),
+            max: WorldPosition::new(1, 1, 1),
+        };
+        assert_eq!(positions(&cube).len(), 27);
+
+        let sphere = Brush::Sphere {
+            center: origin.clone(),
+            radius: 1.0,
+        };
+        assert_eq!(positions(&sphere).len(), 7);
+
+        let cylinder = Brush::Cylinder {
+            base: origin,
+            radius: 1.5,
+            height: 4,
+        };
+        assert_eq!(positions(&cylinder).len(), 9 * 4);
+    }
+
+    #[test]
+    fn line_is_connected() {
+        let line = Brush::Line {
+            from: WorldPosition::new(-3, 10, 2),
+            to: WorldPosition::new(7, 4, 2),
+        };
+        let positions = positions(&line);
+
+        assert_eq!(positions.len(), 11);
+        assert_eq!(positions[0], WorldPosition::new(-3, 10, 2));
+        assert_eq!(positions[10], WorldPosition::new(7, 4, 2));
+        for pair in positions.windows(2) {
+            assert_eq!(pair[1].x - pair[0].x, 1);
+            assert!((pair[1].y - pair[0].y).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn offset_moves_every_position() {
+        let by = WorldPosition::new(5, -3, 64);
+        let sphere = Brush::Sphere {
+            center: WorldPosition::new(0, 0, 0),
+            radius: 2.5,
+        };
+        let moved = positions(&sphere)
+            .iter()
+            .map(|p| p + &by)
+            .collect::<Vec<_>>();
+
+        assert_eq!(positions(&sphere.offset(&by)), moved);
+    }
+
+    #[test]
+    fn border_voxels_touch_neighbors() {
+        assert_eq!(
+            chunks_touching(&WorldPosition::new(10, 10, 10)),
+            vec![ChunkId::new(0, 0, 0)]
+        );
+        assert_eq!(
+            chunks_touching(&WorldPosition::new(63, 5, 5)),
+            vec![ChunkId::new(0, 0, 0), ChunkId::new(1, 0, 0)]
+        );
+        assert_eq!(chunks_touching(&WorldPosition::new(-64, 0, 127)).len(), 8);
+    }
+}
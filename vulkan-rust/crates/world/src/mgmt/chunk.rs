@@ -1,17 +1,37 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
 
 use gamedata::material::Material;
 
-use crate::{chunk_id::ChunkId, traits::Data3D, ChunkData, WorldPosition, CHUNK_SIZE_I};
+use super::{
+    brush::{chunks_touching, Brush},
+    history::EditHistory,
+    journal::EditJournal,
+};
+use crate::{
+    chunk_id::ChunkId,
+    light::{ChunkLight, Light, LightMap},
+    storage::RegionStore,
+    traits::Data3D,
+    ChunkData, WorldPosition, CHUNK_SIZE_I,
+};
 
 pub struct ChunkManager {
     chunks: HashMap<ChunkId, ChunkData>,
+    journal: EditJournal,
+    history: EditHistory,
+    light: LightMap,
 }
 
 impl ChunkManager {
     pub fn new() -> Self {
         Self {
             chunks: Default::default(),
+            journal: Default::default(),
+            history: Default::default(),
+            light: Default::default(),
         }
     }
 
@@ -19,40 +39,262 @@ impl ChunkManager {
         self.chunks.len()
     }
 
+    pub fn ids(&self) -> impl Iterator<Item = &ChunkId> {
+        self.chunks.keys()
+    }
+
     pub fn insert(&mut self, id: &ChunkId, data: ChunkData) {
         self.chunks.insert(id.clone(), data);
     }
 
+    /**
+     * Inserts a chunk together with its light, which is then kept up to date on changes.
+     */
+    pub fn insert_lit(&mut self, id: &ChunkId, data: ChunkData, light: ChunkLight) {
+        self.chunks.insert(*id, data);
+        self.light.insert(*id, light, &self.chunks);
+    }
+
     pub fn set_block(&mut self, x: i32, y: i32, z: i32, m: Material) -> Result<ChunkId, ()> {
         let position = WorldPosition::new(x, y, z);
         let id = ChunkId::from(&position);
         let pos_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
         if let Some(data) = self.chunks.get_mut(&id) {
-            data.set(
+            let (x, y, z) = (
                 pos_in_chunk.x as usize,
                 pos_in_chunk.y as usize,
                 pos_in_chunk.z as usize,
-                m,
             );
+            let changed = data.get(x, y, z) != m;
+            data.set(x, y, z, m);
+            if changed {
+                self.light.update_block(&position, &self.chunks);
+            }
             Ok(id)
         } else {
             Err(())
         }
     }
 
+    pub fn get_block(&self, position: &WorldPosition) -> Option<Material> {
+        let pos_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
+        self.chunks.get(&ChunkId::from(position)).map(|data| {
+            data.get(
+                pos_in_chunk.x as usize,
+                pos_in_chunk.y as usize,
+                pos_in_chunk.z as usize,
+            )
+        })
+    }
+
+    /**
+     * Player edit, recorded in the journal so it can be replayed after regeneration
+     * and in the history so it can be undone.
+     */
+    pub fn edit_block(&mut self, x: i32, y: i32, z: i32, m: Material) -> Option<ChunkId> {
+        let position = WorldPosition::new(x, y, z);
+        let before = self.get_block(&position)?;
+        let id = self.set_block(x, y, z, m).ok()?;
+
+        self.history.begin();
+        self.history.record(position.clone(), before, m);
+        self.history.commit();
+        self.journal.record(position, m);
+        Some(id)
+    }
+
+    /**
+     * Change made by the world itself, like flowing water. It is recorded in the journal so it
+     * survives reloads, but not in the history, so it can not be undone.
+     */
+    pub fn journal_block(&mut self, x: i32, y: i32, z: i32, m: Material) -> Option<ChunkId> {
+        let id = self.set_block(x, y, z, m).ok()?;
+        self.journal.record(WorldPosition::new(x, y, z), m);
+        Some(id)
+    }
+
+    /**
+     * Player edit of a whole volume. Returns the chunks that need to be remeshed.
+     */
+    pub fn fill(&mut self, brush: &Brush, m: Material) -> HashSet<ChunkId> {
+        self.edit_volume(brush, |_| Some(m))
+    }
+
+    /**
+     * Like fill, but only voxels of one material are changed.
+     */
+    pub fn replace(&mut self, brush: &Brush, from: Material, to: Material) -> HashSet<ChunkId> {
+        self.edit_volume(brush, |current| (current == from).then_some(to))
+    }
+
+    fn edit_volume(
+        &mut self,
+        brush: &Brush,
+        mut material_for: impl FnMut(Material) -> Option<Material>,
+    ) -> HashSet<ChunkId> {
+        let mut dirty = HashSet::new();
+        self.history.begin();
+        brush.for_each_position(|position| {
+            let Some(data) = self.chunks.get_mut(&ChunkId::from(&position)) else {
+                return;
+            };
+
+            let pos_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
+            let (x, y, z) = (
+                pos_in_chunk.x as usize,
+                pos_in_chunk.y as usize,
+                pos_in_chunk.z as usize,
+            );
+            let current = data.get(x, y, z);
+            match material_for(current) {
+                Some(m) if m != current => {
+                    data.set(x, y, z, m);
+                    self.light.update_block(&position, &self.chunks);
+                    dirty.extend(
+                        chunks_touching(&position)
+                            .into_iter()
+                            .filter(|id| self.chunks.contains_key(id)),
+                    );
+                    self.history.record(position.clone(), current, m);
+                    self.journal.record(position, m);
+                }
+                _ => {}
+            }
+        });
+        self.history.commit();
+        dirty
+    }
+
+    /**
+     * Groups all following edits into one undo step until the transaction is committed.
+     */
+    pub fn begin_transaction(&mut self) {
+        self.history.begin();
+    }
+
+    pub fn commit_transaction(&mut self) {
+        self.history.commit();
+    }
+
+    /**
+     * Reverts the latest transaction. Changes in unloaded chunks only go to the journal.
+     */
+    pub fn undo(&mut self) -> HashSet<ChunkId> {
+        let steps = match self.history.undo() {
+            Some(transaction) => transaction
+                .undo_steps()
+                .map(|(position, m)| (position.clone(), m))
+                .collect(),
+            None => vec![],
+        };
+        self.apply_history_steps(steps)
+    }
+
+    pub fn redo(&mut self) -> HashSet<ChunkId> {
+        let steps = match self.history.redo() {
+            Some(transaction) => transaction
+                .redo_steps()
+                .map(|(position, m)| (position.clone(), m))
+                .collect(),
+            None => vec![],
+        };
+        self.apply_history_steps(steps)
+    }
+
+    fn apply_history_steps(&mut self, steps: Vec<(WorldPosition, Material)>) -> HashSet<ChunkId> {
+        let mut dirty = HashSet::new();
+        for (position, m) in steps {
+            if self
+                .set_block(position.x, position.y, position.z, m)
+                .is_ok()
+            {
+                dirty.extend(self.chunks_to_remesh(&position));
+            }
+            self.journal.record(position, m);
+        }
+        dirty
+    }
+
+    pub fn history(&self) -> &EditHistory {
+        &self.history
+    }
+
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    /**
+     * Loaded chunks whose meshes include the voxel at the given position.
+     */
+    pub fn chunks_to_remesh(&self, position: &WorldPosition) -> Vec<ChunkId> {
+        chunks_touching(position)
+            .into_iter()
+            .filter(|id| self.chunks.contains_key(id))
+            .collect()
+    }
+
     pub fn remove(&mut self, id: &ChunkId) {
         self.chunks.remove(id);
+        self.light.remove(id);
     }
 
-    pub fn get(&self, id: &ChunkId) -> Option<&ChunkData> {
-        match self.chunks.get(id) {
-            Some(chunk_box) => Some(chunk_box),
-            None => None,
+    pub fn light(&self, id: &ChunkId) -> Option<&ChunkLight> {
+        self.light.get(id)
+    }
+
+    pub fn light_at(&self, position: &WorldPosition) -> Option<Light> {
+        self.light.light_at(position)
+    }
+
+    /**
+     * Chunks whose light changed since the last call and need to be remeshed.
+     */
+    pub fn take_relit_chunks(&mut self) -> HashSet<ChunkId> {
+        self.light.take_relit()
+    }
+
+    pub fn is_modified(&self, id: &ChunkId) -> bool {
+        self.journal.contains(id)
+    }
+
+    pub fn journal(&self) -> &EditJournal {
+        &self.journal
+    }
+
+    pub fn set_journal(&mut self, journal: EditJournal) {
+        self.journal = journal;
+    }
+
+    /**
+     * Saves loaded chunks with more than `limit` journaled edits as whole chunks to the region
+     * store and drops their edits from the journal. Loading replays later edits over the
+     * stored chunk, so small deltas stay in the journal.
+     */
+    pub fn store_large_deltas<'a>(
+        &mut self,
+        store: &RegionStore,
+        ids: impl IntoIterator<Item = &'a ChunkId>,
+        limit: usize,
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        for id in ids {
+            let (Some(data), Some(edits)) = (self.chunks.get(id), self.journal.get(id)) else {
+                continue;
+            };
+            if edits.len() <= limit {
+                continue;
+            }
+
+            store.save(id, data)?;
+            self.journal.remove(id);
+            count += 1;
         }
+
+        Ok(count)
     }
 
-    pub fn get_mut(&mut self, id: &ChunkId) -> Option<&mut ChunkData> {
-        match self.chunks.get_mut(id) {
+    pub fn get(&self, id: &ChunkId) -> Option<&ChunkData> {
+        match self.chunks.get(id) {
             Some(chunk_box) => Some(chunk_box),
             None => None,
         }
@@ -60,13 +302,22 @@ impl ChunkManager {
 
     pub(crate) fn reset(&mut self) {
         self.chunks.clear();
+        self.light.clear();
+        self.journal = Default::default();
+        self.history.clear();
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::{env, fs};
+
+    use gamedata::material::Material;
+
     use super::{ChunkId, ChunkManager};
-    use crate::ChunkData;
+    use crate::{
+        mgmt::brush::Brush, storage::RegionStore, traits::Data3D, ChunkData, WorldPosition,
+    };
 
     #[test]
     fn insert() {
@@ -87,4 +338,144 @@ mod test {
         let chunk = cm.get(&id2);
         assert!(chunk.is_none());
     }
+
+    #[test]
+    fn edit_block_is_journaled() {
+        let mut cm = ChunkManager::new();
+        let id = ChunkId::new(-1, 0, 0);
+        cm.insert(&id, ChunkData::default());
+
+        assert_eq!(cm.set_block(-1, 0, 0, Material::Stone), Ok(id));
+        assert!(!cm.is_modified(&id));
+
+        assert_eq!(cm.edit_block(-2, 0, 0, Material::Stone), Some(id));
+        assert!(cm.is_modified(&id));
+        assert_eq!(cm.journal().edit_count(), 1);
+
+        assert_eq!(cm.edit_block(0, 0, 0, Material::Stone), None);
+        assert_eq!(cm.journal().edit_count(), 1);
+    }
+
+    #[test]
+    fn large_deltas_move_to_regions() {
+        let dir = env::temp_dir().join(format!("large_deltas_{}", std::process::id()));
+        let store = RegionStore::new(&dir).unwrap();
+        let mut cm = ChunkManager::new();
+        let (small, large) = (ChunkId::new(0, 0, 0), ChunkId::new(-1, 0, 0));
+        cm.insert(&small, ChunkData::default());
+        cm.insert(&large, ChunkData::default());
+        cm.edit_block(0, 0, 0, Material::Stone);
+        for x in -3..0 {
+            cm.edit_block(x, 0, 0, Material::Stone);
+        }
+
+        let ids = cm.ids().copied().collect::<Vec<_>>();
+        assert_eq!(cm.store_large_deltas(&store, &ids, 2).unwrap(), 1);
+        assert!(cm.is_modified(&small));
+        assert!(!cm.is_modified(&large));
+        assert!(store.load(&small).unwrap().is_none());
+
+        // later edits are journaled again and replayed over the stored chunk
+        cm.edit_block(-4, 0, 0, Material::Stone);
+        let mut reloaded = store.load(&large).unwrap().unwrap();
+        cm.journal().replay(&large, &mut reloaded);
+        assert_eq!(Some(&reloaded), cm.get(&large));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fill_reports_dirty_neighbors() {
+        let mut cm = ChunkManager::new();
+        for id in [
+            ChunkId::new(0, 0, 0),
+            ChunkId::new(1, 0, 0),
+            ChunkId::new(0, 1, 0),
+        ] {
+            cm.insert(&id, ChunkData::default());
+        }
+
+        // touches the border to (1, 0, 0) and reaches into unloaded (-1, 0, 0)
+        let dirty = cm.fill(
+            &Brush::Box {
+                min: WorldPosition::new(-2, 10, 10),
+                max: WorldPosition::new(63, 12, 12),
+            },
+            Material::Stone,
+        );
+        assert_eq!(dirty.len(), 2);
+        assert!(dirty.contains(&ChunkId::new(0, 0, 0)));
+        assert!(dirty.contains(&ChunkId::new(1, 0, 0)));
+        assert_eq!(cm.journal().edit_count(), 64 * 9);
+
+        // filling again changes nothing
+        let dirty = cm.fill(
+            &Brush::Line {
+                from: WorldPosition::new(0, 10, 10),
+                to: WorldPosition::new(63, 10, 10),
+            },
+            Material::Stone,
+        );
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn replace_only_matching_material() {
+        let mut cm = ChunkManager::new();
+        let id = ChunkId::new(0, 0, 0);
+        cm.insert(&id, ChunkData::default());
+        cm.set_block(5, 5, 5, Material::Dirt).unwrap();
+        cm.set_block(5, 5, 6, Material::Stone).unwrap();
+
+        let dirty = cm.replace(
+            &Brush::Sphere {
+                center: WorldPosition::new(5, 5, 5),
+                radius: 3.0,
+            },
+            Material::Dirt,
+            Material::Grass,
+        );
+        assert_eq!(dirty.into_iter().collect::<Vec<_>>(), vec![id]);
+
+        let chunk = cm.get(&id).unwrap();
+        assert_eq!(chunk.get(5, 5, 5), Material::Grass);
+        assert_eq!(chunk.get(5, 5, 6), Material::Stone);
+        assert_eq!(cm.journal().edit_count(), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_materials() {
+        let mut cm = ChunkManager::new();
+        let id = ChunkId::new(0, 0, 0);
+        let neighbor = ChunkId::new(0, 0, 1);
+        cm.insert(&id, ChunkData::default());
+        cm.insert(&neighbor, ChunkData::default());
+        cm.set_block(1, 1, 63, Material::Dirt).unwrap();
+
+        cm.begin_transaction();
+        cm.edit_block(1, 1, 63, Material::Stone).unwrap();
+        cm.fill(
+            &Brush::Line {
+                from: WorldPosition::new(1, 1, 62),
+                to: WorldPosition::new(1, 1, 63),
+            },
+            Material::Glass,
+        );
+        cm.commit_transaction();
+        assert_eq!(cm.history().undo_len(), 1);
+
+        let dirty = cm.undo();
+        assert_eq!(dirty.len(), 2);
+        assert!(dirty.contains(&neighbor));
+        let chunk = cm.get(&id).unwrap();
+        assert_eq!(chunk.get(1, 1, 63), Material::Dirt);
+        assert_eq!(chunk.get(1, 1, 62), Material::Unset);
+
+        cm.redo();
+        let chunk = cm.get(&id).unwrap();
+        assert_eq!(chunk.get(1, 1, 63), Material::Glass);
+        assert_eq!(chunk.get(1, 1, 62), Material::Glass);
+
+        assert!(cm.redo().is_empty());
+    }
 }
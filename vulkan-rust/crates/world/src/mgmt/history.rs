@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+
+use gamedata::material::Material;
+
+use crate::WorldPosition;
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/**
+ * Voxel changes of one edit operation, in the order they were applied.
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Transaction {
+    changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub position: WorldPosition,
+    pub before: Material,
+    pub after: Material,
+}
+
+impl Transaction {
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /**
+     * Materials that restore the state before the transaction, latest change first.
+     */
+    pub fn undo_steps(&self) -> impl Iterator<Item = (&WorldPosition, Material)> {
+        self.changes.iter().rev().map(|c| (&c.position, c.before))
+    }
+
+    pub fn redo_steps(&self) -> impl Iterator<Item = (&WorldPosition, Material)> {
+        self.changes.iter().map(|c| (&c.position, c.after))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &WorldPosition> {
+        self.changes.iter().map(|c| &c.position)
+    }
+}
+
+/**
+ * Undo and redo stacks of transactions. Transactions can be nested,
+ * only the outermost one ends up in the history.
+ * The oldest transactions are dropped once the depth is exceeded.
+ */
+#[derive(Debug)]
+pub struct EditHistory {
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    open: Option<(usize, Transaction)>,
+    depth: usize,
+}
+
+impl EditHistory {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn begin(&mut self) {
+        match &mut self.open {
+            Some((nesting, _)) => *nesting += 1,
+            None => self.open = Some((0, Transaction::default())),
+        }
+    }
+
+    pub fn record(&mut self, position: WorldPosition, before: Material, after: Material) {
+        let (_, transaction) = self
+            .open
+            .as_mut()
+            .expect("Changes must be recorded inside a transaction");
+        transaction.changes.push(Change {
+            position,
+            before,
+            after,
+        });
+    }
+
+    pub fn commit(&mut self) {
+        match self.open.take() {
+            Some((0, transaction)) => {
+                if !transaction.is_empty() {
+                    self.redo.clear();
+                    self.undo.push_back(transaction);
+                    self.trim();
+                }
+            }
+            Some((nesting, transaction)) => self.open = Some((nesting - 1, transaction)),
+            None => panic!("No transaction to commit"),
+        }
+    }
+
+    /**
+     * Moves the latest transaction to the redo stack and returns it for reverting.
+     */
+    pub fn undo(&mut self) -> Option<&Transaction> {
+        debug_assert!(self.open.is_none());
+        let transaction = self.undo.pop_back()?;
+        self.redo.push(transaction);
+        self.redo.last()
+    }
+
+    pub fn redo(&mut self) -> Option<&Transaction> {
+        debug_assert!(self.open.is_none());
+        let transaction = self.redo.pop()?;
+        self.undo.push_back(transaction);
+        self.undo.back()
+    }
+
+    /**
+     * The transaction the next undo reverts.
+     */
+    pub fn peek_undo(&self) -> Option<&Transaction> {
+        self.undo.back()
+    }
+
+    pub fn peek_redo(&self) -> Option<&Transaction> {
+        self.redo.last()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_DEPTH)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::EditHistory;
+    use crate::WorldPosition;
+
+    fn single_edit(history: &mut EditHistory, x: i32) {
+        history.begin();
+        history.record(WorldPosition::new(x, 0, 0), Material::Air, Material::Stone);
+        history.commit();
+    }
+
+    #[test]
+    fn nested_transactions_are_merged() {
+        let mut history = EditHistory::default();
+        history.begin();
+        single_edit(&mut history, 0);
+        single_edit(&mut history, 1);
+        history.commit();
+
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(history.undo().unwrap().len(), 2);
+        assert_eq!(history.redo_len(), 1);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = EditHistory::default();
+        single_edit(&mut history, 0);
+        history.undo();
+        single_edit(&mut history, 1);
+
+        assert_eq!(history.redo_len(), 0);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn depth_drops_oldest() {
+        let mut history = EditHistory::new(2);
+        for x in 0..5 {
+            single_edit(&mut history, x);
+        }
+        assert_eq!(history.undo_len(), 2);
+
+        let (position, material) = history.undo().unwrap().undo_steps().next().unwrap();
+        assert_eq!(*position, WorldPosition::new(4, 0, 0));
+        assert_eq!(material, Material::Air);
+    }
+}
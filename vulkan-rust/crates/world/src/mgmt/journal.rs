@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, HashMap};
+
+use gamedata::material::Material;
+
+use crate::{traits::Data3D, ChunkData, ChunkId, WorldPosition, CHUNK_SIZE_I};
+
+/**
+ * Player edits per chunk, stored as deltas over the generated terrain.
+ * Later edits of the same voxel replace earlier ones.
+ */
+#[derive(Default, Debug, PartialEq)]
+pub struct EditJournal {
+    edits: HashMap<ChunkId, BTreeMap<WorldPosition, Material>>,
+}
+
+impl EditJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, position: WorldPosition, material: Material) -> ChunkId {
+        let id = ChunkId::from(&position);
+        self.edits.entry(id).or_default().insert(position, material);
+        id
+    }
+
+    pub fn contains(&self, id: &ChunkId) -> bool {
+        self.edits.contains_key(id)
+    }
+
+    pub fn get(&self, id: &ChunkId) -> Option<&BTreeMap<WorldPosition, Material>> {
+        self.edits.get(id)
+    }
+
+    /**
+     * Forgets the edits of a chunk, once they are stored as part of the whole chunk.
+     */
+    pub fn remove(&mut self, id: &ChunkId) -> Option<BTreeMap<WorldPosition, Material>> {
+        self.edits.remove(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ChunkId, &BTreeMap<WorldPosition, Material>)> {
+        self.edits.iter()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn edit_count(&self) -> usize {
+        self.edits.values().map(|edits| edits.len()).sum()
+    }
+
+    /**
+     * Applies all edits of a chunk to its freshly generated or loaded data.
+     */
+    pub fn replay(&self, id: &ChunkId, data: &mut ChunkData) -> usize {
+        let Some(edits) = self.edits.get(id) else {
+            return 0;
+        };
+
+        for (position, material) in edits {
+            let position_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
+            data.set(
+                position_in_chunk.x as usize,
+                position_in_chunk.y as usize,
+                position_in_chunk.z as usize,
+                *material,
+            );
+        }
+
+        edits.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::EditJournal;
+    use crate::{
+        gen::chunk::{compress, Chunk},
+        traits::{Data3D, Generate, Voxelize},
+        ChunkData, ChunkId, ChunkSeed, WorldPosition, WorldSeed,
+    };
+
+    const WORLD_SEED: WorldSeed = WorldSeed::new(17);
+
+    #[test]
+    fn later_edits_replace_earlier() {
+        let mut journal = EditJournal::new();
+        journal.record(WorldPosition::new(1, 2, 3), Material::Stone);
+        journal.record(WorldPosition::new(1, 2, 3), Material::Unset);
+        journal.record(WorldPosition::new(-1, 2, 3), Material::Debug);
+
+        assert_eq!(journal.chunk_count(), 2);
+        assert_eq!(journal.edit_count(), 2);
+        assert_eq!(
+            journal.get(&ChunkId::new(0, 0, 0)).unwrap()[&WorldPosition::new(1, 2, 3)],
+            Material::Unset
+        );
+    }
+
+    #[test]
+    fn replay_over_generated_chunk() {
+        let id = ChunkId::new(2, 1, -1);
+        let generate = || {
+            let generated = Chunk::generate(ChunkSeed::new(&WORLD_SEED, &id)).voxelize();
+            compress(&generated.voxels)
+        };
+
+        let mut journal = EditJournal::new();
+        journal.record(WorldPosition::new(128, 64, -64), Material::Debug);
+        journal.record(WorldPosition::new(191, 127, -1), Material::Unset);
+        journal.record(WorldPosition::new(0, 0, 0), Material::Debug);
+
+        let mut data = generate();
+        assert_eq!(journal.replay(&id, &mut data), 2);
+        assert_eq!(data.get(0, 0, 0), Material::Debug);
+        assert_eq!(data.get(63, 63, 63), Material::Unset);
+
+        let mut expected = generate();
+        expected.set(0, 0, 0, Material::Debug);
+        expected.set(63, 63, 63, Material::Unset);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn replay_without_edits() {
+        let journal = EditJournal::new();
+        let mut data = ChunkData::default();
+        assert_eq!(journal.replay(&ChunkId::new(0, 0, 0), &mut data), 0);
+        assert_eq!(data, ChunkData::default());
+    }
+}
@@ -1 +1,4 @@
+pub mod brush;
 pub(crate) mod chunk;
+pub mod history;
+pub mod journal;
@@ -0,0 +1,256 @@
+use std::borrow::Cow;
+
+use gamedata::material::Material;
+
+use crate::{slice::CubeSlice, traits::Data3D, ChunkData, ChunkId, CHUNK_SIZE, CHUNK_SIZE_SAFE};
+
+/**
+ * Offsets of the 26 chunks around a chunk: faces, edges and corners.
+ */
+pub fn offsets() -> impl Iterator<Item = [i32; 3]> {
+    (0..27)
+        .map(|i| [i % 3 - 1, i / 3 % 3 - 1, i / 9 - 1])
+        .filter(|o| *o != [0, 0, 0])
+}
+
+/**
+ * A chunk with its 26 neighbors, the input of the meshers in the padded layout.
+ * Voxels of neighbors that are not loaded read as `Material::Unset`, so faces toward them are
+ * meshed like faces toward open space. The chunk has to be meshed again once they are loaded,
+ * `dependents` lists the chunks to remesh.
+ */
+pub struct ChunkNeighborhood<'a> {
+    chunks: [Option<Cow<'a, ChunkData>>; 27],
+}
+
+impl<'a> ChunkNeighborhood<'a> {
+    /**
+     * Gathers the chunk and its neighbors, or nothing if the chunk itself is not loaded.
+     */
+    pub fn gather<F>(id: &ChunkId, mut chunk: F) -> Option<Self>
+    where
+        F: FnMut(&ChunkId) -> Option<Cow<'a, ChunkData>>,
+    {
+        let chunks = std::array::from_fn(|i| {
+            let [dx, dy, dz] = [i as i32 % 3 - 1, i as i32 / 3 % 3 - 1, i as i32 / 9 - 1];
+            chunk(&ChunkId::new(id.x + dx, id.y + dy, id.z + dz))
+        });
+        let neighborhood = Self { chunks };
+        neighborhood.chunks[13].is_some().then_some(neighborhood)
+    }
+
+    /**
+     * Chunks whose padded input contains the given chunk.
+     * Borders are meshed by the chunks below them and occlusion reads diagonal neighbors,
+     * so this is every neighbor.
+     */
+    pub fn dependents(id: &ChunkId) -> impl Iterator<Item = ChunkId> {
+        let id = *id;
+        offsets().map(move |[dx, dy, dz]| ChunkId::new(id.x + dx, id.y + dy, id.z + dz))
+    }
+
+    pub fn center(&self) -> &ChunkData {
+        self.chunks[13].as_ref().unwrap()
+    }
+
+    pub fn neighbor(&self, offset: [i32; 3]) -> Option<&ChunkData> {
+        let [dx, dy, dz] = offset.map(|o| (o + 1) as usize);
+        self.chunks[dz * 9 + dy * 3 + dx].as_deref()
+    }
+
+    pub fn is_loaded(&self, offset: [i32; 3]) -> bool {
+        self.neighbor(offset).is_some()
+    }
+
+    /**
+     * Calls f with every padded position of the loaded neighbors, its material and whether the
+     * voxel lies in front of a border meshed by this chunk.
+     */
+    pub fn for_each_border<F>(&self, mut f: F)
+    where
+        F: FnMut([usize; 3], Material, bool),
+    {
+        let range = |o: i32| match o {
+            -1 => 0..1,
+            0 => 1..CHUNK_SIZE_SAFE - 1,
+            _ => CHUNK_SIZE_SAFE - 1..CHUNK_SIZE_SAFE,
+        };
+        let local = |o: i32, p: usize| match o {
+            -1 => CHUNK_SIZE - 1,
+            0 => p - 1,
+            _ => 0,
+        };
+
+        for offset in offsets() {
+            let Some(neighbor) = self.neighbor(offset) else {
+                continue;
+            };
+            let [dx, dy, dz] = offset;
+            let meshed = dx >= 0 && dy >= 0 && dz >= 0;
+            for x in range(dx) {
+                for y in range(dy) {
+                    for z in range(dz) {
+                        let material = neighbor.get(local(dx, x), local(dy, y), local(dz, z));
+                        f([x, y, z], material, meshed);
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * Padding around the chunk with an empty center, for meshers that read the chunk directly.
+     */
+    pub fn border(&self) -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut blocks = CubeSlice::default();
+        self.for_each_border(|[x, y, z], material, _| blocks.set(x, y, z, material));
+        blocks
+    }
+
+    /**
+     * Chunk and padding expanded into a slice.
+     */
+    pub fn padded(&self) -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut blocks = self.border();
+        let center = self.center();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    blocks.set(x + 1, y + 1, z + 1, center.get(x, y, z));
+                }
+            }
+        }
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{borrow::Cow, collections::HashMap};
+
+    use gamedata::material::Material;
+    use graphics::{validate::validate, Mesh};
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use super::{offsets, ChunkNeighborhood};
+    use crate::{
+        mesh_generator::generate_greedy_mesh, octree_mesh::generate_octree_mesh, traits::Data3D,
+        ChunkData, ChunkId, ChunkManager, WorldPosition, CHUNK_SIZE, CHUNK_SIZE_I,
+    };
+
+    #[test]
+    fn gathers_all_neighbors() {
+        assert_eq!(offsets().count(), 26);
+
+        let mut chunks = ChunkManager::new();
+        let id = ChunkId::new(0, 0, 0);
+        assert!(ChunkNeighborhood::gather(&id, |id| chunks.get(id).map(Cow::Borrowed)).is_none());
+
+        for (i, offset) in offsets().enumerate().filter(|(i, _)| i % 2 == 0) {
+            let mut data = ChunkData::default();
+            data.set(63, 63, 63, Material::ALL[i % 8 + 3]);
+            chunks.insert(&ChunkId::new(offset[0], offset[1], offset[2]), data);
+        }
+        chunks.insert(&id, ChunkData::default());
+
+        let neighborhood =
+            ChunkNeighborhood::gather(&id, |id| chunks.get(id).map(Cow::Borrowed)).unwrap();
+        for (i, offset) in offsets().enumerate() {
+            assert_eq!(neighborhood.is_loaded(offset), i % 2 == 0);
+        }
+        // the far corner of the (-1, -1, -1) neighbor ends up in the padded corner
+        assert_eq!(neighborhood.padded().get(0, 0, 0), Material::ALL[3]);
+    }
+
+    /**
+     * Ground filling the lower half of the world, so every side border runs through stone.
+     */
+    fn ground(id: &ChunkId) -> ChunkData {
+        let mut data = ChunkData::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                let height = match id.z {
+                    0 => 20 + (x + 2 * y) % 7,
+                    _ => CHUNK_SIZE,
+                };
+                for z in 0..height {
+                    data.set(x, y, z, Material::Stone);
+                }
+            }
+        }
+        data
+    }
+
+    /**
+     * Faces of a chunk mesh between two opaque voxels, found through the world.
+     */
+    fn hidden_faces(id: &ChunkId, mesh: &Mesh, chunks: &ChunkManager) -> usize {
+        let opaque = |p: [i32; 3]| {
+            let origin = [id.x, id.y, id.z].map(|c| c * CHUNK_SIZE_I);
+            let position = WorldPosition::new(origin[0] + p[0], origin[1] + p[1], origin[2] + p[2]);
+            chunks.get_block(&position).map_or(false, |m| m.is_opaque())
+        };
+
+        let mut hidden = 0;
+        for quad in mesh.vertices.chunks(4) {
+            let d = (0..3).find(|d| quad[0].normal[*d] != 0.0).unwrap();
+            let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+            let min = |a: usize| quad.iter().map(|q| q.pos_mat[a] as i32).min().unwrap();
+            let max = |a: usize| quad.iter().map(|q| q.pos_mat[a] as i32).max().unwrap();
+            for pu in min(u)..max(u) {
+                for pv in min(v)..max(v) {
+                    let mut p = [0; 3];
+                    p[d] = min(d);
+                    p[u] = pu;
+                    p[v] = pv;
+                    let mut behind = p;
+                    behind[d] -= 1;
+                    hidden += (opaque(p) && opaque(behind)) as usize;
+                }
+            }
+        }
+        hidden
+    }
+
+    #[test]
+    fn borders_between_solid_voxels_are_never_meshed() {
+        let ids = (0..8)
+            .map(|i| ChunkId::new(i % 2, i / 2 % 2, i / 4 - 1))
+            .collect::<Vec<_>>();
+        let mut rng = StdRng::seed_from_u64(16);
+
+        for round in 0..2 {
+            let mut order = ids.clone();
+            order.shuffle(&mut rng);
+
+            let mut chunks = ChunkManager::new();
+            let mut meshes = HashMap::new();
+            for id in order {
+                chunks.insert(&id, ground(&id));
+                let dirty = ChunkNeighborhood::dependents(&id)
+                    .chain([id])
+                    .filter(|id| chunks.get(id).is_some())
+                    .collect::<Vec<_>>();
+                for id in dirty {
+                    let neighborhood =
+                        ChunkNeighborhood::gather(&id, |id| chunks.get(id).map(Cow::Borrowed))
+                            .unwrap();
+                    let mesh = match round % 2 {
+                        0 => {
+                            generate_octree_mesh(&id, neighborhood.center(), &neighborhood.border())
+                        }
+                        _ => generate_greedy_mesh(&id, &neighborhood.padded()),
+                    };
+                    meshes.insert(id, mesh);
+                }
+            }
+
+            for (id, mesh) in &meshes {
+                assert_eq!(validate(mesh), vec![], "{:?}", id);
+                assert_eq!(hidden_faces(id, mesh, &chunks), 0, "{:?}", id);
+            }
+            // the surface of the ground is still there
+            assert!(meshes.values().any(|mesh| !mesh.vertices.is_empty()));
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use gamedata::material::{Material, RenderLayer};
+use graphics::Mesh;
+use octree::Regions;
+
+use crate::{
+    chunk_id::ChunkId,
+    light::Light,
+    mesh_generator::{face, mesh_layer, LayerMask},
+    traits::Data3D,
+    ChunkData, CHUNK_SIZE, CHUNK_SIZE_SAFE, CHUNK_SIZE_SQUARED,
+};
+
+/**
+ * Padded blocks of a chunk, the center is read from the octree and the rest from the border.
+ */
+struct OctreeBlocks<'a, B> {
+    chunk: &'a ChunkData,
+    border: &'a B,
+}
+
+impl<B> OctreeBlocks<'_, B>
+where
+    B: Data3D<Material>,
+{
+    #[inline]
+    fn get(&self, x: usize, y: usize, z: usize) -> Material {
+        let center = 1..CHUNK_SIZE_SAFE - 1;
+        if center.contains(&x) && center.contains(&y) && center.contains(&z) {
+            self.chunk.get(x - 1, y - 1, z - 1)
+        } else {
+            self.border.get(x, y, z)
+        }
+    }
+}
+
+/**
+ * Copies the non opaque regions of a chunk and the voxels next to them into the padded slice,
+ * enough for the layer meshers to find and shade their faces. The outermost voxels are copied
+ * as well, non opaque voxels of the border face them.
+ */
+pub fn expand_non_opaque<B>(chunk: &ChunkData, blocks: &mut B)
+where
+    B: Data3D<Material>,
+{
+    let mut expand = |min: [usize; 3], max: [usize; 3]| {
+        for x in min[0]..max[0] {
+            for y in min[1]..max[1] {
+                for z in min[2]..max[2] {
+                    blocks.set(x + 1, y + 1, z + 1, chunk.get(x, y, z));
+                }
+            }
+        }
+    };
+
+    for d in 0..3 {
+        for side in [0, CHUNK_SIZE - 1] {
+            let (mut min, mut max) = ([0; 3], [CHUNK_SIZE; 3]);
+            (min[d], max[d]) = (side, side + 1);
+            expand(min, max);
+        }
+    }
+
+    chunk.for_each_region(|origin, size, material| {
+        if !matches!(material.render_layer(), None | Some(RenderLayer::Opaque)) {
+            expand(
+                origin.map(|o| o.saturating_sub(1)),
+                origin.map(|o| (o + size + 1).min(CHUNK_SIZE)),
+            );
+        }
+    });
+}
+
+/**
+ * Same mesh as the greedy mesher, read directly from the octree of the chunk.
+ * Only the padding of the border slice is used, the center is never expanded.
+ */
+pub fn generate_octree_mesh<B>(_id: &ChunkId, chunk: &ChunkData, border: &B) -> Mesh
+where
+    B: Data3D<Material>,
+{
+    octree_mesh(chunk, border, |_, _, _| Light::SKY)
+}
+
+/**
+ * Same mesh as the lit greedy mesher, read directly from the octree of the chunk.
+ */
+pub fn generate_lit_octree_mesh<B, L>(
+    _id: &ChunkId,
+    chunk: &ChunkData,
+    border: &B,
+    light: &L,
+) -> Mesh
+where
+    B: Data3D<Material>,
+    L: Data3D<Light>,
+{
+    octree_mesh(chunk, border, |x, y, z| light.get(x, y, z))
+}
+
+fn octree_mesh<B>(
+    chunk: &ChunkData,
+    border: &B,
+    light: impl Fn(usize, usize, usize) -> Light,
+) -> Mesh
+where
+    B: Data3D<Material>,
+{
+    let blocks = OctreeBlocks { chunk, border };
+    let material = |x, y, z| blocks.get(x, y, z);
+    // masks of the layers on each axis, only allocated for layers with faces
+    let mut masks: [Vec<Option<Box<LayerMask>>>; 3] =
+        std::array::from_fn(|_| (0..CHUNK_SIZE_SAFE).map(|_| None).collect());
+
+    let add_faces = |masks: &mut [Vec<Option<Box<LayerMask>>>; 3],
+                     d: usize,
+                     layer: usize,
+                     min: [usize; 3],
+                     size: usize| {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+        let mask = masks[d][layer].get_or_insert_with(|| Box::new([None; CHUNK_SIZE_SQUARED]));
+        let mut x = [0; 3];
+        x[d] = layer;
+        for x_v in min[v]..min[v] + size {
+            x[v] = x_v;
+            for x_u in min[u]..min[u] + size {
+                x[u] = x_u;
+                mask[(x_v - 1) * CHUNK_SIZE + x_u - 1] = face(&material, &light, x, d);
+            }
+        }
+    };
+
+    // every face inside the chunk lies on the border of an opaque region, empty and full
+    // subtrees are skipped as a whole
+    chunk
+        .0
+        .for_each_region([0, 0, 0], &mut |origin, size, value| {
+            if !value.map_or(false, |m| m.is_opaque()) {
+                return;
+            }
+            let min = origin.map(|o| o + 1);
+            for d in 0..3 {
+                for layer in [min[d], min[d] + size] {
+                    if layer >= 2 {
+                        add_faces(&mut masks, d, layer, min, size);
+                    }
+                }
+            }
+        });
+
+    // faces of opaque borders in front of non opaque voxels
+    let last = CHUNK_SIZE_SAFE - 1;
+    for d in 0..3 {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+        let mut x = [0; 3];
+        x[d] = last;
+        for x_v in 1..last {
+            x[v] = x_v;
+            for x_u in 1..last {
+                x[u] = x_u;
+                if blocks.get(x[0], x[1], x[2]).is_opaque() {
+                    let mut min = x;
+                    min[d] = last;
+                    add_faces(&mut masks, d, last, min, 1);
+                }
+            }
+        }
+    }
+
+    // merge in the same order as the greedy mesher
+    let mut mesh = Mesh::default();
+    for (d, layers) in masks.iter_mut().enumerate() {
+        for (layer, mask) in layers.iter_mut().enumerate() {
+            if let Some(mask) = mask {
+                mesh_layer(mask, d, layer, &mut mesh);
+            }
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use test::Bencher;
+
+    use gamedata::material::{Material, RenderLayer};
+    use graphics::validate::validate;
+
+    use super::{expand_non_opaque, generate_lit_octree_mesh, generate_octree_mesh};
+    use crate::{
+        light::Light,
+        mesh_generator::{generate_greedy_mesh, generate_layer_mesh, generate_lit_greedy_mesh},
+        slice::CubeSlice,
+        traits::Data3D,
+        ChunkData, ChunkId, CHUNK_SIZE, CHUNK_SIZE_SAFE,
+    };
+
+    /**
+     * Hills of stone with a dirt and grass top, a cave and a few floating blocks.
+     */
+    fn terrain() -> ChunkData {
+        let mut chunk = ChunkData::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                let height =
+                    20 + ((x as f32 * 0.2).sin() * 6.0 + (y as f32 * 0.15).cos() * 5.0) as usize;
+                for z in 0..height {
+                    let material = match height - z {
+                        1 => Material::Grass,
+                        2..=3 => Material::Dirt,
+                        _ => Material::Stone,
+                    };
+                    chunk.set(x, y, z, material);
+                }
+            }
+        }
+        for x in 10..30 {
+            for y in 20..24 {
+                for z in 5..9 {
+                    chunk.set(x, y, z, Material::Air);
+                }
+            }
+        }
+        for i in 0..8 {
+            chunk.set(7 * i, 3 * i, 40 + i, Material::Wood);
+        }
+        chunk
+    }
+
+    /**
+     * Padding with opaque and open voxels on all sides.
+     */
+    fn border() -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut border = CubeSlice::default();
+        let last = CHUNK_SIZE_SAFE - 1;
+        for a in 0..CHUNK_SIZE_SAFE {
+            for b in 0..CHUNK_SIZE_SAFE {
+                let material = match (a + b) % 3 {
+                    0 => Material::Stone,
+                    1 => Material::Water,
+                    _ => Material::Air,
+                };
+                for p in [0, last] {
+                    border.set(p, a, b, material);
+                    border.set(a, p, b, material);
+                    border.set(a, b, p, material);
+                }
+            }
+        }
+        border
+    }
+
+    /**
+     * The chunk expanded into the border, as read by the greedy mesher.
+     */
+    fn padded(
+        chunk: &ChunkData,
+        border: &CubeSlice<Material, CHUNK_SIZE_SAFE>,
+    ) -> CubeSlice<Material, CHUNK_SIZE_SAFE> {
+        let mut blocks = CubeSlice::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    blocks.set(x, y, z, border.get(x, y, z));
+                }
+            }
+        }
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    blocks.set(x + 1, y + 1, z + 1, chunk.get(x, y, z));
+                }
+            }
+        }
+        blocks
+    }
+
+    fn assert_same_mesh(chunk: &ChunkData, border: &CubeSlice<Material, CHUNK_SIZE_SAFE>) {
+        let id = ChunkId::new(0, 0, 0);
+        let greedy = generate_greedy_mesh(&id, &padded(chunk, border));
+        let octree = generate_octree_mesh(&id, chunk, border);
+        assert_eq!(validate(&octree), vec![]);
+        assert_eq!(octree.vertices, greedy.vertices);
+        assert_eq!(octree.indices, greedy.indices);
+    }
+
+    #[test]
+    fn same_mesh_as_greedy() {
+        let empty = CubeSlice::default();
+        assert_same_mesh(&terrain(), &empty);
+        assert_same_mesh(&terrain(), &border());
+        assert_same_mesh(&ChunkData::default(), &border());
+
+        let mut full = ChunkData::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    full.set(x, y, z, Material::Stone);
+                }
+            }
+        }
+        assert_same_mesh(&full, &empty);
+        assert_same_mesh(&full, &border());
+    }
+
+    #[test]
+    fn same_mesh_as_greedy_for_noise() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let materials = [
+            Material::Air,
+            Material::Stone,
+            Material::Water,
+            Material::Dirt,
+        ];
+        let mut chunk = ChunkData::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, materials[rng.gen_range(0..materials.len())]);
+                }
+            }
+        }
+        assert_same_mesh(&chunk, &border());
+    }
+
+    #[test]
+    fn same_lit_mesh_as_greedy() {
+        let id = ChunkId::new(0, 0, 0);
+        let chunk = terrain();
+        let mut light = CubeSlice::<Light, CHUNK_SIZE_SAFE>::default();
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    light.set(x, y, z, Light::new((x % 16) as u8, (z / 5) as u8));
+                }
+            }
+        }
+
+        let greedy = generate_lit_greedy_mesh(&id, &padded(&chunk, &border()), &light);
+        let octree = generate_lit_octree_mesh(&id, &chunk, &border(), &light);
+        assert_eq!(octree.vertices, greedy.vertices);
+        assert_eq!(octree.indices, greedy.indices);
+    }
+
+    #[test]
+    fn non_opaque_layers_need_only_their_regions() {
+        let id = ChunkId::new(0, 0, 0);
+        let mut chunk = terrain();
+        for x in 30..50 {
+            for y in 0..CHUNK_SIZE {
+                chunk.set(x, y, 30, Material::Water);
+            }
+        }
+        for i in 0..6 {
+            chunk.set(3 + 9 * i, 60, 45, Material::Leaves);
+            chunk.set(50, 4 + 7 * i, 40 + i, Material::Glass);
+        }
+
+        let mut blocks = Box::new(border());
+        expand_non_opaque(&chunk, blocks.as_mut());
+        let full = padded(&chunk, &border());
+        for layer in [RenderLayer::Cutout, RenderLayer::Translucent] {
+            let expected = generate_layer_mesh(&id, &full, layer);
+            let mesh = generate_layer_mesh(&id, blocks.as_ref(), layer);
+            assert!(!mesh.vertices.is_empty());
+            assert_eq!(mesh.vertices, expected.vertices);
+            assert_eq!(mesh.indices, expected.indices);
+        }
+    }
+
+    #[bench]
+    fn terrain_meshing_greedy(b: &mut Bencher) {
+        let id = ChunkId::new(0, 0, 0);
+        let blocks = padded(&terrain(), &border());
+
+        b.iter(|| {
+            test::black_box(generate_greedy_mesh(&id, &blocks));
+        });
+    }
+
+    #[bench]
+    fn terrain_meshing_octree(b: &mut Bencher) {
+        let id = ChunkId::new(0, 0, 0);
+        let (chunk, border) = (terrain(), border());
+
+        b.iter(|| {
+            test::black_box(generate_octree_mesh(&id, &chunk, &border));
+        });
+    }
+}
@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use rand::{thread_rng, Rng};
 
-use crate::{chunk_id::ChunkId, world_position::WorldPosition};
+use crate::{chunk_id::ChunkId, gen::config::WorldGenConfig, world_position::WorldPosition};
 
 #[derive(Clone)]
 pub struct WorldSeed(u64);
@@ -13,6 +15,19 @@ impl WorldSeed {
     pub fn random() -> Self {
         Self(thread_rng().gen())
     }
+
+    /**
+     * Seed written by a player. Numbers are used as they are, other text is hashed.
+     */
+    pub fn from_text(text: &str) -> Self {
+        match text.trim().parse() {
+            Ok(seed) => Self(seed),
+            // FNV-1a, stable across builds unlike the hasher of the standard library
+            Err(_) => Self(text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })),
+        }
+    }
 }
 
 impl From<&WorldSeed> for u64 {
@@ -63,13 +78,22 @@ impl PositionalSeed {
 pub struct ChunkSeed {
     world_seed: WorldSeed,
     id: ChunkId,
+    config: Arc<WorldGenConfig>,
 }
 
 impl ChunkSeed {
+    /**
+     * Seed of a chunk generated with the default configuration.
+     */
     pub fn new(world_seed: &WorldSeed, id: &ChunkId) -> Self {
+        Self::with_config(world_seed, id, Arc::default())
+    }
+
+    pub fn with_config(world_seed: &WorldSeed, id: &ChunkId, config: Arc<WorldGenConfig>) -> Self {
         Self {
             world_seed: world_seed.clone(),
             id: id.clone(),
+            config,
         }
     }
 
@@ -89,4 +113,23 @@ impl ChunkSeed {
     pub fn world_seed(&self) -> &WorldSeed {
         &self.world_seed
     }
+
+    pub fn config(&self) -> &WorldGenConfig {
+        &self.config
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WorldSeed;
+
+    #[test]
+    fn seeds_from_text() {
+        assert_eq!(u64::from(&WorldSeed::from_text("17")), 17);
+        assert_eq!(u64::from(&WorldSeed::from_text(" 42 ")), 42);
+
+        let word = u64::from(&WorldSeed::from_text("glacier"));
+        assert_eq!(word, u64::from(&WorldSeed::from_text("glacier")));
+        assert_ne!(word, u64::from(&WorldSeed::from_text("Glacier")));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{gen::chunk::in_chunk_data, traits::Data3D, CHUNK_SIZE_SAFE_I};
+use crate::traits::Data3D;
 
 pub type QuadSlice<T, const N: usize> = [[T; N]; N];
 
@@ -77,69 +77,6 @@ where
             data: vec![Default::default(); x * y * z],
         }
     }
-
-    pub fn write_into<D>(
-        &self,
-        dest: &mut D,
-        offset_x: i32,
-        offset_y: i32,
-        offset_z: i32,
-        voxel_count: &mut usize,
-        overflow: &mut Vec<(i32, i32, i32, T)>,
-    ) where
-        D: Data3D<T>,
-    {
-        let [end_x, end_y, end_z] = self.dimensions;
-        let start_x = 0;
-        let start_y = 0;
-        let start_z = 0;
-
-        // TODO: could improve by calculating the box better;
-        let mut index = 0;
-        for z in start_z..end_z {
-            for y in start_y..end_y {
-                for x in start_x..end_x {
-                    let value = self.data.get(index).unwrap();
-                    if *value != T::default() {
-                        let dest_x = offset_x + x as i32;
-                        let dest_y = offset_y + y as i32;
-                        let dest_z = offset_z + z as i32;
-                        if dest_x >= 0
-                            && dest_x < CHUNK_SIZE_SAFE_I
-                            && dest_y >= 0
-                            && dest_y < CHUNK_SIZE_SAFE_I
-                            && dest_z >= 0
-                            && dest_z < CHUNK_SIZE_SAFE_I
-                        {
-                            let is_in_chunk =
-                                in_chunk_data(dest_x as usize, dest_y as usize, dest_z as usize);
-
-                            if dest.get(dest_x as usize, dest_y as usize, dest_z as usize)
-                                != Default::default()
-                            {
-                                if is_in_chunk {
-                                    *voxel_count += 1;
-                                }
-                            }
-
-                            dest.set(
-                                dest_x as usize,
-                                dest_y as usize,
-                                dest_z as usize,
-                                value.clone(),
-                            );
-
-                            if !is_in_chunk {
-                                overflow.push((0, 0, 0, value.clone()));
-                            }
-                        }
-                    }
-
-                    index += 1;
-                }
-            }
-        }
-    }
 }
 
 impl<T> Data3D<T> for Slice3<T>
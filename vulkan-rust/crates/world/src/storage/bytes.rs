@@ -0,0 +1,30 @@
+use std::io;
+
+use gamedata::material::Material;
+
+pub(crate) fn read_byte(input: &mut &[u8]) -> io::Result<u8> {
+    let [byte] = read_array(input)?;
+    Ok(byte)
+}
+
+pub(crate) fn read_array<const N: usize>(input: &mut &[u8]) -> io::Result<[u8; N]> {
+    if input.len() < N {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+
+    let (bytes, rest) = input.split_at(N);
+    *input = rest;
+    Ok(bytes.try_into().expect("Length was checked"))
+}
+
+pub(crate) fn material_from_id(id: u8) -> io::Result<Material> {
+    if (id as usize) < Material::ALL.len() {
+        Ok(Material::from(id))
+    } else {
+        Err(invalid_data("Unknown material"))
+    }
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
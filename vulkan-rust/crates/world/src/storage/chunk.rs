@@ -0,0 +1,125 @@
+use std::io;
+
+use octree::{Codec, L6Node};
+
+use super::bytes::{invalid_data, read_array};
+use crate::ChunkData;
+
+/**
+ * Serializes a chunk as the voxel count followed by its octree in the octree codec.
+ */
+pub fn encode(data: &ChunkData, out: &mut Vec<u8>) {
+    out.extend((data.1 as u32).to_le_bytes());
+    out.extend(data.0.encode());
+}
+
+pub fn decode(mut input: &[u8]) -> io::Result<ChunkData> {
+    let count = u32::from_le_bytes(read_array(&mut input)?) as usize;
+    let node = L6Node::decode(input).map_err(|e| invalid_data(&e.to_string()))?;
+
+    Ok(ChunkData(node, count))
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+    use test::Bencher;
+
+    use super::{decode, encode};
+    use crate::{
+        gen::chunk::{compress, Chunk},
+        slice::CubeSlice,
+        traits::{Data3D, Generate, Voxelize},
+        ChunkData, ChunkId, ChunkSeed, WorldSeed, CHUNK_SIZE_SAFE,
+    };
+
+    const WORLD_SEED: WorldSeed = WorldSeed::new(17);
+    // one byte per voxel of the padded chunk
+    const RAW_SIZE: usize = CHUNK_SIZE_SAFE * CHUNK_SIZE_SAFE * CHUNK_SIZE_SAFE;
+
+    /**
+     * Underground chunks with caves, so no tree models are required.
+     */
+    fn generated_chunks() -> Vec<ChunkData> {
+        [
+            ChunkId::new(0, 0, -1),
+            ChunkId::new(3, -2, -1),
+            ChunkId::new(-5, 7, -2),
+        ]
+        .iter()
+        .map(|id| {
+            compress(
+                &Chunk::generate(ChunkSeed::new(&WORLD_SEED, id))
+                    .voxelize()
+                    .voxels,
+            )
+        })
+        .collect()
+    }
+
+    fn round_trip(data: &ChunkData) -> ChunkData {
+        let mut bytes = vec![];
+        encode(data, &mut bytes);
+        decode(&bytes).unwrap()
+    }
+
+    #[test]
+    fn empty_chunk() {
+        let data = ChunkData::default();
+        assert_eq!(round_trip(&data), data);
+    }
+
+    #[test]
+    fn sparse_chunk() {
+        let mut voxels = Box::<CubeSlice<Material, CHUNK_SIZE_SAFE>>::default();
+        voxels.set(1, 1, 1, Material::Stone);
+        voxels.set(2, 1, 1, Material::Air);
+        voxels.set(33, 17, 64, Material::Water);
+        voxels.set(64, 64, 64, Material::Glass);
+        let data = compress(&voxels);
+
+        assert_eq!(round_trip(&data), data);
+    }
+
+    #[test]
+    fn round_trips_generated_chunks() {
+        for data in generated_chunks() {
+            assert_eq!(round_trip(&data), data);
+        }
+    }
+
+    #[test]
+    fn generated_chunk_size() {
+        for data in generated_chunks() {
+            let mut bytes = vec![];
+            encode(&data, &mut bytes);
+            assert!(bytes.len() < RAW_SIZE / 32);
+        }
+    }
+
+    // Throughput is reported relative to a raw array of bytes, like the octree benches.
+    #[bench]
+    fn generated_chunk_encode(b: &mut Bencher) {
+        let chunks = generated_chunks();
+        b.bytes = (chunks.len() * RAW_SIZE) as u64;
+
+        b.iter(|| {
+            for data in &chunks {
+                let mut bytes = vec![];
+                encode(data, &mut bytes);
+                test::black_box(bytes);
+            }
+        });
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let mut voxels = Box::<CubeSlice<Material, CHUNK_SIZE_SAFE>>::default();
+        voxels.set(5, 6, 7, Material::Dirt);
+        let mut bytes = vec![];
+        encode(&compress(&voxels), &mut bytes);
+        bytes.pop();
+
+        assert!(decode(&bytes).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{bytes::invalid_data, meta::WorldMeta};
+use crate::{WorldGenConfig, WorldSeed};
+
+/**
+ * Directory holding everything that belongs to one world: the metadata file,
+ * the generator config, the edit journal, the water levels and the region files.
+ */
+#[derive(Debug, Clone)]
+pub struct WorldDir {
+    path: PathBuf,
+}
+
+impl WorldDir {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn meta_path(&self) -> PathBuf {
+        self.path.join("world.toml")
+    }
+
+    pub fn gen_config_path(&self) -> PathBuf {
+        self.path.join("worldgen.toml")
+    }
+
+    pub fn journal_path(&self) -> PathBuf {
+        self.path.join("edits.journal")
+    }
+
+    pub fn fluids_path(&self) -> PathBuf {
+        self.path.join("fluids.levels")
+    }
+
+    pub fn regions_path(&self) -> PathBuf {
+        self.path.join("regions")
+    }
+
+    pub fn exists(&self) -> bool {
+        self.meta_path().is_file()
+    }
+
+    pub fn read_meta(&self) -> io::Result<WorldMeta> {
+        let text = fs::read_to_string(self.meta_path())?;
+        toml::from_str(&text).map_err(|e| invalid_data(&e.to_string()))
+    }
+
+    pub fn write_meta(&self, meta: &WorldMeta) -> io::Result<()> {
+        let text = toml::to_string(meta).map_err(|e| invalid_data(&e.to_string()))?;
+        fs::create_dir_all(&self.path)?;
+        fs::write(self.meta_path(), text)
+    }
+
+    /**
+     * Reads the generator config of the world, worlds without one use the defaults.
+     */
+    pub fn read_gen_config(&self) -> io::Result<WorldGenConfig> {
+        match self.gen_config_path().is_file() {
+            true => WorldGenConfig::load(&self.gen_config_path()),
+            false => Ok(WorldGenConfig::default()),
+        }
+    }
+
+    pub fn write_gen_config(&self, config: &WorldGenConfig) -> io::Result<()> {
+        fs::create_dir_all(&self.path)?;
+        config.save(&self.gen_config_path())
+    }
+
+    /**
+     * Reads the metadata of an existing world or creates a new world
+     * with the given seed, or a random one if none is given.
+     * New worlds get the default generator config to tune.
+     */
+    pub fn load_or_create(&self, seed: Option<WorldSeed>) -> io::Result<WorldMeta> {
+        if self.exists() {
+            return self.read_meta();
+        }
+
+        let meta = WorldMeta::new(&seed.unwrap_or_else(WorldSeed::random));
+        self.write_meta(&meta)?;
+        if !self.gen_config_path().is_file() {
+            self.write_gen_config(&WorldGenConfig::default())?;
+        }
+        Ok(meta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use super::WorldDir;
+    use crate::{WorldGenConfig, WorldSeed};
+
+    #[test]
+    fn creates_and_reloads_world() {
+        let path = env::temp_dir().join(format!("world_dir_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let dir = WorldDir::new(&path);
+        assert!(!dir.exists());
+
+        let mut meta = dir.load_or_create(Some(WorldSeed::new(42))).unwrap();
+        assert!(dir.exists());
+        assert_eq!(meta.seed, 42);
+
+        meta.player.pitch = 0.5;
+        dir.write_meta(&meta).unwrap();
+
+        // the seed of an existing world is kept
+        assert_eq!(dir.load_or_create(Some(WorldSeed::new(7))).unwrap(), meta);
+
+        let mut config = dir.read_gen_config().unwrap();
+        assert_eq!(config, WorldGenConfig::default());
+        config.sea_level = 32;
+        dir.write_gen_config(&config).unwrap();
+        assert_eq!(dir.read_gen_config().unwrap(), config);
+
+        // worlds from before the generator config use the defaults
+        fs::remove_file(dir.gen_config_path()).unwrap();
+        assert_eq!(dir.read_gen_config().unwrap(), WorldGenConfig::default());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use super::bytes::{invalid_data, read_array, read_byte};
+use crate::{fluid::MAX_WATER_LEVEL, WorldPosition};
+
+const MAGIC: [u8; 4] = *b"VXFL";
+const VERSION: u32 = 1;
+
+/**
+ * Layout: header, level count, then one (x, y, z, level) entry per flowing water voxel.
+ */
+pub fn encode(levels: &BTreeMap<WorldPosition, u8>, out: &mut Vec<u8>) {
+    out.extend(MAGIC);
+    out.extend(VERSION.to_le_bytes());
+    out.extend((levels.len() as u32).to_le_bytes());
+
+    for (position, level) in levels {
+        out.extend(position.x.to_le_bytes());
+        out.extend(position.y.to_le_bytes());
+        out.extend(position.z.to_le_bytes());
+        out.push(*level);
+    }
+}
+
+pub fn decode(mut input: &[u8]) -> io::Result<BTreeMap<WorldPosition, u8>> {
+    if read_array::<4>(&mut input)? != MAGIC {
+        return Err(invalid_data("Not a fluid level file"));
+    }
+
+    if u32::from_le_bytes(read_array(&mut input)?) != VERSION {
+        return Err(invalid_data("Unsupported fluid level version"));
+    }
+
+    let mut levels = BTreeMap::new();
+    let count = u32::from_le_bytes(read_array(&mut input)?);
+    for _ in 0..count {
+        let position = WorldPosition::new(
+            i32::from_le_bytes(read_array(&mut input)?),
+            i32::from_le_bytes(read_array(&mut input)?),
+            i32::from_le_bytes(read_array(&mut input)?),
+        );
+        let level = read_byte(&mut input)?;
+        if level == 0 || level > MAX_WATER_LEVEL {
+            return Err(invalid_data("Invalid water level"));
+        }
+        levels.insert(position, level);
+    }
+
+    Ok(levels)
+}
+
+pub fn read(path: &Path) -> io::Result<BTreeMap<WorldPosition, u8>> {
+    match fs::read(path) {
+        Ok(bytes) => decode(&bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn write(path: &Path, levels: &BTreeMap<WorldPosition, u8>) -> io::Result<()> {
+    let mut bytes = vec![];
+    encode(levels, &mut bytes);
+    fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{decode, encode};
+    use crate::WorldPosition;
+
+    #[test]
+    fn round_trip() {
+        let levels = BTreeMap::from([
+            (WorldPosition::new(0, 0, 0), 7),
+            (WorldPosition::new(-1, -65, 200), 1),
+        ]);
+
+        let mut bytes = vec![];
+        encode(&levels, &mut bytes);
+
+        assert_eq!(bytes.len(), 12 + 2 * 13);
+        assert_eq!(decode(&bytes).unwrap(), levels);
+
+        *bytes.last_mut().unwrap() = 9;
+        assert!(decode(&bytes).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use std::{fs, io, path::Path};
+
+use super::bytes::{invalid_data, material_from_id, read_array, read_byte};
+use crate::{mgmt::journal::EditJournal, ChunkId, WorldPosition, CHUNK_SIZE_I};
+
+const MAGIC: [u8; 4] = *b"VXJN";
+const VERSION: u32 = 1;
+
+/**
+ * Layout: header, chunk count, then per chunk its id, edit count and
+ * one (x, y, z, material) byte quadruple per edit relative to the chunk.
+ */
+pub fn encode(journal: &EditJournal, out: &mut Vec<u8>) {
+    out.extend(MAGIC);
+    out.extend(VERSION.to_le_bytes());
+    out.extend((journal.chunk_count() as u32).to_le_bytes());
+
+    for (id, edits) in journal.iter() {
+        out.extend(id.x.to_le_bytes());
+        out.extend(id.y.to_le_bytes());
+        out.extend(id.z.to_le_bytes());
+        out.extend((edits.len() as u32).to_le_bytes());
+
+        for (position, material) in edits {
+            let position_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
+            out.extend([
+                position_in_chunk.x as u8,
+                position_in_chunk.y as u8,
+                position_in_chunk.z as u8,
+                u8::from(*material),
+            ]);
+        }
+    }
+}
+
+pub fn decode(mut input: &[u8]) -> io::Result<EditJournal> {
+    if read_array::<4>(&mut input)? != MAGIC {
+        return Err(invalid_data("Not an edit journal"));
+    }
+
+    if u32::from_le_bytes(read_array(&mut input)?) != VERSION {
+        return Err(invalid_data("Unsupported edit journal version"));
+    }
+
+    let mut journal = EditJournal::new();
+    let chunk_count = u32::from_le_bytes(read_array(&mut input)?);
+    for _ in 0..chunk_count {
+        let id = ChunkId::new(
+            i32::from_le_bytes(read_array(&mut input)?),
+            i32::from_le_bytes(read_array(&mut input)?),
+            i32::from_le_bytes(read_array(&mut input)?),
+        );
+        let start = WorldPosition::from(&id);
+
+        let edit_count = u32::from_le_bytes(read_array(&mut input)?);
+        for _ in 0..edit_count {
+            let [x, y, z] = read_array(&mut input)?;
+            if x as i32 >= CHUNK_SIZE_I || y as i32 >= CHUNK_SIZE_I || z as i32 >= CHUNK_SIZE_I {
+                return Err(invalid_data("Edit outside of chunk"));
+            }
+
+            let material = material_from_id(read_byte(&mut input)?)?;
+            let position = WorldPosition::new(x as i32, y as i32, z as i32);
+            journal.record(&start + &position, material);
+        }
+    }
+
+    Ok(journal)
+}
+
+pub fn read(path: &Path) -> io::Result<EditJournal> {
+    match fs::read(path) {
+        Ok(bytes) => decode(&bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(EditJournal::new()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn write(path: &Path, journal: &EditJournal) -> io::Result<()> {
+    let mut bytes = vec![];
+    encode(journal, &mut bytes);
+    fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+
+    use super::{decode, encode};
+    use crate::{mgmt::journal::EditJournal, WorldPosition};
+
+    #[test]
+    fn round_trip() {
+        let mut journal = EditJournal::new();
+        journal.record(WorldPosition::new(0, 0, 0), Material::Debug);
+        journal.record(WorldPosition::new(-1, -65, 200), Material::Unset);
+        journal.record(WorldPosition::new(63, 63, 63), Material::Glass);
+
+        let mut bytes = vec![];
+        encode(&journal, &mut bytes);
+
+        assert_eq!(bytes.len(), 12 + 2 * 16 + 3 * 4);
+        assert_eq!(decode(&bytes).unwrap(), journal);
+    }
+
+    #[test]
+    fn rejects_unknown_material() {
+        let mut journal = EditJournal::new();
+        journal.record(WorldPosition::new(1, 1, 1), Material::Stone);
+
+        let mut bytes = vec![];
+        encode(&journal, &mut bytes);
+        *bytes.last_mut().unwrap() = 200;
+
+        assert!(decode(&bytes).is_err());
+    }
+}
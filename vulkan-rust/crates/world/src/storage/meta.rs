@@ -0,0 +1,137 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    gen::GENERATOR_VERSION, mesh_generator::Mesher, mgmt::history::DEFAULT_HISTORY_DEPTH,
+    WorldSeed, CHUNK_SIZE_F,
+};
+
+/**
+ * Contents of the metadata file of a world directory.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldMeta {
+    #[serde(
+        serialize_with = "serialize_seed",
+        deserialize_with = "deserialize_seed"
+    )]
+    pub seed: u64,
+    pub generator_version: u32,
+    #[serde(default)]
+    pub player: PlayerMeta,
+    #[serde(default)]
+    pub render: RenderMeta,
+    #[serde(default)]
+    pub edit: EditMeta,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerMeta {
+    pub position: [f32; 3],
+    pub pitch: f32,
+    pub yaw: f32,
+    pub godmode: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderMeta {
+    pub load_distance: f32,
+    pub unload_distance: f32,
+    /// Distances beyond which chunks are meshed at the next coarser level of detail
+    pub lod_distances: Vec<f32>,
+    pub mesher: Mesher,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditMeta {
+    /// Number of edits that can be undone
+    pub history_depth: usize,
+}
+
+impl WorldMeta {
+    pub fn new(seed: &WorldSeed) -> Self {
+        Self {
+            seed: u64::from(seed),
+            generator_version: GENERATOR_VERSION,
+            player: PlayerMeta::default(),
+            render: RenderMeta::default(),
+            edit: EditMeta::default(),
+        }
+    }
+
+    pub fn world_seed(&self) -> WorldSeed {
+        WorldSeed::new(self.seed)
+    }
+}
+
+impl Default for PlayerMeta {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0, 64.0],
+            pitch: 0.0,
+            yaw: 0.0,
+            godmode: false,
+        }
+    }
+}
+
+impl Default for RenderMeta {
+    fn default() -> Self {
+        Self {
+            load_distance: CHUNK_SIZE_F * 5.0,
+            unload_distance: CHUNK_SIZE_F * 6.0,
+            lod_distances: vec![CHUNK_SIZE_F * 2.5, CHUNK_SIZE_F * 3.5, CHUNK_SIZE_F * 4.5],
+            mesher: Mesher::Greedy,
+        }
+    }
+}
+
+impl Default for EditMeta {
+    fn default() -> Self {
+        Self {
+            history_depth: DEFAULT_HISTORY_DEPTH,
+        }
+    }
+}
+
+// TOML integers are signed, so seeds are stored with their bits reinterpreted.
+fn serialize_seed<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(*seed as i64)
+}
+
+fn deserialize_seed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    i64::deserialize(deserializer).map(|seed| seed as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EditMeta, PlayerMeta, WorldMeta};
+    use crate::{mesh_generator::Mesher, WorldSeed};
+
+    #[test]
+    fn round_trip() {
+        let mut meta = WorldMeta::new(&WorldSeed::new(u64::MAX - 3));
+        meta.player.position = [12.5, -3.0, 80.0];
+        meta.player.yaw = 1.25;
+        meta.player.godmode = true;
+        meta.render.load_distance = 128.0;
+        meta.render.lod_distances = vec![200.0];
+        meta.render.mesher = Mesher::SurfaceNets;
+        meta.edit.history_depth = 12;
+
+        let text = toml::to_string(&meta).unwrap();
+        assert_eq!(toml::from_str::<WorldMeta>(&text).unwrap(), meta);
+    }
+
+    #[test]
+    fn missing_sections_use_defaults() {
+        let meta = toml::from_str::<WorldMeta>("seed = 17\ngenerator_version = 1\n").unwrap();
+
+        assert_eq!(meta.seed, 17);
+        assert_eq!(meta.generator_version, 1);
+        assert_eq!(meta.player, PlayerMeta::default());
+        assert_eq!(meta.edit, EditMeta::default());
+    }
+}
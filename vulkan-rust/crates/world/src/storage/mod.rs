@@ -0,0 +1,12 @@
+pub(crate) mod bytes;
+pub(crate) mod chunk;
+pub mod dir;
+pub mod fluids;
+pub mod journal;
+pub mod meta;
+pub mod region;
+pub mod vox;
+
+pub use dir::WorldDir;
+pub use meta::{EditMeta, PlayerMeta, RenderMeta, WorldMeta};
+pub use region::{RegionId, RegionStore};
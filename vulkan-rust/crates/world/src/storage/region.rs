@@ -0,0 +1,281 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use super::{bytes::invalid_data, chunk};
+use crate::{ChunkData, ChunkId};
+
+pub const REGION_SIZE: i32 = 16;
+pub const CHUNKS_PER_REGION: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+
+const MAGIC: [u8; 4] = *b"VXRG";
+const VERSION: u32 = 1;
+const ENTRY_SIZE: u64 = 8;
+const TABLE_START: u64 = 8;
+const DATA_START: u64 = TABLE_START + CHUNKS_PER_REGION as u64 * ENTRY_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionId {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl RegionId {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("r.{}.{}.{}.region", self.x, self.y, self.z)
+    }
+}
+
+impl From<&ChunkId> for RegionId {
+    fn from(id: &ChunkId) -> Self {
+        Self::new(
+            id.x.div_euclid(REGION_SIZE),
+            id.y.div_euclid(REGION_SIZE),
+            id.z.div_euclid(REGION_SIZE),
+        )
+    }
+}
+
+/**
+ * Index of a chunk inside the offset table of its region.
+ */
+fn table_index(id: &ChunkId) -> usize {
+    let x = id.x.rem_euclid(REGION_SIZE);
+    let y = id.y.rem_euclid(REGION_SIZE);
+    let z = id.z.rem_euclid(REGION_SIZE);
+
+    ((z * REGION_SIZE + y) * REGION_SIZE + x) as usize
+}
+
+/**
+ * Stores chunks in region files of 16x16x16 chunks.
+ *
+ * Every file starts with a header and a table of (offset, length) entries, one per chunk.
+ * An offset of 0 marks a chunk that was never saved. Chunks that grow are appended.
+ */
+pub struct RegionStore {
+    dir: PathBuf,
+}
+
+impl RegionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, region: &RegionId) -> PathBuf {
+        self.dir.join(region.file_name())
+    }
+
+    pub fn load(&self, id: &ChunkId) -> io::Result<Option<ChunkData>> {
+        let mut file = match File::open(self.path(&RegionId::from(id))) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        read_header(&mut file)?;
+        let (offset, length) = read_entry(&mut file, table_index(id))?;
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let mut bytes = vec![0; length as usize];
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.read_exact(&mut bytes)?;
+
+        chunk::decode(&bytes).map(Some)
+    }
+
+    pub fn save(&self, id: &ChunkId, data: &ChunkData) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(self.path(&RegionId::from(id)))?;
+
+        if file.metadata()?.len() == 0 {
+            write_header(&mut file)?;
+        } else {
+            read_header(&mut file)?;
+        }
+
+        let mut bytes = vec![];
+        chunk::encode(data, &mut bytes);
+
+        let index = table_index(id);
+        let (old_offset, old_length) = read_entry(&mut file, index)?;
+        let offset = if old_offset != 0 && bytes.len() as u64 <= old_length as u64 {
+            old_offset as u64
+        } else {
+            file.seek(SeekFrom::End(0))?
+        };
+
+        let offset = u32::try_from(offset).map_err(|_| invalid_data("Region file too large"))?;
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.write_all(&bytes)?;
+        write_entry(&mut file, index, offset, bytes.len() as u32)
+    }
+
+    pub fn save_all<'a>(
+        &self,
+        chunks: impl Iterator<Item = (&'a ChunkId, &'a ChunkData)>,
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        for (id, data) in chunks {
+            self.save(id, data)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+fn write_header(file: &mut File) -> io::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&vec![0; (DATA_START - TABLE_START) as usize])
+}
+
+fn read_header(file: &mut File) -> io::Result<()> {
+    let mut header = [0; TABLE_START as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+
+    if header[0..4] != MAGIC {
+        return Err(invalid_data("Not a region file"));
+    }
+
+    if u32::from_le_bytes([header[4], header[5], header[6], header[7]]) != VERSION {
+        return Err(invalid_data("Unsupported region version"));
+    }
+
+    Ok(())
+}
+
+fn read_entry(file: &mut File, index: usize) -> io::Result<(u32, u32)> {
+    let mut entry = [0; ENTRY_SIZE as usize];
+    file.seek(SeekFrom::Start(TABLE_START + index as u64 * ENTRY_SIZE))?;
+    file.read_exact(&mut entry)?;
+
+    let offset = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+    let length = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+    Ok((offset, length))
+}
+
+fn write_entry(file: &mut File, index: usize, offset: u32, length: u32) -> io::Result<()> {
+    file.seek(SeekFrom::Start(TABLE_START + index as u64 * ENTRY_SIZE))?;
+    file.write_all(&offset.to_le_bytes())?;
+    file.write_all(&length.to_le_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::PathBuf};
+
+    use gamedata::material::Material;
+
+    use super::{table_index, RegionId, RegionStore, CHUNKS_PER_REGION};
+    use crate::{
+        gen::chunk::compress, slice::CubeSlice, traits::Data3D, ChunkData, ChunkId, CHUNK_SIZE_SAFE,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("region_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn sample_chunk(seed: usize) -> ChunkData {
+        let mut voxels = Box::<CubeSlice<Material, CHUNK_SIZE_SAFE>>::default();
+        for i in 0..seed {
+            voxels.set(
+                1 + i % 64,
+                1 + (i * 7) % 64,
+                1 + (i * 13) % 64,
+                Material::Stone,
+            );
+        }
+        compress(&voxels)
+    }
+
+    #[test]
+    fn region_of_negative_chunk() {
+        assert_eq!(
+            RegionId::from(&ChunkId::new(-1, 0, 16)),
+            RegionId::new(-1, 0, 1)
+        );
+        assert_eq!(table_index(&ChunkId::new(-1, 0, 0)), 15);
+        assert_eq!(
+            table_index(&ChunkId::new(15, 15, 15)),
+            CHUNKS_PER_REGION - 1
+        );
+    }
+
+    #[test]
+    fn missing_chunk_is_none() {
+        let dir = temp_dir("missing");
+        let store = RegionStore::new(&dir).unwrap();
+        assert!(store.load(&ChunkId::new(0, 0, 0)).unwrap().is_none());
+
+        store
+            .save(&ChunkId::new(1, 0, 0), &sample_chunk(3))
+            .unwrap();
+        assert!(store.load(&ChunkId::new(0, 0, 0)).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn save_and_reload() {
+        let dir = temp_dir("reload");
+        let ids = [
+            ChunkId::new(0, 0, 0),
+            ChunkId::new(-1, -1, -1),
+            ChunkId::new(15, 3, -17),
+        ];
+
+        {
+            let store = RegionStore::new(&dir).unwrap();
+            for (i, id) in ids.iter().enumerate() {
+                store.save(id, &sample_chunk(i * 10)).unwrap();
+            }
+        }
+
+        let store = RegionStore::new(&dir).unwrap();
+        for (i, id) in ids.iter().enumerate() {
+            assert_eq!(store.load(id).unwrap(), Some(sample_chunk(i * 10)));
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn overwrite_grow_and_shrink() {
+        let dir = temp_dir("overwrite");
+        let store = RegionStore::new(&dir).unwrap();
+        let id = ChunkId::new(2, 2, 2);
+        let neighbor = ChunkId::new(3, 2, 2);
+
+        store.save(&id, &sample_chunk(5)).unwrap();
+        store.save(&neighbor, &sample_chunk(7)).unwrap();
+        store.save(&id, &sample_chunk(200)).unwrap();
+        assert_eq!(store.load(&id).unwrap(), Some(sample_chunk(200)));
+        assert_eq!(store.load(&neighbor).unwrap(), Some(sample_chunk(7)));
+
+        store.save(&id, &sample_chunk(1)).unwrap();
+        assert_eq!(store.load(&id).unwrap(), Some(sample_chunk(1)));
+        assert_eq!(store.load(&neighbor).unwrap(), Some(sample_chunk(7)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
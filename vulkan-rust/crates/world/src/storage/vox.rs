@@ -0,0 +1,250 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use gamedata::material::Material;
+use vox_format::{
+    data::VoxModels,
+    types::{Color, ColorIndex, Model, Palette, Size, Vector, Voxel},
+    VoxData,
+};
+
+use super::bytes::invalid_data;
+use crate::{traits::Data3D, ChunkId, ChunkManager, WorldPosition, CHUNK_SIZE_I};
+
+/// Models in .vox files can not be larger than this in any dimension.
+pub const MAX_VOX_SIZE: i32 = 256;
+
+/**
+ * Maps the color indices of a .vox model to materials. Indices without a material are skipped.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorMapping {
+    materials: [Option<Material>; 256],
+}
+
+impl ColorMapping {
+    pub fn empty() -> Self {
+        Self {
+            materials: [None; 256],
+        }
+    }
+
+    /**
+     * Color index n is the material with id n - 1, the layout written by `export`.
+     */
+    pub fn by_material_id() -> Self {
+        let mut mapping = Self::empty();
+        for material in Material::ALL {
+            mapping.set(u8::from(material) + 1, Some(material));
+        }
+        mapping
+    }
+
+    /**
+     * Picks the visible material with the closest color for every palette entry.
+     */
+    pub fn nearest_color(palette: &Palette) -> Self {
+        let mut mapping = Self::empty();
+        for index in 1..=255 {
+            let color = <[u8; 4]>::from(palette.get(ColorIndex(index)));
+            let nearest = Material::ALL
+                .into_iter()
+                .filter(|m| !m.is_invisible())
+                .min_by_key(|m| {
+                    m.color_bytes()
+                        .iter()
+                        .zip(color)
+                        .map(|(a, b)| (*a as i32 - b as i32).pow(2))
+                        .sum::<i32>()
+                });
+            mapping.set(index, nearest);
+        }
+        mapping
+    }
+
+    pub fn set(&mut self, index: u8, material: Option<Material>) {
+        self.materials[index as usize] = material;
+    }
+
+    pub fn get(&self, index: ColorIndex) -> Option<Material> {
+        self.materials[index.0 as usize]
+    }
+}
+
+impl Default for ColorMapping {
+    fn default() -> Self {
+        Self::by_material_id()
+    }
+}
+
+/**
+ * Palette with the color of every material at its id + 1.
+ */
+pub fn palette() -> Palette {
+    let mut palette = Palette::default();
+    for material in Material::ALL {
+        palette.colors[u8::from(material) as usize + 1] = Color::from(material.color_bytes());
+    }
+    palette
+}
+
+/**
+ * Copies all visible voxels between min and max (inclusive) into a model.
+ * Voxels of unloaded chunks are left empty.
+ */
+pub fn export(
+    chunks: &ChunkManager,
+    min: &WorldPosition,
+    max: &WorldPosition,
+) -> io::Result<VoxData> {
+    let size = [max.x - min.x + 1, max.y - min.y + 1, max.z - min.z + 1];
+    if size.iter().any(|s| *s <= 0 || *s > MAX_VOX_SIZE) {
+        return Err(invalid_data("Invalid size for a .vox model"));
+    }
+
+    let mut voxels = vec![];
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let position = WorldPosition::new(x, y, z);
+                let Some(data) = chunks.get(&ChunkId::from(&position)) else {
+                    continue;
+                };
+
+                let pos_in_chunk = position.rem_euclid(CHUNK_SIZE_I);
+                let material = data.get(
+                    pos_in_chunk.x as usize,
+                    pos_in_chunk.y as usize,
+                    pos_in_chunk.z as usize,
+                );
+                if !material.is_invisible() {
+                    let point = [x - min.x, y - min.y, z - min.z].map(|p| p as u8 as i8);
+                    voxels.push(Voxel::new(point, ColorIndex(u8::from(material) + 1)));
+                }
+            }
+        }
+    }
+
+    let size = size.map(|s| s as u32);
+    Ok(VoxModels {
+        models: vec![Model {
+            size: Size::new(size[0], size[1], size[2]),
+            voxels,
+        }],
+        palette: palette(),
+        ..Default::default()
+    })
+}
+
+/**
+ * Places the voxels of a model with its minimum corner at the given position as one
+ * undoable player edit. Returns the chunks that need to be remeshed.
+ */
+pub fn import(
+    chunks: &mut ChunkManager,
+    model: &Model,
+    position: &WorldPosition,
+    mapping: &ColorMapping,
+) -> HashSet<ChunkId> {
+    let mut dirty = HashSet::new();
+    chunks.begin_transaction();
+    for voxel in &model.voxels {
+        let Some(material) = mapping.get(voxel.color_index) else {
+            continue;
+        };
+
+        let Vector { x, y, z } = voxel.point;
+        let target = WorldPosition::new(
+            position.x + x as u8 as i32,
+            position.y + y as u8 as i32,
+            position.z + z as u8 as i32,
+        );
+        if chunks
+            .edit_block(target.x, target.y, target.z, material)
+            .is_some()
+        {
+            dirty.extend(chunks.chunks_to_remesh(&target));
+        }
+    }
+    chunks.commit_transaction();
+    dirty
+}
+
+pub fn read(path: &Path) -> io::Result<VoxData> {
+    let bytes = fs::read(path)?;
+    vox_format::from_slice(&bytes).map_err(|e| invalid_data(&e.to_string()))
+}
+
+pub fn write(path: &Path, vox: &VoxData) -> io::Result<()> {
+    let bytes = vox_format::to_vec(vox).map_err(|e| invalid_data(&e.to_string()))?;
+    fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+    use vox_format::types::ColorIndex;
+
+    use super::{export, import, palette, ColorMapping};
+    use crate::{traits::Data3D, ChunkData, ChunkId, ChunkManager, WorldPosition};
+
+    fn manager_with(ids: &[ChunkId]) -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+        for id in ids {
+            chunks.insert(id, ChunkData::default());
+        }
+        chunks
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let mut source = manager_with(&[ChunkId::new(-1, 0, 0), ChunkId::new(0, 0, 0)]);
+        source.set_block(-1, 0, 0, Material::Stone).unwrap();
+        source.set_block(0, 3, 2, Material::Wood).unwrap();
+        source.set_block(1, 1, 1, Material::Air).unwrap();
+
+        let vox = export(
+            &source,
+            &WorldPosition::new(-2, 0, 0),
+            &WorldPosition::new(2, 3, 2),
+        )
+        .unwrap();
+        let bytes = vox_format::to_vec(&vox).unwrap();
+        let vox = vox_format::from_slice(&bytes).unwrap();
+        assert_eq!(vox.models[0].voxels.len(), 2);
+
+        let mut target = manager_with(&[ChunkId::new(1, 1, 1)]);
+        let dirty = import(
+            &mut target,
+            &vox.models[0],
+            &WorldPosition::new(70, 70, 70),
+            &ColorMapping::nearest_color(&vox.palette),
+        );
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(target.history().undo_len(), 1);
+
+        let chunk = target.get(&ChunkId::new(1, 1, 1)).unwrap();
+        assert_eq!(chunk.get(7, 6, 6), Material::Stone);
+        assert_eq!(chunk.get(8, 9, 8), Material::Wood);
+        assert_eq!(chunk.get(9, 7, 7), Material::Unset);
+    }
+
+    #[test]
+    fn export_rejects_large_regions() {
+        let chunks = manager_with(&[]);
+        let min = WorldPosition::new(0, 0, 0);
+        assert!(export(&chunks, &min, &WorldPosition::new(300, 0, 0)).is_err());
+        assert!(export(&chunks, &min, &WorldPosition::new(-1, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn mappings_agree_on_exported_palette() {
+        let by_id = ColorMapping::by_material_id();
+        let nearest = ColorMapping::nearest_color(&palette());
+        for material in [Material::Stone, Material::Grass, Material::Leaves] {
+            let index = ColorIndex(u8::from(material) + 1);
+            assert_eq!(by_id.get(index), Some(material));
+            assert_eq!(nearest.get(index), Some(material));
+        }
+        assert_eq!(by_id.get(ColorIndex(0)), None);
+    }
+}
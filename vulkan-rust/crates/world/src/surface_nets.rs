@@ -0,0 +1,364 @@
+use nalgebra_glm as glm;
+
+use gamedata::material::Material;
+use graphics::{Mesh, Vertex};
+
+use crate::{chunk_id::ChunkId, light::Light, traits::Data3D, CHUNK_SIZE, CHUNK_SIZE_SAFE};
+
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// Pairs of corners connected by the 12 edges of a cell
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/**
+ * Vertex of a cell that the surface passes through.
+ */
+#[derive(Clone, Copy)]
+struct CellVertex {
+    position: glm::Vec3,
+    normal: glm::Vec3,
+    material: Material,
+    light: Light,
+}
+
+/**
+ * Smooth mesh of all opaque voxels in full sky light, built with surface nets.
+ * Uses the same padded layout as the greedy mesher, but needs the borders on all sides.
+ */
+pub fn generate_surface_nets_mesh<T>(_id: &ChunkId, data: &T) -> Mesh
+where
+    T: Data3D<Material>,
+{
+    surface_nets(data, |_, _, _| Light::SKY)
+}
+
+/**
+ * Smooth mesh of all opaque voxels, lit by the brightest open voxel around each vertex.
+ */
+pub fn generate_lit_surface_nets_mesh<T, L>(_id: &ChunkId, data: &T, light: &L) -> Mesh
+where
+    T: Data3D<Material>,
+    L: Data3D<Light>,
+{
+    surface_nets(data, |x, y, z| light.get(x, y, z))
+}
+
+fn density<T>(data: &T, x: usize, y: usize, z: usize) -> f32
+where
+    T: Data3D<Material>,
+{
+    data.get(x, y, z).is_opaque() as u8 as f32
+}
+
+/**
+ * Gradient of the density at a voxel by central differences, clamped at the borders.
+ */
+fn gradient<T>(data: &T, p: [usize; 3]) -> glm::Vec3
+where
+    T: Data3D<Material>,
+{
+    let mut gradient = [0.0; 3];
+    for (d, g) in gradient.iter_mut().enumerate() {
+        let mut low = p;
+        let mut high = p;
+        low[d] = p[d].saturating_sub(1);
+        high[d] = (p[d] + 1).min(CHUNK_SIZE_SAFE - 1);
+        *g = density(data, high[0], high[1], high[2]) - density(data, low[0], low[1], low[2]);
+    }
+    glm::vec3(gradient[0], gradient[1], gradient[2])
+}
+
+/**
+ * Most common opaque material among the corners of a cell, the first one wins ties.
+ */
+fn cell_material(materials: &[Material; 8]) -> Material {
+    let mut best = (0, Material::Unset);
+    for m in materials.iter().filter(|m| m.is_opaque()) {
+        let count = materials.iter().filter(|other| *other == m).count();
+        if count > best.0 {
+            best = (count, *m);
+        }
+    }
+    best.1
+}
+
+fn cell_vertex<T>(
+    data: &T,
+    light: &impl Fn(usize, usize, usize) -> Light,
+    cell: [usize; 3],
+) -> Option<CellVertex>
+where
+    T: Data3D<Material>,
+{
+    let corner = |i: usize| {
+        let c = CORNERS[i];
+        [cell[0] + c[0], cell[1] + c[1], cell[2] + c[2]]
+    };
+    let materials = [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        let p = corner(i);
+        data.get(p[0], p[1], p[2])
+    });
+    let solid = materials.map(|m| m.is_opaque());
+    if solid.iter().all(|s| *s) || solid.iter().all(|s| !*s) {
+        return None;
+    }
+
+    // the density is binary, so the surface crosses every edge in its middle
+    let mut sum = glm::Vec3::zeros();
+    let mut crossings = 0.0;
+    for (a, b) in EDGES {
+        if solid[a] != solid[b] {
+            let (a, b) = (CORNERS[a], CORNERS[b]);
+            sum += glm::vec3(
+                (a[0] + b[0]) as f32,
+                (a[1] + b[1]) as f32,
+                (a[2] + b[2]) as f32,
+            ) * 0.5;
+            crossings += 1.0;
+        }
+    }
+
+    let mut normal = glm::Vec3::zeros();
+    let mut face_light = Light::DARK;
+    for (i, solid) in solid.into_iter().enumerate() {
+        let p = corner(i);
+        normal -= gradient(data, p);
+        if !solid {
+            let l = light(p[0], p[1], p[2]);
+            face_light = Light::new(
+                l.sky().max(face_light.sky()),
+                l.block().max(face_light.block()),
+            );
+        }
+    }
+
+    // samples are voxel centers, half a voxel into the padded border
+    let origin = glm::vec3(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+    Some(CellVertex {
+        position: origin + sum / crossings - glm::vec3(0.5, 0.5, 0.5),
+        normal: match normal == glm::Vec3::zeros() {
+            true => glm::vec3(0.0, 0.0, 1.0),
+            false => normal.normalize(),
+        },
+        material: cell_material(&materials),
+        light: face_light,
+    })
+}
+
+fn surface_nets<T>(data: &T, light: impl Fn(usize, usize, usize) -> Light) -> Mesh
+where
+    T: Data3D<Material>,
+{
+    // cells between the samples i and i + 1, the border cells are shared with the neighbors
+    let mut cells = vec![None; CHUNK_SIZE_SAFE.pow(3)];
+    let index = |c: [usize; 3]| (c[2] * CHUNK_SIZE_SAFE + c[1]) * CHUNK_SIZE_SAFE + c[0];
+    for z in 0..CHUNK_SIZE_SAFE - 1 {
+        for y in 0..CHUNK_SIZE_SAFE - 1 {
+            for x in 0..CHUNK_SIZE_SAFE - 1 {
+                cells[index([x, y, z])] = cell_vertex(data, &light, [x, y, z]);
+            }
+        }
+    }
+
+    // every edge between two voxels of this chunk and the next ones on the positive sides
+    let mut mesh = Mesh::default();
+    for d in 0..3 {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+        for z in 1..=CHUNK_SIZE {
+            for y in 1..=CHUNK_SIZE {
+                for x in 1..=CHUNK_SIZE {
+                    let p = [x, y, z];
+                    let mut q = p;
+                    q[d] += 1;
+                    let solid = data.get(p[0], p[1], p[2]).is_opaque();
+                    if solid == data.get(q[0], q[1], q[2]).is_opaque() {
+                        continue;
+                    }
+
+                    let around = [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(du, dv)| {
+                        let mut c = p;
+                        c[u] -= du;
+                        c[v] -= dv;
+                        cells[index(c)].expect("Cells around a crossed edge contain the surface")
+                    });
+                    // counter clockwise when seen from the open side
+                    let quad = match solid {
+                        true => around,
+                        false => [around[0], around[3], around[2], around[1]],
+                    };
+                    for triangle in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+                        add_triangle(&mut mesh, triangle);
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/**
+ * Adds a triangle with its own vertices, so the materials of its corners blend across it.
+ */
+fn add_triangle(mesh: &mut Mesh, triangle: [CellVertex; 3]) {
+    let materials = glm::vec3(
+        u8::from(triangle[0].material) as f32,
+        u8::from(triangle[1].material) as f32,
+        u8::from(triangle[2].material) as f32,
+    );
+    let i0 = mesh.vertices.len() as u32;
+    for (i, vertex) in triangle.into_iter().enumerate() {
+        let mut blend = glm::Vec3::zeros();
+        blend[i] = 1.0;
+        mesh.vertices.push(
+            Vertex::from_material(vertex.position, vertex.material.into(), vertex.normal)
+                .with_light(vertex.light.normalized())
+                .with_blend(materials, blend),
+        );
+    }
+    mesh.indices.extend([i0, i0 + 1, i0 + 2]);
+}
+
+#[cfg(test)]
+mod test {
+    use gamedata::material::Material;
+    use graphics::validate::validate_closed;
+
+    use super::generate_surface_nets_mesh;
+    use crate::{
+        chunk_id::ChunkId, slice::CubeSlice, traits::Data3D, CHUNK_SIZE_I, CHUNK_SIZE_SAFE,
+    };
+
+    type Blocks = CubeSlice<Material, CHUNK_SIZE_SAFE>;
+
+    /**
+     * Padded blocks of a chunk, taken from a function of world voxel positions.
+     */
+    fn blocks(id: &ChunkId, material: impl Fn(i32, i32, i32) -> Material) -> Box<Blocks> {
+        let mut blocks = Box::<Blocks>::default();
+        let world = |p: usize, chunk: i32| p as i32 - 1 + chunk * CHUNK_SIZE_I;
+        for x in 0..CHUNK_SIZE_SAFE {
+            for y in 0..CHUNK_SIZE_SAFE {
+                for z in 0..CHUNK_SIZE_SAFE {
+                    let m = material(world(x, id.x), world(y, id.y), world(z, id.z));
+                    blocks.set(x, y, z, m);
+                }
+            }
+        }
+        blocks
+    }
+
+    /**
+     * Ball of stone with a grass top half.
+     */
+    fn ball(center: [f32; 3], radius: f32) -> impl Fn(i32, i32, i32) -> Material {
+        move |x, y, z| {
+            let distance = [x, y, z]
+                .into_iter()
+                .zip(center)
+                .map(|(p, c)| (p as f32 + 0.5 - c).powi(2))
+                .sum::<f32>();
+            match (distance <= radius * radius, (z as f32) < center[2]) {
+                (false, _) => Material::Air,
+                (true, true) => Material::Stone,
+                (true, false) => Material::Grass,
+            }
+        }
+    }
+
+    /**
+     * Meshes of several chunks as one mesh in world space.
+     */
+    fn merge(meshes: &[(graphics::Mesh, ChunkId)]) -> graphics::Mesh {
+        let mut merged = graphics::Mesh::default();
+        for (mesh, id) in meshes {
+            let offset = glm::Vec3::from(id);
+            let first = merged.vertices.len() as u32;
+            merged.vertices.extend(mesh.vertices.iter().map(|vertex| {
+                let mut vertex = *vertex;
+                let pos = vertex.pos_mat.xyz() + offset;
+                vertex.pos_mat = glm::vec4(pos.x, pos.y, pos.z, vertex.pos_mat.w);
+                vertex
+            }));
+            merged
+                .indices
+                .extend(mesh.indices.iter().map(|i| i + first));
+        }
+        merged
+    }
+
+    #[test]
+    fn ball_is_closed_and_smooth() {
+        let id = ChunkId::new(0, 0, 0);
+        let center = glm::vec3(32.0, 32.0, 32.0);
+        let mesh = generate_surface_nets_mesh(&id, &*blocks(&id, ball([32.0; 3], 10.0)));
+
+        assert!(!mesh.vertices.is_empty());
+        for vertex in &mesh.vertices {
+            let outwards = vertex.pos_mat.xyz() - center;
+            assert!((outwards.norm() - 10.0).abs() < 1.5);
+            assert!(vertex.normal.dot(&outwards.normalize()) > 0.5);
+        }
+        assert_eq!(validate_closed(&mesh), vec![]);
+    }
+
+    #[test]
+    fn materials_blend_at_boundaries() {
+        let id = ChunkId::new(0, 0, 0);
+        let mesh = generate_surface_nets_mesh(&id, &*blocks(&id, ball([32.0; 3], 10.0)));
+
+        let stone = f32::from(u8::from(Material::Stone));
+        let grass = f32::from(u8::from(Material::Grass));
+        let blended = mesh.vertices.iter().filter(|v| {
+            let m = v.materials;
+            m.min() == stone && m.max() == grass
+        });
+        assert!(blended.count() > 0);
+        for triangle in mesh.indices.chunks(3) {
+            let materials = mesh.vertices[triangle[0] as usize].materials;
+            for i in triangle {
+                assert_eq!(mesh.vertices[*i as usize].materials, materials);
+            }
+        }
+    }
+
+    #[test]
+    fn seams_line_up() {
+        // ball in the corner of 8 chunks
+        let material = ball([64.0; 3], 9.0);
+        let mut meshes = vec![];
+        for i in 0..8 {
+            let id = ChunkId::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+            let mesh = generate_surface_nets_mesh(&id, &*blocks(&id, &material));
+            assert!(!mesh.vertices.is_empty());
+            meshes.push((mesh, id));
+        }
+        assert_eq!(validate_closed(&merge(&meshes)), vec![]);
+
+        meshes.pop();
+        assert_ne!(validate_closed(&merge(&meshes)), vec![]);
+    }
+}
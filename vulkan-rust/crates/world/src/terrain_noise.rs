@@ -1,6 +1,10 @@
 use std::f32::consts::PI;
 
-use crate::{world_position::WorldPosition, WorldSeed, CHUNK_SIZE, CHUNK_SIZE_I};
+use crate::{
+    gen::config::{CaveConfig, ClimateConfig, HeightConfig},
+    world_position::WorldPosition,
+    WorldSeed, CHUNK_SIZE, CHUNK_SIZE_I,
+};
 use simdnoise::NoiseBuilder;
 
 mod noise_id {
@@ -10,7 +14,13 @@ mod noise_id {
     pub const RAINFALL: i32 = 3;
 }
 
-pub fn height(seed: &WorldSeed, offset_x: i32, offset_y: i32, size: usize) -> Vec<f32> {
+pub fn height(
+    seed: &WorldSeed,
+    config: &HeightConfig,
+    offset_x: i32,
+    offset_y: i32,
+    size: usize,
+) -> Vec<f32> {
     let seed_offset_x = u64::from(seed) & 0xFFFF;
     let seed_offset_y = (u64::from(seed) >> 32) & 0xFFFF;
     let x = offset_x as f32 + seed_offset_x as f32;
@@ -18,7 +28,7 @@ pub fn height(seed: &WorldSeed, offset_x: i32, offset_y: i32, size: usize) -> Ve
 
     let (base_height, _min, _max) = NoiseBuilder::gradient_2d_offset(x, size, y, size)
         .with_seed(noise_id::HEIGHT + i32::from(seed))
-        .with_freq(0.000017)
+        .with_freq(config.base_frequency)
         // .with_octaves(11)
         // .with_gain(1.0)
         // .with_lacunarity(2.0)
@@ -26,26 +36,32 @@ pub fn height(seed: &WorldSeed, offset_x: i32, offset_y: i32, size: usize) -> Ve
 
     let (variation, _min, _max) = NoiseBuilder::fbm_2d_offset(x as f32, size, y as f32, size)
         .with_seed(noise_id::HEIGHT + i32::from(seed))
-        .with_freq(0.000003)
-        .with_octaves(14)
-        .with_gain(1.0)
-        .with_lacunarity(2.0)
+        .with_freq(config.variation_frequency)
+        .with_octaves(config.variation_octaves)
+        .with_gain(config.variation_gain)
+        .with_lacunarity(config.variation_lacunarity)
         .generate();
 
     base_height
         .into_iter()
         .zip(variation.into_iter())
-        .map(|(base_height, variation)| (base_height * 12000.0) + variation * 100.0)
+        .map(|(base_height, variation)| {
+            (base_height * config.base_amplitude) + variation * config.variation_amplitude
+        })
         .collect()
 }
 
-pub fn chunk_temperature(seed: &WorldSeed, position: &WorldPosition) -> Vec<f32> {
+pub fn chunk_temperature(
+    seed: &WorldSeed,
+    config: &ClimateConfig,
+    position: &WorldPosition,
+) -> Vec<f32> {
     let x = (position.x / CHUNK_SIZE_I) as f32;
     let y = (position.y / CHUNK_SIZE_I) as f32;
 
     let (temp, ..) = NoiseBuilder::fbm_2d_offset(x, 2, y, 2)
         .with_seed(noise_id::TEMPERATURE + i32::from(seed))
-        .with_freq(0.08)
+        .with_freq(config.temperature_frequency)
         .with_octaves(3)
         .with_gain(2.0)
         .with_lacunarity(0.5)
@@ -58,13 +74,17 @@ pub fn chunk_temperature(seed: &WorldSeed, position: &WorldPosition) -> Vec<f32>
     temp
 }
 
-pub fn chunk_rainfall(seed: &WorldSeed, position: &WorldPosition) -> Vec<f32> {
+pub fn chunk_rainfall(
+    seed: &WorldSeed,
+    config: &ClimateConfig,
+    position: &WorldPosition,
+) -> Vec<f32> {
     let x = (position.x / CHUNK_SIZE_I) as f32;
     let y = (position.y / CHUNK_SIZE_I) as f32;
 
     let (temp, ..) = NoiseBuilder::fbm_2d_offset(x, 2, y, 2)
         .with_seed(noise_id::RAINFALL + i32::from(seed))
-        .with_freq(0.2)
+        .with_freq(config.rainfall_frequency)
         .with_octaves(3)
         .with_gain(2.0)
         .with_lacunarity(0.5)
@@ -98,7 +118,14 @@ pub fn wave(x_start: i32, y_start: i32, size: usize) -> Vec<f32> {
     result
 }
 
-pub fn caves(seed: &WorldSeed, offset_x: i32, offset_y: i32, z: i32, size: usize) -> Vec<f32> {
+pub fn caves(
+    seed: &WorldSeed,
+    config: &CaveConfig,
+    offset_x: i32,
+    offset_y: i32,
+    z: i32,
+    size: usize,
+) -> Vec<f32> {
     let seed_offset_x = u64::from(seed) & 0xFFFF;
     let seed_offset_y = (u64::from(seed) >> 32) & 0xFFFF;
     let x = offset_x as f32 + seed_offset_x as f32;
@@ -107,10 +134,10 @@ pub fn caves(seed: &WorldSeed, offset_x: i32, offset_y: i32, z: i32, size: usize
     let (noise, _min, _max) =
         NoiseBuilder::turbulence_3d_offset(x as f32, size, y as f32, size, z as f32, size)
             .with_seed(noise_id::CAVES + i32::from(seed))
-            .with_freq(0.005)
-            .with_octaves(2)
-            .with_gain(2.0)
-            .with_lacunarity(0.5)
+            .with_freq(config.frequency)
+            .with_octaves(config.octaves)
+            .with_gain(config.gain)
+            .with_lacunarity(config.lacunarity)
             .generate();
 
     // println!("Cave noise: ({}, {})", _min, _max);
@@ -1,6 +1,3 @@
-// WORLD
-pub const SEA_LEVEL: i32 = 0;
-
 // CHUNKS
 pub const CHUNK_SIZE: usize = 64;
 pub const CHUNK_SIZE_SQUARED: usize = CHUNK_SIZE * CHUNK_SIZE;
@@ -0,0 +1,178 @@
+use std::{io, path::PathBuf};
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use world::{
+    storage::{
+        journal,
+        vox::{self, ColorMapping},
+        RegionStore, WorldDir,
+    },
+    ChunkId, World, WorldPosition,
+};
+
+/**
+ * Parses a block position written as x,y,z.
+ */
+fn parse_position(value: &str) -> Result<WorldPosition, String> {
+    let coordinates = value
+        .split(',')
+        .map(|c| c.trim().parse::<i32>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match coordinates[..] {
+        [x, y, z] => Ok(WorldPosition::new(x, y, z)),
+        _ => Err(format!("expected x,y,z but got {}", value)),
+    }
+}
+
+fn position_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .long(name)
+        .required(true)
+        .help(help)
+        .allow_hyphen_values(true)
+        .value_parser(parse_position)
+}
+
+fn main() -> io::Result<()> {
+    let matches = Command::new("worldvox")
+        .about("Exports blocks of a saved world to a .vox model or imports a model into it")
+        .arg(
+            Arg::new("world")
+                .long("world")
+                .required(true)
+                .help("Directory of the world, the game must not have it open")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("export")
+                .about("Writes the blocks between min and max to a .vox model")
+                .arg(position_arg("min", "First block of the region as x,y,z"))
+                .arg(position_arg(
+                    "max",
+                    "Last block of the region as x,y,z, inclusive",
+                ))
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Places the first model of a .vox file into the world")
+                .arg(position_arg(
+                    "at",
+                    "Block of the minimum corner of the model as x,y,z",
+                ))
+                .arg(
+                    Arg::new("nearest-color")
+                        .long("nearest-color")
+                        .help("Map colors to the closest material instead of by palette index")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("input")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .get_matches();
+
+    let world_dir = WorldDir::new(matches.get_one::<PathBuf>("world").unwrap());
+    if !world_dir.exists() {
+        eprintln!("No world at {}", world_dir.path().display());
+        std::process::exit(1);
+    }
+
+    match matches.subcommand() {
+        Some(("export", matches)) => export(&world_dir, matches),
+        Some(("import", matches)) => import(&world_dir, matches),
+        _ => unreachable!(),
+    }
+}
+
+fn export(world_dir: &WorldDir, matches: &ArgMatches) -> io::Result<()> {
+    let min = matches.get_one::<WorldPosition>("min").unwrap();
+    let max = matches.get_one::<WorldPosition>("max").unwrap();
+    let output = matches.get_one::<PathBuf>("output").unwrap();
+
+    let mut world = open(world_dir)?;
+    load(&mut world, world_dir, min, max)?;
+
+    let data = vox::export(&world.chunk_manager, min, max)?;
+    println!("Writing {}...", output.display());
+    vox::write(output, &data)?;
+    println!("Done");
+    Ok(())
+}
+
+fn import(world_dir: &WorldDir, matches: &ArgMatches) -> io::Result<()> {
+    let at = matches.get_one::<WorldPosition>("at").unwrap();
+    let input = matches.get_one::<PathBuf>("input").unwrap();
+
+    let data = vox::read(input)?;
+    let Some(model) = data.models.first() else {
+        eprintln!("{} contains no models", input.display());
+        std::process::exit(1);
+    };
+    let mapping = if matches.get_flag("nearest-color") {
+        ColorMapping::nearest_color(&data.palette)
+    } else {
+        ColorMapping::by_material_id()
+    };
+
+    let size = &model.size;
+    let max = at + &WorldPosition::new(size.x as i32 - 1, size.y as i32 - 1, size.z as i32 - 1);
+    let mut world = open(world_dir)?;
+    load(&mut world, world_dir, at, &max)?;
+
+    let changed = vox::import(&mut world.chunk_manager, model, at, &mapping);
+    println!("Changed blocks in {} chunks", changed.len());
+
+    journal::write(&world_dir.journal_path(), world.chunk_manager.journal())?;
+    println!("Done");
+    Ok(())
+}
+
+/**
+ * World with the seed, generator config and edits of the saved world, no chunks are loaded.
+ */
+fn open(world_dir: &WorldDir) -> io::Result<World> {
+    let seed = world_dir.read_meta()?.world_seed();
+    let mut world = World::with_config(seed, world_dir.read_gen_config()?);
+    world
+        .chunk_manager
+        .set_journal(journal::read(&world_dir.journal_path())?);
+    Ok(world)
+}
+
+/**
+ * Loads the chunks covering the blocks between min and max (inclusive) like the game does,
+ * from their region or the generator with the journaled edits on top.
+ */
+fn load(
+    world: &mut World,
+    world_dir: &WorldDir,
+    min: &WorldPosition,
+    max: &WorldPosition,
+) -> io::Result<()> {
+    let store = RegionStore::new(world_dir.regions_path())?;
+    let (min, max) = (ChunkId::from(min), ChunkId::from(max));
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let id = ChunkId::new(x, y, z);
+                let mut data = match store.load(&id)? {
+                    Some(data) => data,
+                    None => world.generate_chunk(&id),
+                };
+                world.chunk_manager.journal().replay(&id, &mut data);
+                world.chunk_manager.insert(&id, data);
+            }
+        }
+    }
+    Ok(())
+}
@@ -2,6 +2,7 @@
 
 use std::env;
 use clap::Command;
+use gamedata::atlas::{Tile, TILE_SIZE};
 use gamedata::material::Material;
 use std::mem::variant_count;
 use xtaskops::ops::{clean_files};
@@ -10,12 +11,14 @@ use std::process::Command as Cmd;
 fn main() {
     let cli = Command::new("xtask")
         .subcommand(Command::new("palette"))
+        .subcommand(Command::new("atlas"))
         .subcommand(Command::new("clean"))
         .subcommand(Command::new("shader"));
 
     let matches = cli.get_matches();
     match matches.subcommand() {
         Some(("palette", _)) => generate_palette_file(),
+        Some(("atlas", _)) => generate_atlas_file(),
         Some(("clean", _)) => clean_project(),
         Some(("shader", _)) => compile_shaders(),
         _ => {}
@@ -43,6 +46,80 @@ fn generate_palette_file() {
     println!("Done");
 }
 
+fn generate_atlas_file() {
+    println!("Generating atlas...");
+    let width = Tile::ALL.len() * TILE_SIZE;
+    let mut pixels = vec![0; width * TILE_SIZE * 4];
+
+    for tile in Tile::ALL {
+        for y in 0..TILE_SIZE {
+            for x in 0..TILE_SIZE {
+                let i = (y * width + tile.index() * TILE_SIZE + x) * 4;
+                pixels[i..i + 4].copy_from_slice(&tile_pixel(tile, x, y));
+            }
+        }
+    }
+
+    resources::write_image("assets/atlas.png", &pixels, width, TILE_SIZE)
+        .expect("Failed to generate atlas");
+
+    println!("Done");
+}
+
+/**
+ * Hash of a pixel in 0..=1, the same for every run.
+ */
+fn noise(x: usize, y: usize, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(374_761_393)
+        ^ (y as u32).wrapping_mul(668_265_263)
+        ^ seed.wrapping_mul(2_246_822_519);
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    ((h ^ (h >> 16)) & 0xff) as f32 / 255.0
+}
+
+fn shade(color: [u8; 4], factor: f32) -> [u8; 4] {
+    let [r, g, b, a] = color;
+    let scale = |c: u8| (c as f32 * factor).min(255.0) as u8;
+    [scale(r), scale(g), scale(b), a]
+}
+
+/**
+ * Color of a pixel of a tile, row 0 is the top of the block.
+ */
+fn tile_pixel(tile: Tile, x: usize, y: usize) -> [u8; 4] {
+    let n = noise(x, y, tile.index() as u32);
+    match tile {
+        Tile::Stone => shade(palette::STONE, 0.85 + 0.3 * n),
+        Tile::Dirt => shade(palette::DIRT, 0.8 + 0.35 * n),
+        Tile::GrassTop => shade(palette::GRASS, 0.85 + 0.3 * n),
+        Tile::GrassSide => match y < 3 + (noise(x, 0, 99) * 3.0) as usize {
+            true => shade(palette::GRASS, 0.85 + 0.3 * n),
+            false => shade(palette::DIRT, 0.8 + 0.35 * n),
+        },
+        Tile::Sand => shade(palette::SAND, 0.92 + 0.16 * n),
+        Tile::Snow => shade(palette::WHITE, 0.9 + 0.1 * n),
+        Tile::Ice => match (x + y) % 7 == 0 {
+            true => [235, 245, 255, 255],
+            false => shade([180, 215, 250, 255], 0.9 + 0.1 * n),
+        },
+        Tile::WoodTop => {
+            let center = (TILE_SIZE as f32 - 1.0) / 2.0;
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            match distance as usize % 3 {
+                0 => shade(palette::WOOD, 0.7),
+                _ => shade(palette::WOOD, 1.1 + 0.1 * n),
+            }
+        }
+        Tile::WoodSide => shade(palette::WOOD, 0.7 + 0.3 * noise(x, 0, 7) + 0.1 * n),
+        // leaves are cut out, their gaps are fully transparent
+        Tile::Leaves => match n < 0.15 {
+            true => palette::TRANSPARENT,
+            false => shade(palette::LEAVES, 0.8 + 0.4 * n),
+        },
+        Tile::Gravel => shade(palette::GRAVEL, 0.75 + 0.45 * noise(x / 2, y / 2, 15)),
+    }
+}
+
 fn clean_project() {
     println!("Cleaning...");
     clean_files("**/gfxrecon_capture_*").expect("Cleaning GPU captures must succeed");
@@ -70,6 +147,18 @@ fn compile_shaders() {
         println!("Error in vertex shader\n{}", String::from_utf8(vert_err).unwrap());
     }
 
+    let smooth_vert_err = Cmd::new(&glsl_compile)
+        .current_dir(&shader_dir)
+        .arg("smooth.vert")
+        .arg("-o")
+        .arg("smooth_vert.spv")
+        .output()
+        .expect("Failed to compile vertex shader").stderr;
+
+    if !smooth_vert_err.is_empty() {
+        println!("Error in smooth vertex shader\n{}", String::from_utf8(smooth_vert_err).unwrap());
+    }
+
     let frag_err = Cmd::new(&glsl_compile)
         .current_dir(&shader_dir)
         .arg("shader.frag")
@@ -5,22 +5,50 @@ layout(push_constant) uniform PushConstants {
 } pcs;
 
 layout(binding = 1) uniform sampler2D palette;
+layout(binding = 2) uniform sampler2D atlas;
+
+// gamedata::atlas::TILE_SIZE
+const float TILE_SIZE = 16.0;
+const float ALPHA_CUTOFF = 0.1;
 
 layout(location = 0) in float fragMaterial;
 layout(location = 1) in vec3 fragNormal;
 layout(location = 2) in float fragDistance;
+layout(location = 3) in vec2 fragLight;
+layout(location = 4) in float fragAO;
+layout(location = 5) flat in vec3 fragMaterials;
+layout(location = 6) in vec3 fragBlend;
+layout(location = 7) in vec2 fragUV;
+layout(location = 8) flat in float fragTile;
 
 layout(location = 0) out vec4 outColor;
 
 void main() {
     // MATERIAL
-    vec4 material_color = textureLod(palette, vec2(fragMaterial + 0.5, 0.5), 0);
+    vec4 material_color = vec4(0.0);
+    if (fragTile >= 0.0) {
+        // repeat inside the tile, the atlas is sampled in pixels and its first row is the top
+        vec2 texel = vec2(fract(fragUV.x), 1.0 - fract(fragUV.y)) * TILE_SIZE;
+        texel = clamp(texel, 0.5, TILE_SIZE - 0.5);
+        material_color = textureLod(atlas, vec2(fragTile * TILE_SIZE, 0.0) + texel, 0);
+    } else {
+        for (int i = 0; i < 3; i++) {
+            material_color += fragBlend[i] * textureLod(palette, vec2(fragMaterials[i] + 0.5, 0.5), 0);
+        }
+    }
+    // cutout gaps, translucent materials stay well above this
+    if (material_color.a < ALPHA_CUTOFF) {
+        discard;
+    }
     
     // ILLUMINATION
     vec3 light = normalize(vec3(cos(pcs.time), sin(pcs.time), 2.0));
     float illumination = max(dot(fragNormal, light), 0.0);
     float ambient = 0.2;
-    vec4 illuminated_color = vec4(material_color.xyz * min(illumination + ambient, 1.0), material_color.w);
+    float sky_light = min(illumination + ambient, 1.0) * fragLight.x;
+    float occlusion = mix(0.4, 1.0, fragAO);
+    float brightness = max(max(sky_light, fragLight.y), 0.05) * occlusion;
+    vec4 illuminated_color = vec4(material_color.xyz * brightness, material_color.w);
     
     // DISTANCE FOG
     vec4 fog_background = vec4(0.5,0.5,0.5,1);
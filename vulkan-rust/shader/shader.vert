@@ -11,16 +11,40 @@ layout(push_constant) uniform PushConstants {
     float time;
 } pcs;
 
-layout(location = 0) in vec4 inPosMat;
-layout(location = 1) in vec3 inNormal;
+// graphics::PackedVertex, block vertices packed into two words
+layout(location = 0) in uvec2 inPacked;
+
+const vec3 NORMALS[6] = vec3[](
+    vec3(1, 0, 0), vec3(-1, 0, 0),
+    vec3(0, 1, 0), vec3(0, -1, 0),
+    vec3(0, 0, 1), vec3(0, 0, -1)
+);
+const float DROP_STEPS = 12.0;
+const float MAX_LIGHT = 15.0;
+const float MAX_AO = 3.0;
 
 layout(location = 0) out float fragMaterial;
 layout(location = 1) out vec3 fragNormal;
 layout(location = 2) out float fragDistance;
+layout(location = 3) out vec2 fragLight;
+layout(location = 4) out float fragAO;
+layout(location = 5) flat out vec3 fragMaterials;
+layout(location = 6) out vec3 fragBlend;
+layout(location = 7) out vec2 fragUV;
+layout(location = 8) flat out float fragTile;
 
 void main() {
-    vec3 pos = inPosMat.xyz;
-    float mat = inPosMat.w;
+    uint word0 = inPacked.x;
+    uint word1 = inPacked.y;
+    float drop = float(bitfieldExtract(word1, 29, 3)) / DROP_STEPS;
+    vec3 pos = vec3(bitfieldExtract(word0, 0, 7), bitfieldExtract(word0, 7, 7), bitfieldExtract(word0, 14, 7));
+    pos.z -= drop;
+    vec3 normal = NORMALS[bitfieldExtract(word0, 21, 3)];
+    float mat = float(bitfieldExtract(word0, 24, 8));
+    vec2 light = vec2(bitfieldExtract(word1, 14, 4), bitfieldExtract(word1, 18, 4)) / MAX_LIGHT;
+    float ao = float(bitfieldExtract(word1, 22, 2)) / MAX_AO;
+    vec2 uv = vec2(bitfieldExtract(word1, 0, 7), bitfieldExtract(word1, 7, 7));
+    float tile = float(bitfieldExtract(word1, 24, 5)) - 1.0;
     // TODO: need wind_affected property
     // vec3 disposition = (1 - inColor + vec4(37/255, 95/255, 36/255, 255/255)).xyz * vec3(sin(51 * pcs.time), cos(37 * pcs.time), 0) * 0.1;
     vec4 world_pos = pcs.model * vec4(pos, 1.0);
@@ -28,5 +52,11 @@ void main() {
 
     fragDistance = length(world_pos.xyz - ubo.player.xyz);
     fragMaterial = mat;
-    fragNormal = inNormal;
+    fragNormal = normal;
+    fragLight = light;
+    fragAO = ao;
+    fragMaterials = vec3(mat);
+    fragBlend = vec3(1.0, 0.0, 0.0);
+    fragUV = uv;
+    fragTile = tile;
 }
\ No newline at end of file